@@ -0,0 +1,313 @@
+//! Integers of unbounded size, for running programs with --bigint. Only the operations the
+//! interpreter needs are implemented, with the simple schoolbook algorithms.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// An integer of any size. The magnitude is stored in base 2^32, least significant digit
+/// first, without leading zero digits. Zero has no digits, and is not negative.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    /// Parse a decimal integer with an optional sign.
+    pub fn parse(s: &str) -> Option<BigInt> {
+        let (negative, decimals) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        if decimals.is_empty() {
+            return None;
+        }
+        let mut digits = Vec::new();
+        for c in decimals.chars() {
+            let decimal = c.to_digit(10)?;
+            multiply_add(&mut digits, 10, decimal);
+        }
+        Some(BigInt::new(negative, digits))
+    }
+
+    fn new(negative: bool, mut digits: Vec<u32>) -> BigInt {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BigInt { negative: negative && !digits.is_empty(), digits }
+    }
+
+    /// The integer as an i32, if it fits in one.
+    pub fn to_i32(&self) -> Option<i32> {
+        let magnitude = match self.digits.len() {
+            0 => 0,
+            1 => i64::from(self.digits[0]),
+            _ => return None,
+        };
+        let value = if self.negative { -magnitude } else { magnitude };
+        if value < i64::from(i32::MIN) || value > i64::from(i32::MAX) {
+            None
+        } else {
+            Some(value as i32)
+        }
+    }
+
+    /// The integer part of a finite float, rounded towards zero.
+    pub fn from_f64(r: f64) -> BigInt {
+        let r = r.trunc();
+        if r.abs() < 9.0e18 {
+            return BigInt::from(r as i64);
+        }
+        // A float this large is an integer, its 53-bit mantissa shifted left by the exponent.
+        let bits = r.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let mut digits = vec![mantissa as u32, (mantissa >> 32) as u32];
+        for _ in 0..exponent {
+            shift_left(&mut digits, 0);
+        }
+        BigInt::new(r < 0.0, digits)
+    }
+
+    /// The nearest float, or an infinity if the integer is too large.
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.digits.iter().rev().fold(0.0, |value, &digit| value * 4294967296.0 + f64::from(digit));
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitudes(&self.digits, &other.digits));
+        }
+        // The signs differ, so subtract the smaller magnitude from the larger one.
+        match compare_magnitudes(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::new(other.negative, subtract_magnitudes(&other.digits, &self.digits)),
+            _ => BigInt::new(self.negative, subtract_magnitudes(&self.digits, &other.digits)),
+        }
+    }
+
+    pub fn subtract(&self, other: &BigInt) -> BigInt {
+        self.add(&other.negate())
+    }
+
+    pub fn multiply(&self, other: &BigInt) -> BigInt {
+        let mut digits = vec![0; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let product = u64::from(a) * u64::from(b) + u64::from(digits[i + j]) + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigInt::new(self.negative != other.negative, digits)
+    }
+
+    /// The quotient rounded towards zero, and the remainder, which has the sign of the
+    /// dividend. The divisor must not be zero.
+    pub fn divide(&self, other: &BigInt) -> (BigInt, BigInt) {
+        assert!(!other.is_zero(), "division of a BigInt by zero");
+        let mut quotient = vec![0; self.digits.len()];
+        let mut remainder = Vec::new();
+        for bit in (0..self.digits.len() * 32).rev() {
+            shift_left(&mut remainder, (self.digits[bit / 32] >> (bit % 32)) & 1);
+            if compare_magnitudes(&remainder, &other.digits) != Ordering::Less {
+                remainder = subtract_magnitudes(&remainder, &other.digits);
+                quotient[bit / 32] |= 1 << (bit % 32);
+            }
+        }
+        (BigInt::new(self.negative != other.negative, quotient), BigInt::new(self.negative, remainder))
+    }
+
+    fn negate(&self) -> BigInt {
+        BigInt::new(!self.negative, self.digits.clone())
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(i: i64) -> BigInt {
+        let magnitude = i.unsigned_abs();
+        BigInt::new(i < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.digits, &other.digits),
+            (true, true) => compare_magnitudes(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Split the magnitude into groups of nine decimals, least significant first.
+        let mut digits = self.digits.clone();
+        let mut groups = Vec::new();
+        while !digits.is_empty() {
+            groups.push(divide_small(&mut digits, 1_000_000_000));
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut groups = groups.iter().rev();
+        if let Some(first) = groups.next() {
+            write!(f, "{}", first)?;
+        }
+        for group in groups {
+            write!(f, "{:09}", group)?;
+        }
+        Ok(())
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let digit = u64::from(*a.get(i).unwrap_or(&0)) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+        sum.push(digit as u32);
+        carry = digit >> 32;
+    }
+    sum.push(carry as u32);
+    sum
+}
+
+/// Subtract the magnitude b from the magnitude a, which must not be smaller.
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &digit) in a.iter().enumerate() {
+        let subtrahend = i64::from(*b.get(i).unwrap_or(&0)) + borrow;
+        let mut digit = i64::from(digit) - subtrahend;
+        borrow = 0;
+        if digit < 0 {
+            digit += 1 << 32;
+            borrow = 1;
+        }
+        difference.push(digit as u32);
+    }
+    while difference.last() == Some(&0) {
+        difference.pop();
+    }
+    difference
+}
+
+/// Shift the magnitude left by one bit, shifting in the bit.
+fn shift_left(digits: &mut Vec<u32>, bit: u32) {
+    let mut carry = bit;
+    for digit in digits.iter_mut() {
+        let next = *digit >> 31;
+        *digit = (*digit << 1) | carry;
+        carry = next;
+    }
+    if carry != 0 {
+        digits.push(carry);
+    }
+}
+
+/// Multiply the magnitude by a small factor and add a small term to it.
+fn multiply_add(digits: &mut Vec<u32>, factor: u32, term: u32) {
+    let mut carry = u64::from(term);
+    for digit in digits.iter_mut() {
+        let value = u64::from(*digit) * u64::from(factor) + carry;
+        *digit = value as u32;
+        carry = value >> 32;
+    }
+    if carry != 0 {
+        digits.push(carry as u32);
+    }
+}
+
+/// Divide the magnitude by a small divisor in place, and return the remainder.
+fn divide_small(digits: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0;
+    for digit in digits.iter_mut().rev() {
+        let value = (remainder << 32) | u64::from(*digit);
+        *digit = (value / u64::from(divisor)) as u32;
+        remainder = value % u64::from(divisor);
+    }
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    remainder as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        BigInt::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parsing_and_printing() {
+        for s in ["0", "7", "-7", "4294967296", "-18446744073709551616", "1000000000000000000000000000001"].iter() {
+            assert_eq!(big(s).to_string(), *s);
+        }
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("+0012").to_string(), "12");
+        assert_eq!(BigInt::parse("1a"), None);
+        assert_eq!(BigInt::parse("-"), None);
+        assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(big("2147483647").to_i32(), Some(i32::MAX));
+        assert_eq!(big("-2147483648").to_i32(), Some(i32::MIN));
+        assert_eq!(big("2147483648").to_i32(), None);
+        assert_eq!(big("-4294967296").to_i32(), None);
+        assert_eq!(big("-12345678901234567890").to_f64(), -12345678901234567890.0);
+        assert_eq!(BigInt::from_f64(-3.9), big("-3"));
+        assert_eq!(BigInt::from_f64(1e20), big("100000000000000000000"));
+        assert_eq!(BigInt::from_f64(-2f64.powi(70)), big("-1180591620717411303424"));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432");
+        assert_eq!(a.add(&b).to_string(), "123456789011358024580135802458");
+        assert_eq!(b.add(&a).to_string(), "123456789011358024580135802458");
+        assert_eq!(b.subtract(&a).to_string(), "-123456789013333333222333333322");
+        assert_eq!(a.multiply(&b).to_string(), "-121932631137021795212620027521140070120989178480");
+        assert_eq!(a.subtract(&a), BigInt::from(0));
+        let (quotient, remainder) = a.divide(&b);
+        assert_eq!(quotient.to_string(), "-124999998860");
+        assert_eq!(remainder.to_string(), "925925953827160370");
+        let (quotient, remainder) = BigInt::from(-7).divide(&BigInt::from(2));
+        assert_eq!((quotient, remainder), (BigInt::from(-3), BigInt::from(-1)));
+    }
+
+    #[test]
+    fn ordering() {
+        let mut numbers = [big("4294967296"), big("-1"), big("0"), big("-4294967296"), big("3")];
+        numbers.sort();
+        let numbers: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+        assert_eq!(numbers, vec!["-4294967296", "-1", "0", "3", "4294967296"]);
+    }
+}
@@ -0,0 +1,475 @@
+//! Control-flow graphs. The statements of the program, and of each procedure and function, are
+//! split into basic blocks: sequences of instructions that run from the first to the last one
+//! when the block is entered. The edges between the blocks follow the branches of if
+//! statements, the loops with their back edges and exits, and break, continue and return.
+//!
+//! A for loop is split into its start, which evaluates the range and assigns the beginning to
+//! the control variable, a header that tests whether the control variable is still within the
+//! range, and a latch that steps it before the back edge to the header.
+
+use super::parser::ast::*;
+use super::parser::printer::type_name;
+
+use std::fmt;
+use std::fmt::Write;
+
+/// The graph of a program, or of a procedure or function.
+pub struct Cfg<'a> {
+    pub name: String, // "program", or the procedure or function
+    pub blocks: Vec<Block<'a>>, // the entry is the first block, and the exit the second one
+}
+
+pub const ENTRY: usize = 0;
+pub const EXIT: usize = 1;
+
+/// A basic block and the edges leaving it.
+#[derive(Default)]
+pub struct Block<'a> {
+    pub instructions: Vec<Instruction<'a>>,
+    pub successors: Vec<(usize, Edge)>,
+}
+
+/// An instruction of a basic block.
+#[derive(Clone, Copy)]
+pub enum Instruction<'a> {
+    Statement(&'a Statement), // a statement without control flow, or a return
+    Condition(&'a Expression), // the condition of an if or while, which chooses the edge taken
+    ForStart(&'a Statement), // the evaluation of the range of a for loop and its first value
+    ForTest(&'a Statement), // whether the control variable is within the range
+    ForStep(&'a Statement), // the step of the control variable to its next value
+}
+
+/// The kinds of edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Next, // falls through to the next block
+    True, // the condition or the test holds
+    False, // the condition or the test does not hold, which exits a loop
+    Back, // from the end of a loop body back to its header
+    Break,
+    Return,
+}
+
+/// Build the graphs of a checked AST: first the one of the program, then the ones of the
+/// procedures and functions in the order of their declarations.
+pub fn build(ast: &Ast) -> Vec<Cfg<'_>> {
+    let mut graphs = vec![Builder::graph("program".to_string(), &ast.statements)];
+    for statement in &ast.statements {
+        if let Statement::Function(ref function) = statement.node {
+            graphs.push(Builder::graph(function.identifier.clone(), &function.statements));
+        }
+    }
+    graphs
+}
+
+/// The builder holds the blocks built so far, and the targets of continue and break in the
+/// enclosing loops.
+struct Builder<'a> {
+    blocks: Vec<Block<'a>>,
+    loops: Vec<((usize, Edge), usize)>, // the target and edge of a continue, and the target of a break
+}
+
+impl<'a> Builder<'a> {
+    fn graph(name: String, statements: &'a [Located<Statement>]) -> Cfg<'a> {
+        let mut builder = Builder { blocks: vec![Block::default(), Block::default()], loops: Vec::new() };
+        let last = builder.statements(statements, ENTRY);
+        builder.edge(last, EXIT, Edge::Next);
+        Cfg { name, blocks: builder.prune() }
+    }
+
+    fn block(&mut self) -> usize {
+        self.blocks.push(Block::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, edge: Edge) {
+        self.blocks[from].successors.push((to, edge));
+    }
+
+    fn push(&mut self, block: usize, instruction: Instruction<'a>) {
+        self.blocks[block].instructions.push(instruction);
+    }
+
+    /// Add the statements to the graph, starting in the block. Returns the block where the
+    /// control continues after them.
+    fn statements(&mut self, statements: &'a [Located<Statement>], mut current: usize) -> usize {
+        for statement in statements {
+            current = self.statement(&statement.node, current);
+        }
+        current
+    }
+
+    fn statement(&mut self, statement: &'a Statement, current: usize) -> usize {
+        match *statement {
+            Statement::If { ref condition, ref statements, ref else_statements } => {
+                self.push(current, Instruction::Condition(condition));
+                let (then_block, join) = (self.block(), self.block());
+                self.edge(current, then_block, Edge::True);
+                let then_end = self.statements(statements, then_block);
+                self.edge(then_end, join, Edge::Next);
+                if else_statements.is_empty() {
+                    self.edge(current, join, Edge::False);
+                } else {
+                    let else_block = self.block();
+                    self.edge(current, else_block, Edge::False);
+                    let else_end = self.statements(else_statements, else_block);
+                    self.edge(else_end, join, Edge::Next);
+                }
+                join
+            },
+            Statement::While { ref condition, ref statements } => {
+                let (header, body, after) = (self.block(), self.block(), self.block());
+                self.edge(current, header, Edge::Next);
+                self.push(header, Instruction::Condition(condition));
+                self.edge(header, body, Edge::True);
+                self.edge(header, after, Edge::False);
+                self.loops.push(((header, Edge::Back), after));
+                let end = self.statements(statements, body);
+                self.loops.pop();
+                self.edge(end, header, Edge::Back);
+                after
+            },
+            Statement::For { ref statements, .. } => {
+                self.push(current, Instruction::ForStart(statement));
+                let (header, body, latch, after) = (self.block(), self.block(), self.block(), self.block());
+                self.edge(current, header, Edge::Next);
+                self.push(header, Instruction::ForTest(statement));
+                self.edge(header, body, Edge::True);
+                self.edge(header, after, Edge::False);
+                self.loops.push(((latch, Edge::Next), after));
+                let end = self.statements(statements, body);
+                self.loops.pop();
+                self.edge(end, latch, Edge::Next);
+                self.push(latch, Instruction::ForStep(statement));
+                self.edge(latch, header, Edge::Back);
+                after
+            },
+            // The checker allows break and continue only in loops.
+            Statement::Break => {
+                if let Some(&(_, after)) = self.loops.last() {
+                    self.edge(current, after, Edge::Break);
+                }
+                self.block()
+            },
+            Statement::Continue => {
+                if let Some(&((target, edge), _)) = self.loops.last() {
+                    self.edge(current, target, edge);
+                }
+                self.block()
+            },
+            Statement::Return(_) => {
+                self.push(current, Instruction::Statement(statement));
+                self.edge(current, EXIT, Edge::Return);
+                self.block()
+            },
+            // Procedures and functions have graphs of their own.
+            Statement::Function(_) | Statement::Empty => current,
+            Statement::Declaration { .. } | Statement::Constant { .. } | Statement::Assignment { .. }
+            | Statement::Call { .. } | Statement::Read { .. } | Statement::Print(_) | Statement::Assert(_) => {
+                self.push(current, Instruction::Statement(statement));
+                current
+            },
+        }
+    }
+
+    /// Remove the blocks that cannot be reached from the entry, nor from a block with
+    /// instructions, such as the empty ones started after a break, and number the rest again.
+    /// The unreachable blocks with instructions are kept to show the dead code, together with
+    /// the blocks they lead to, so that every edge has a target.
+    fn prune(self) -> Vec<Block<'a>> {
+        let mut keep = vec![false; self.blocks.len()];
+        let mut stack: Vec<usize> = self.blocks.iter().enumerate()
+            .filter(|&(i, block)| i == ENTRY || i == EXIT || !block.instructions.is_empty())
+            .map(|(i, _)| i)
+            .collect();
+        while let Some(block) = stack.pop() {
+            if !keep[block] {
+                keep[block] = true;
+                stack.extend(self.blocks[block].successors.iter().map(|&(to, _)| to));
+            }
+        }
+        let mut numbers = Vec::with_capacity(keep.len());
+        let mut next = 0;
+        for &kept in &keep {
+            numbers.push(next);
+            if kept {
+                next += 1;
+            }
+        }
+        self.blocks.into_iter().zip(keep).filter(|&(_, kept)| kept)
+            .map(|(mut block, _)| {
+                for successor in &mut block.successors {
+                    successor.0 = numbers[successor.0];
+                }
+                block
+            })
+            .collect()
+    }
+}
+
+impl<'a> Cfg<'a> {
+    /// The graph in the DOT language of Graphviz. The nodes are named by the graph and the
+    /// number of the block, so the graphs of a program can be drawn together as clusters.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let id = |block: usize| format!("\"{}.{}\"", self.name, block);
+        writeln!(dot, "  subgraph \"cluster_{}\" {{", self.name).unwrap();
+        writeln!(dot, "    label=\"{}\";", escape(&self.name)).unwrap();
+        for (i, block) in self.blocks.iter().enumerate() {
+            let mut label = format!("{}\\l", block_name(i));
+            for instruction in &block.instructions {
+                label.push_str(&escape(&instruction.to_string()));
+                label.push_str("\\l");
+            }
+            writeln!(dot, "    {} [label=\"{}\"];", id(i), label).unwrap();
+        }
+        for (i, block) in self.blocks.iter().enumerate() {
+            for &(to, edge) in &block.successors {
+                match edge {
+                    Edge::Next => writeln!(dot, "    {} -> {};", id(i), id(to)).unwrap(),
+                    Edge::Back => writeln!(dot, "    {} -> {} [label=\"back\", style=dashed];", id(i), id(to)).unwrap(),
+                    edge => writeln!(dot, "    {} -> {} [label=\"{}\"];", id(i), id(to), edge).unwrap(),
+                }
+            }
+        }
+        dot.push_str("  }\n");
+        dot
+    }
+}
+
+/// The graphs of a program in the DOT language.
+pub fn to_dot(graphs: &[Cfg]) -> String {
+    let mut dot = "digraph cfg {\n  node [shape=box, fontname=monospace];\n".to_string();
+    for graph in graphs {
+        dot.push_str(&graph.to_dot());
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Escape a string for a quoted DOT label.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn block_name(block: usize) -> String {
+    match block {
+        ENTRY => "B0 (entry)".to_string(),
+        EXIT => "B1 (exit)".to_string(),
+        block => format!("B{}", block),
+    }
+}
+
+impl<'a> fmt::Display for Cfg<'a> {
+    /// The blocks with their instructions, each followed by its successors.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        for (i, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "  {}", block_name(i))?;
+            for instruction in &block.instructions {
+                writeln!(f, "    {}", instruction)?;
+            }
+            let successors: Vec<String> = block.successors.iter().map(|&(to, edge)| match edge {
+                Edge::Next => format!("B{}", to),
+                edge => format!("B{} [{}]", to, edge),
+            }).collect();
+            if !successors.is_empty() {
+                writeln!(f, "    -> {}", successors.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Edge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Edge::Next => "next",
+            Edge::True => "true",
+            Edge::False => "false",
+            Edge::Back => "back",
+            Edge::Break => "break",
+            Edge::Return => "return",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl<'a> fmt::Display for Instruction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Statement(statement) => write_statement(f, statement),
+            Instruction::Condition(condition) => write!(f, "{}", condition),
+            Instruction::ForStart(Statement::For { identifier, begin, .. }) => write!(f, "{} := {}", identifier, begin),
+            Instruction::ForTest(&Statement::For { ref identifier, ref end, descending, .. }) => {
+                let operator = if descending { Operator::GreaterEqual } else { Operator::LessEqual };
+                write!(f, "{} {} {}", identifier, operator, end)
+            },
+            Instruction::ForStep(&Statement::For { ref identifier, descending, ref step, .. }) => {
+                let operator = if descending { Operator::Minus } else { Operator::Plus };
+                match *step {
+                    Some(ref step) => write!(f, "{} := {} {} {}", identifier, identifier, operator, step),
+                    None => write!(f, "{} := {} {} 1", identifier, identifier, operator),
+                }
+            },
+            Instruction::ForStart(_) | Instruction::ForTest(_) | Instruction::ForStep(_)
+                => unreachable!("for loop instructions are made of for loops"),
+        }
+    }
+}
+
+/// Write a statement without control flow on one line.
+fn write_statement(f: &mut fmt::Formatter, statement: &Statement) -> fmt::Result {
+    let target = |identifier: &str, index: &Option<Expression>| match *index {
+        Some(ref index) => format!("{}[{}]", identifier, index),
+        None => identifier.to_string(),
+    };
+    match *statement {
+        Statement::Declaration { ref identifier, ref mpl_type, value: Some(ref value) }
+            => write!(f, "var {} : {} := {}", identifier, type_name(mpl_type), value),
+        Statement::Declaration { ref identifier, ref mpl_type, value: None } => write!(f, "var {} : {}", identifier, type_name(mpl_type)),
+        Statement::Constant { ref identifier, ref mpl_type, ref value } => write!(f, "const {} : {} := {}", identifier, type_name(mpl_type), value),
+        Statement::Assignment { ref identifier, ref index, ref expression }
+            => write!(f, "{} := {}", target(identifier, index), expression),
+        Statement::Call { ref identifier, ref arguments } => {
+            let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+            write!(f, "{}({})", identifier, arguments.join(", "))
+        },
+        Statement::Read { ref identifier, ref index } => write!(f, "read {}", target(identifier, index)),
+        Statement::Print(ref expr) => write!(f, "print {}", expr),
+        Statement::Assert(ref expr) => write!(f, "assert ({})", expr),
+        Statement::Return(Some(ref value)) => write!(f, "return {}", value),
+        Statement::Return(None) => write!(f, "return"),
+        _ => unreachable!("only statements without control flow are instructions"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{checker, parser, scanner};
+
+    fn cfg_source(source: &str) -> Vec<String> {
+        let ast = parser::parse(scanner::scan(source, false).unwrap()).unwrap();
+        checker::check(&ast).unwrap();
+        build(&ast).iter().map(|graph| graph.to_string()).collect()
+    }
+
+    #[test]
+    fn branches_and_while_loops() {
+        let source = "var x : int;\nread x;\nwhile x > 0 do\n  if x = 5 then break; end if;\n  x := x - 1;\nend while;\n\
+                      if x = 5 then print \"five\"; else print \"zero\"; end if;\n";
+        assert_eq!(cfg_source(source), vec!["\
+program
+  B0 (entry)
+    var x : int
+    read x
+    -> B2
+  B1 (exit)
+  B2
+    x > 0
+    -> B3 [true], B4 [false]
+  B3
+    x = 5
+    -> B5 [true], B6 [false]
+  B4
+    x = 5
+    -> B7 [true], B9 [false]
+  B5
+    -> B4 [break]
+  B6
+    x := x - 1
+    -> B2 [back]
+  B7
+    print \"five\"
+    -> B8
+  B8
+    -> B1
+  B9
+    print \"zero\"
+    -> B8
+"]);
+    }
+
+    #[test]
+    fn for_loops_and_functions() {
+        let source = "var i : int;\nfor i in 10 downto 1 step 3 do\n  if i = 4 then continue; end if;\n  print i;\nend for;\n\
+                      function f(n : int) : int do\n  return n;\n  print n;\nend function;\n";
+        assert_eq!(cfg_source(source), vec!["\
+program
+  B0 (entry)
+    var i : int
+    i := 10
+    -> B2
+  B1 (exit)
+  B2
+    i >= 1
+    -> B3 [true], B5 [false]
+  B3
+    i = 4
+    -> B6 [true], B7 [false]
+  B4
+    i := i - 3
+    -> B2 [back]
+  B5
+    -> B1
+  B6
+    -> B4
+  B7
+    print i
+    -> B4
+", "\
+f
+  B0 (entry)
+    return n
+    -> B1 [return]
+  B1 (exit)
+  B2
+    print n
+    -> B1
+"]);
+    }
+
+    #[test]
+    fn statements_after_return_in_if() {
+        let source = "function f(n : int) : int do\n  if n > 0 then\n    return n;\n    if n = 1 then print n; end if;\n  end if;\n\
+                      return 0;\nend function;\n";
+        assert_eq!(cfg_source(source)[1], "\
+f
+  B0 (entry)
+    n > 0
+    -> B2 [true], B3 [false]
+  B1 (exit)
+  B2
+    return n
+    -> B1 [return]
+  B3
+    return 0
+    -> B1 [return]
+  B4
+    n = 1
+    -> B5 [true], B6 [false]
+  B5
+    print n
+    -> B6
+  B6
+    -> B3
+");
+    }
+
+    #[test]
+    fn dot() {
+        let ast = parser::parse(scanner::scan("print \"a \\\"b\\\"\";\n", false).unwrap()).unwrap();
+        assert_eq!(to_dot(&build(&ast)), "\
+digraph cfg {
+  node [shape=box, fontname=monospace];
+  subgraph \"cluster_program\" {
+    label=\"program\";
+    \"program.0\" [label=\"B0 (entry)\\lprint \\\"a \\\\\\\"b\\\\\\\"\\\"\\l\"];
+    \"program.1\" [label=\"B1 (exit)\\l\"];
+    \"program.0\" -> \"program.1\";
+  }
+}
+");
+    }
+}
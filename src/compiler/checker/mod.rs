@@ -0,0 +1,652 @@
+//! Static semantic analysis. Checks that identifiers are declared before they are used, and
+//! that every expression and statement is well typed, before the program is run.
+//!
+//! Variables are lexically scoped. The body of a loop, a branch of an if statement and the
+//! body of a procedure or function are blocks, and a variable declared in a block can only be
+//! used in the rest of that block. A declaration may not shadow a variable that is visible at
+//! that point, so an identifier always refers to the same variable within a block. Blocks that
+//! do not nest can declare the same identifier independently.
+//!
+//! Procedures and functions are declared at the top level of the program, and can be called
+//! anywhere in it, also before their declaration. The body of a procedure or function can only
+//! use its parameters and its own variables. The builtin functions, such as length and
+//! substring, can be called anywhere, and their identifiers cannot be declared again.
+
+use super::parser::ast::*;
+use super::interpreter::builtins;
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem;
+
+/// Check the AST. Returns all errors found, one per line, each prefixed by the position of the
+/// statement it was found in.
+pub fn check(ast: &Ast) -> Result<(), String> {
+    types(ast).map(|_| ())
+}
+
+/// Check the AST like `check`, and return the types of its expressions and operands.
+pub fn types(ast: &Ast) -> Result<Types<'_>, String> {
+    let mut checker = Checker {
+        scopes: vec![HashMap::new()],
+        functions: HashMap::new(),
+        returns: None,
+        loops: 0,
+        errors: Vec::new(),
+        position: Position::default(),
+        expression_types: HashMap::new(),
+        operand_types: HashMap::new(),
+    };
+    checker.declare_functions(&ast.statements);
+    checker.check_statements(&ast.statements);
+    if checker.errors.is_empty() {
+        Ok(Types { expressions: checker.expression_types, operands: checker.operand_types, ast: PhantomData })
+    } else {
+        Err(checker.errors.join("\n"))
+    }
+}
+
+/// The types of the expressions and operands of a checked AST. They are found by the address
+/// of the node, so the AST is borrowed as long as the types are used.
+pub struct Types<'a> {
+    expressions: HashMap<*const Expression, MplType>,
+    operands: HashMap<*const Operand, MplType>,
+    ast: PhantomData<&'a Ast>,
+}
+
+impl<'a> Types<'a> {
+    pub fn expression(&self, expr: &Expression) -> Option<&MplType> {
+        self.expressions.get(&(expr as *const Expression))
+    }
+
+    pub fn operand(&self, operand: &Operand) -> Option<&MplType> {
+        self.operands.get(&(operand as *const Operand))
+    }
+}
+
+/// The parameter types and the return type of a procedure or function.
+#[derive(Clone)]
+struct Signature {
+    parameters: Vec<MplType>,
+    return_type: Option<MplType>, // None for a procedure
+}
+
+/// A declared variable, constant or parameter.
+struct Variable {
+    mpl_type: MplType,
+    position: Position, // position of the declaration
+    constant: bool,
+}
+
+/// The checker holds the chain of scopes of declared variables, the signatures of the
+/// procedures and functions, the context of the statement being checked, and the errors found
+/// so far.
+struct Checker {
+    scopes: Vec<HashMap<String, Variable>>, // innermost scope last
+    functions: HashMap<String, Signature>,
+    returns: Option<Option<MplType>>, // return type of the enclosing procedure or function, if any
+    loops: usize,
+    errors: Vec<String>,
+    position: Position, // position of the statement being checked
+    expression_types: HashMap<*const Expression, MplType>,
+    operand_types: HashMap<*const Operand, MplType>,
+}
+
+impl Checker {
+    fn error(&mut self, message: String) {
+        self.errors.push(format!("{}: {}", self.position, message));
+    }
+
+    /// Collect the signatures of the procedures and functions declared at the top level.
+    fn declare_functions(&mut self, statements: &[Located<Statement>]) {
+        for statement in statements {
+            if let Statement::Function(ref function) = statement.node {
+                self.position = statement.position;
+                let signature = Signature {
+                    parameters: function.parameters.iter().map(|p| p.mpl_type.clone()).collect(),
+                    return_type: function.return_type.clone(),
+                };
+                if builtins::builtin(&function.identifier).is_some() {
+                    self.error(format!("{} is a builtin function", function.identifier));
+                } else if self.functions.insert(function.identifier.clone(), signature).is_some() {
+                    self.error(format!("Procedure or function {} is already declared", function.identifier));
+                }
+            }
+        }
+    }
+
+    fn check_statements(&mut self, statements: &[Located<Statement>]) {
+        for statement in statements {
+            self.position = statement.position;
+            self.check_statement(&statement.node);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match *statement {
+            Statement::Declaration { ref identifier, ref mpl_type, ref value } => {
+                self.check_redeclaration(identifier);
+                if let Some(ref expr) = *value {
+                    self.expect_type(expr, mpl_type, "Initial value");
+                }
+                let variable = Variable { mpl_type: mpl_type.clone(), position: self.position, constant: false };
+                self.scopes.last_mut().expect("there is always a scope").insert(identifier.clone(), variable);
+            },
+            Statement::Constant { ref identifier, ref mpl_type, ref value } => {
+                self.check_redeclaration(identifier);
+                if let MplType::Array { .. } = *mpl_type {
+                    self.error(format!("Constant {} cannot be an array", identifier));
+                }
+                self.expect_type(value, mpl_type, &format!("Value of constant {}", identifier));
+                if !self.is_constant_expression(value) {
+                    self.error(format!("Value of constant {} must be known at compile time", identifier));
+                }
+                let variable = Variable { mpl_type: mpl_type.clone(), position: self.position, constant: true };
+                self.scopes.last_mut().expect("there is always a scope").insert(identifier.clone(), variable);
+            },
+            Statement::Assignment { ref identifier, ref index, ref expression } => {
+                if index.is_none() && self.is_constant(identifier) {
+                    self.error(format!("Cannot assign to constant {}", identifier));
+                }
+                if let Some(mpl_type) = self.target_type(identifier, index.as_ref()) {
+                    let target = match *index {
+                        Some(_) => format!("an element of {}", identifier),
+                        None => identifier.clone(),
+                    };
+                    self.expect_type(expression, &mpl_type, &format!("Value assigned to {}", target));
+                }
+            },
+            Statement::For { ref identifier, ref begin, ref end, ref step, ref statements, .. } => {
+                if self.is_constant(identifier) {
+                    self.error(format!("Constant {} cannot be the control variable of a for loop", identifier));
+                }
+                if let Some(mpl_type) = self.identifier_type(identifier) {
+                    if mpl_type != MplType::Int {
+                        self.error(format!("Control variable {} must be Integer, not {}", identifier, mpl_type));
+                    }
+                }
+                self.expect_type(begin, &MplType::Int, "Start of range");
+                self.expect_type(end, &MplType::Int, "End of range");
+                if let Some(ref step) = *step {
+                    self.expect_type(step, &MplType::Int, "Step of range");
+                }
+                self.check_loop(statements);
+            },
+            Statement::If { ref condition, ref statements, ref else_statements } => {
+                self.expect_type(condition, &MplType::Bool, "Condition of if");
+                self.check_block(statements);
+                self.check_block(else_statements);
+            },
+            Statement::While { ref condition, ref statements } => {
+                self.expect_type(condition, &MplType::Bool, "Condition of while");
+                self.check_loop(statements);
+            },
+            Statement::Break if self.loops == 0 => self.error("Break outside of a loop".to_string()),
+            Statement::Continue if self.loops == 0 => self.error("Continue outside of a loop".to_string()),
+            Statement::Break | Statement::Continue => (),
+            Statement::Function(ref function) if self.scopes.len() > 1 || self.returns.is_some()
+                => self.error(format!("{} must be declared at the top level", function.identifier)),
+            Statement::Function(ref function) => self.check_function(function),
+            Statement::Call { ref identifier, ref arguments } => { self.check_call(identifier, arguments); },
+            Statement::Return(ref value) => match (self.returns.clone(), value.as_ref()) {
+                (None, _) => self.error("Return outside of a procedure or function".to_string()),
+                (Some(None), Some(_)) => self.error("A procedure cannot return a value".to_string()),
+                (Some(Some(mpl_type)), None) => self.error(format!("Return must have a value of type {}", mpl_type)),
+                (Some(Some(mpl_type)), Some(expr)) => self.expect_type(expr, &mpl_type, "Return value"),
+                (Some(None), None) => (),
+            },
+            Statement::Read { ref identifier, ref index } => {
+                if index.is_none() && self.is_constant(identifier) {
+                    self.error(format!("Cannot read into constant {}", identifier));
+                }
+                if let Some(mpl_type @ MplType::Array { .. }) = self.target_type(identifier, index.as_ref()) {
+                    self.error(format!("Cannot read into {} of type {}", identifier, mpl_type));
+                }
+            },
+            Statement::Print(ref expr) => { self.expression_type(expr); },
+            Statement::Assert(ref expr) => self.expect_type(expr, &MplType::Bool, "Assertion"),
+            Statement::Empty => (),
+        }
+    }
+
+    /// Check the statements of a block in a new scope.
+    fn check_block(&mut self, statements: &[Located<Statement>]) {
+        self.scopes.push(HashMap::new());
+        self.check_statements(statements);
+        self.scopes.pop();
+    }
+
+    /// Report an error if the identifier is already declared in a visible scope: in the
+    /// innermost scope the declarations conflict, and in an enclosing scope the new declaration
+    /// would shadow it.
+    fn check_redeclaration(&mut self, identifier: &str) {
+        let innermost = self.scopes.len() - 1;
+        let declared = self.scopes.iter().enumerate().rev()
+            .find_map(|(i, scope)| scope.get(identifier).map(|variable| (i, variable.position)));
+        match declared {
+            Some((i, _)) if i == innermost => self.error(format!("Identifier {} is already declared", identifier)),
+            Some((_, position)) => self.error(format!("Identifier {} shadows the declaration at {}", identifier, position)),
+            None => (),
+        }
+    }
+
+    fn check_loop(&mut self, statements: &[Located<Statement>]) {
+        self.loops += 1;
+        self.check_block(statements);
+        self.loops -= 1;
+    }
+
+    /// Check the body of a procedure or function, in an environment of its parameters.
+    fn check_function(&mut self, function: &Function) {
+        let position = self.position;
+        let mut parameters = HashMap::new();
+        for parameter in &function.parameters {
+            let variable = Variable { mpl_type: parameter.mpl_type.clone(), position, constant: false };
+            if parameters.insert(parameter.identifier.clone(), variable).is_some() {
+                self.error(format!("Parameter {} of {} is declared twice", parameter.identifier, function.identifier));
+            }
+        }
+
+        let globals = mem::replace(&mut self.scopes, vec![parameters]);
+        self.returns = Some(function.return_type.clone());
+        self.check_block(&function.statements);
+        self.returns = None;
+        self.scopes = globals;
+
+        self.position = position;
+        if function.return_type.is_some() && !always_returns(&function.statements) {
+            self.error(format!("Function {} may end without returning a value", function.identifier));
+        }
+    }
+
+    /// Check the arguments of a call against the signature of the called procedure or
+    /// function. Returns the signature, or None if it is not declared.
+    fn check_call(&mut self, identifier: &str, arguments: &[Expression]) -> Option<Signature> {
+        let signature = match builtins::builtin(identifier) {
+            Some(builtin) => Some(Signature {
+                parameters: builtin.parameters.to_vec(),
+                return_type: Some(builtin.return_type.clone()),
+            }),
+            None => self.functions.get(identifier).cloned(),
+        };
+        match signature {
+            Some(ref signature) => {
+                if arguments.len() != signature.parameters.len() {
+                    self.error(format!(
+                        "{} expects {} arguments, got {}",
+                        identifier, signature.parameters.len(), arguments.len(),
+                    ));
+                }
+                for (i, (argument, parameter)) in arguments.iter().zip(&signature.parameters).enumerate() {
+                    self.expect_type(argument, parameter, &format!("Argument {} of {}", i + 1, identifier));
+                }
+            },
+            None => {
+                self.error(format!("Procedure or function {} is not declared", identifier));
+                for argument in arguments {
+                    self.expression_type(argument);
+                }
+            },
+        }
+        signature
+    }
+
+    /// Report an error if the expression is well typed, but not of the expected type. `what`
+    /// describes the expression in the error message.
+    fn expect_type(&mut self, expr: &Expression, expected: &MplType, what: &str) {
+        match self.expression_type(expr) {
+            Some(ref found) if found != expected
+                => self.error(format!("{} must be {}, not {}", what, expected, found)),
+            _ => (),
+        }
+    }
+
+    /// The type of a declared identifier. Reports an error if it is not declared in a visible
+    /// scope.
+    fn identifier_type(&mut self, identifier: &str) -> Option<MplType> {
+        let mpl_type = self.scopes.iter().rev()
+            .find_map(|scope| scope.get(identifier))
+            .map(|variable| variable.mpl_type.clone());
+        if mpl_type.is_none() {
+            self.error(format!("Identifier {} used before declaration", identifier));
+        }
+        mpl_type
+    }
+
+    /// Whether the identifier is a declared constant.
+    fn is_constant(&self, identifier: &str) -> bool {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(identifier))
+            .is_some_and(|variable| variable.constant)
+    }
+
+    /// Whether the value of the expression can be computed at compile time. It may use
+    /// literals, constants, operators and builtin functions.
+    fn is_constant_expression(&self, expr: &Expression) -> bool {
+        match *expr {
+            Expression::Simple(ref operand) | Expression::Unary { ref operand, .. } => self.is_constant_operand(operand),
+            Expression::Binary { ref left, ref right, .. }
+                => self.is_constant_operand(left) && self.is_constant_operand(right),
+        }
+    }
+
+    fn is_constant_operand(&self, operand: &Operand) -> bool {
+        match *operand {
+            Operand::Int(_) | Operand::BigInt(_) | Operand::Real(_) | Operand::String(_) | Operand::Bool(_) => true,
+            Operand::Identifier(ref identifier) => self.is_constant(identifier),
+            Operand::Call { ref identifier, ref arguments } => builtins::builtin(identifier).is_some()
+                && arguments.iter().all(|argument| self.is_constant_expression(argument)),
+            Operand::Expr(ref expr) => self.is_constant_expression(expr),
+            Operand::Index { .. } | Operand::Size(_) => false,
+        }
+    }
+
+    /// The type of an assigned or read variable, or of the indexed element of an array.
+    fn target_type(&mut self, identifier: &str, index: Option<&Expression>) -> Option<MplType> {
+        match index {
+            Some(index) => self.element_type(identifier, index),
+            None => self.identifier_type(identifier),
+        }
+    }
+
+    /// The element type of an array. Reports an error if the identifier is not an array, or
+    /// the index is not an integer.
+    fn element_type(&mut self, identifier: &str, index: &Expression) -> Option<MplType> {
+        self.expect_type(index, &MplType::Int, &format!("Index of {}", identifier));
+        match self.identifier_type(identifier)? {
+            MplType::Array { element, .. } => Some(*element),
+            mpl_type => {
+                self.error(format!("{} is not an array, but {}", identifier, mpl_type));
+                None
+            },
+        }
+    }
+
+    /// The type of an expression. Returns None if the expression has an error, which has
+    /// already been reported. This avoids reporting follow-up errors of the same mistake.
+    fn expression_type(&mut self, expr: &Expression) -> Option<MplType> {
+        let mpl_type = self.infer_expression_type(expr);
+        if let Some(ref mpl_type) = mpl_type {
+            self.expression_types.insert(expr, mpl_type.clone());
+        }
+        mpl_type
+    }
+
+    fn infer_expression_type(&mut self, expr: &Expression) -> Option<MplType> {
+        match *expr {
+            Expression::Simple(ref operand) => self.operand_type(operand),
+            Expression::Unary { operator, ref operand } => {
+                let operand = self.operand_type(operand)?;
+                match (operator, operand) {
+                    (Operator::Not, MplType::Bool) => Some(MplType::Bool),
+                    (Operator::Not, operand) => {
+                        self.error(format!("Operator ! expects Boolean, not {}", operand));
+                        None
+                    },
+                    _ => {
+                        self.error(format!("Operator {} is not a unary operator", operator));
+                        None
+                    },
+                }
+            },
+            Expression::Binary { ref left, operator, ref right } => {
+                let left = self.operand_type(left);
+                let right = self.operand_type(right);
+                let (left, right) = (left?, right?);
+                let result = match (operator, &left, &right) {
+                    (Operator::Plus, &MplType::Int, &MplType::Int) => Some(MplType::Int),
+                    (Operator::Plus, &MplType::String, &MplType::String) => Some(MplType::String),
+                    (Operator::Minus, &MplType::Int, &MplType::Int)
+                    | (Operator::Times, &MplType::Int, &MplType::Int)
+                    | (Operator::Divide, &MplType::Int, &MplType::Int)
+                    | (Operator::Modulo, &MplType::Int, &MplType::Int) => Some(MplType::Int),
+                    (Operator::Plus, &MplType::Real, &MplType::Real)
+                    | (Operator::Minus, &MplType::Real, &MplType::Real)
+                    | (Operator::Times, &MplType::Real, &MplType::Real)
+                    | (Operator::Divide, &MplType::Real, &MplType::Real)
+                    | (Operator::Modulo, &MplType::Real, &MplType::Real) => Some(MplType::Real),
+                    (Operator::And, &MplType::Bool, &MplType::Bool)
+                    | (Operator::Or, &MplType::Bool, &MplType::Bool) => Some(MplType::Bool),
+                    _ if operator.is_comparison() && left == right && is_scalar(&left) => Some(MplType::Bool),
+                    (Operator::Not, _, _) => {
+                        self.error("Operator ! is a unary operator".to_string());
+                        return None;
+                    },
+                    _ => None,
+                };
+                if result.is_none() {
+                    self.error(format!("Operator {} is not defined for {} and {}", operator, left, right));
+                }
+                result
+            },
+        }
+    }
+
+    fn operand_type(&mut self, operand: &Operand) -> Option<MplType> {
+        let mpl_type = self.infer_operand_type(operand);
+        if let Some(ref mpl_type) = mpl_type {
+            self.operand_types.insert(operand, mpl_type.clone());
+        }
+        mpl_type
+    }
+
+    fn infer_operand_type(&mut self, operand: &Operand) -> Option<MplType> {
+        match *operand {
+            Operand::Int(_) | Operand::BigInt(_) => Some(MplType::Int),
+            Operand::Real(_) => Some(MplType::Real),
+            Operand::String(_) => Some(MplType::String),
+            Operand::Bool(_) => Some(MplType::Bool),
+            Operand::Identifier(ref identifier) => self.identifier_type(identifier),
+            Operand::Index { ref identifier, ref index } => self.element_type(identifier, index),
+            Operand::Size(ref identifier) => match self.identifier_type(identifier)? {
+                MplType::Array { .. } => Some(MplType::Int),
+                mpl_type => {
+                    self.error(format!("{} is not an array, but {}", identifier, mpl_type));
+                    None
+                },
+            },
+            Operand::Call { ref identifier, ref arguments } => match self.check_call(identifier, arguments) {
+                Some(Signature { return_type: None, .. }) => {
+                    self.error(format!("Procedure {} does not return a value", identifier));
+                    None
+                },
+                signature => signature.and_then(|signature| signature.return_type),
+            },
+            Operand::Expr(ref expr) => self.expression_type(expr),
+        }
+    }
+}
+
+/// Whether the type is one of the basic types, which the comparison operators are defined for.
+fn is_scalar(mpl_type: &MplType) -> bool {
+    !matches!(*mpl_type, MplType::Array { .. })
+}
+
+/// Whether executing the statements always ends in a return statement.
+fn always_returns(statements: &[Located<Statement>]) -> bool {
+    statements.iter().any(|statement| match statement.node {
+        Statement::Return(_) => true,
+        Statement::If { ref statements, ref else_statements, .. }
+            => always_returns(statements) && always_returns(else_statements),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{parser, scanner};
+
+    fn check_source(source: &str) -> Result<(), String> {
+        check(&parser::parse(scanner::scan(source, false).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn success_if() {
+        let source = "var x : int := 1;\nif x < 2 then print x; else print \"no\"; end if;\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_if_condition() {
+        let source = "var x : int := 1;\nif x + 1 then print x; end if;\n";
+        assert_eq!(check_source(source), Err("2:1: Condition of if must be Boolean, not Integer".to_string()));
+    }
+
+    #[test]
+    fn failure_types() {
+        let source = "var s : string := 1;\ns := \"a\" - \"b\";\nfor s in 1..2 do print y; end for;\n\
+                      var i : int;\nfor i in 2 downto 1 step s do end for;\n";
+        assert_eq!(check_source(source), Err([
+            "1:1: Initial value must be String, not Integer",
+            "2:1: Operator - is not defined for String and String",
+            "3:1: Control variable s must be Integer, not String",
+            "3:18: Identifier y used before declaration",
+            "5:1: Step of range must be Integer, not String",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn success_operators() {
+        let source = "var b : bool := (1 <= 2) | (\"a\" <> \"b\");\nb := (b >= b) & ((7 % 2) > 0);\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_operators() {
+        let source = "var b : bool := 1 | 2;\nb := \"a\" % \"b\";\nb := 1 >= \"b\";\n";
+        assert_eq!(check_source(source), Err([
+            "1:1: Operator | is not defined for Integer and Integer",
+            "2:1: Operator % is not defined for String and String",
+            "3:1: Operator >= is not defined for Integer and String",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn success_reals() {
+        let source = "var r : real := (1.5 * 2.0) / 1e1;\nread r;\nvar b : bool := r <= int_to_real(real_to_int(r));\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_reals() {
+        let source = "var r : real := 1;\nr := r + 1;\nvar i : int := 2.0;\n";
+        assert_eq!(check_source(source), Err([
+            "1:1: Initial value must be Real, not Integer",
+            "2:1: Operator + is not defined for Real and Integer",
+            "3:1: Initial value must be Integer, not Real",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn success_constants() {
+        let source = "const n : int := length(\"abc\") * 2;\nconst on : bool := true;\nconst half : real := 0.5;\n\
+                      var i : int;\nfor i in 1..n do if on then print half; end if; end for;\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_constants() {
+        let source = "var x : int;\nconst n : int := x + 1;\nconst a : array [2] of int := 1;\nn := 2;\nread n;\n\
+                      for n in 1..2 do end for;\nconst s : string := 1;\n";
+        assert_eq!(check_source(source), Err([
+            "2:1: Value of constant n must be known at compile time",
+            "3:1: Constant a cannot be an array",
+            "3:1: Value of constant a must be Array [2] of Integer, not Integer",
+            "4:1: Cannot assign to constant n",
+            "5:1: Cannot read into constant n",
+            "6:1: Constant n cannot be the control variable of a for loop",
+            "7:1: Value of constant s must be String, not Integer",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn success_break_and_continue_in_loops() {
+        let source = "var i : int := 0;\nwhile i < 5 do\n  if i = 3 then break; else continue; end if;\nend while;\n\
+                      for i in 1..2 do continue; end for;\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_break_outside_loop() {
+        let source = "var b : bool;\nif b then break; end if;\ncontinue;\n";
+        assert_eq!(check_source(source), Err("2:11: Break outside of a loop\n3:1: Continue outside of a loop".to_string()));
+    }
+
+    #[test]
+    fn success_functions() {
+        let source = "print twice(\"a\");\n\
+                      function twice(s : string) : string do\n  if s = \"\" then return s; else return s + s; end if;\nend function;\n\
+                      procedure show(i : int) do\n  print i;\n  return;\nend procedure;\nshow(1);\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_functions() {
+        let source = "function f(i : int) : int do\n  while i < 5 do return i; end while;\nend function;\n\
+                      print f(\"x\") + 1;\nvar b : bool := f(1);\n";
+        assert_eq!(check_source(source), Err([
+            "1:1: Function f may end without returning a value",
+            "4:1: Argument 1 of f must be Integer, not String",
+            "5:1: Initial value must be Boolean, not Integer",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn success_builtins() {
+        let source = "var s : string := substring(to_upper(\"abc\"), 1, length(\"ab\"));\n\
+                      var i : int := index_of(s, char_at(s, 0)) + string_to_int(\"12\");\n\
+                      print int_to_string(i) + bool_to_string(i < 2);\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_builtins() {
+        let source = "var i : int := length(1);\nvar s : string := substring(\"a\", 0);\n\
+                      function to_lower(s : string) : string do return s; end function;\n";
+        assert_eq!(check_source(source), Err([
+            "3:1: to_lower is a builtin function",
+            "1:1: Argument 1 of length must be String, not Integer",
+            "2:1: substring expects 3 arguments, got 2",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn success_sibling_scopes() {
+        let source = "var i : int;\nfor i in 1..2 do\n  var x : int := i;\nend for;\n\
+                      if i = 3 then var x : string; else var x : bool; end if;\nvar x : int;\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_scopes() {
+        let source = "var i : int;\nfor i in 1..2 do\n  var x : int := i;\n  var i : int;\nend for;\nprint x;\n";
+        assert_eq!(check_source(source), Err([
+            "4:3: Identifier i shadows the declaration at 1:1",
+            "6:1: Identifier x used before declaration",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn success_arrays() {
+        let source = "var a : array [3] of string;\nvar i : int := a.size - 1;\nread a[i];\na[0] := a[i] + \"!\";\n";
+        assert_eq!(check_source(source), Ok(()));
+    }
+
+    #[test]
+    fn failure_arrays() {
+        let source = "var a : array [3] of int;\nvar s : string;\na[s] := \"x\";\nread a;\nprint s.size;\n";
+        assert_eq!(check_source(source), Err([
+            "3:1: Index of a must be Integer, not String",
+            "3:1: Value assigned to an element of a must be Integer, not String",
+            "4:1: Cannot read into a of type Array [3] of Integer",
+            "5:1: s is not an array, but String",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn failure_redeclaration() {
+        let source = "var x : int;\nvar x : bool;\n";
+        assert_eq!(check_source(source), Err("2:1: Identifier x is already declared".to_string()));
+    }
+}
@@ -0,0 +1,150 @@
+//! Compile time evaluation of constants. After the program has been checked, the value of
+//! every constant is computed, and the uses of the constant are replaced by the value. The
+//! constants follow the same scoping rules as variables.
+
+use super::interpreter::{Interpreter, MplValue};
+use super::parser::ast::*;
+
+use std::collections::HashMap;
+use std::mem;
+
+/// Compute the constants of a checked AST, and replace their uses by their values. The value
+/// of each constant declaration is replaced by a literal too. Returns the errors of computing
+/// the values, one per line, each prefixed by the position of the constant. Integers are
+/// unbounded if `bigint` is set.
+pub fn fold(ast: &mut Ast, bigint: bool) -> Result<(), String> {
+    let mut evaluator = Interpreter::new();
+    evaluator.set_bigint(bigint);
+    let mut folder = Folder { scopes: vec![HashMap::new()], evaluator, errors: Vec::new() };
+    folder.visit_ast(ast);
+    if folder.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(folder.errors.join("\n"))
+    }
+}
+
+/// The folder holds the chain of scopes of the constants with their values, and evaluates the
+/// values with an interpreter that runs no program.
+struct Folder {
+    scopes: Vec<HashMap<String, Operand>>, // innermost scope last
+    evaluator: Interpreter,
+    errors: Vec<String>,
+}
+
+impl VisitorMut for Folder {
+    /// Compute the value of a constant after folding the constants in it.
+    fn visit_statement(&mut self, statement: &mut Located<Statement>) {
+        walk_statement_mut(self, statement);
+        if let Statement::Constant { ref identifier, ref mut value, .. } = statement.node {
+            match self.evaluator.evaluate_expression(value) {
+                Ok(result) => {
+                    let literal = literal(result);
+                    *value = Expression::Simple(literal.clone());
+                    self.scopes.last_mut().expect("there is always a scope").insert(identifier.clone(), literal);
+                },
+                Err(e) => self.errors.push(format!("{}: Value of constant {}: {}", statement.position, identifier, e)),
+            }
+        }
+    }
+
+    fn visit_block(&mut self, statements: &mut [Located<Statement>]) {
+        self.scopes.push(HashMap::new());
+        walk_block_mut(self, statements);
+        self.scopes.pop();
+    }
+
+    fn visit_function(&mut self, function: &mut Function) {
+        let globals = mem::take(&mut self.scopes);
+        walk_function_mut(self, function);
+        self.scopes = globals;
+    }
+
+    fn visit_operand(&mut self, operand: &mut Operand) {
+        let value = match *operand {
+            Operand::Identifier(ref identifier) => self.scopes.iter().rev()
+                .find_map(|scope| scope.get(identifier))
+                .cloned(),
+            _ => None,
+        };
+        match value {
+            Some(value) => *operand = value,
+            None => walk_operand_mut(self, operand),
+        }
+    }
+}
+
+/// The literal operand of a value. Constants, and other values known at compile time, are
+/// never arrays.
+pub fn literal(value: MplValue) -> Operand {
+    match value {
+        MplValue::Int(i) => Operand::Int(i),
+        MplValue::BigInt(i) => Operand::BigInt(i),
+        MplValue::Real(r) => Operand::Real(r),
+        MplValue::String(s) => Operand::String(s),
+        MplValue::Bool(b) => Operand::Bool(b),
+        MplValue::Array(..) => unreachable!("the checker rejects array constants"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{checker, parser, scanner};
+
+    fn fold_source(source: &str) -> Result<Vec<Located<Statement>>, String> {
+        let mut ast = parser::parse(scanner::scan(source, false).unwrap()).unwrap();
+        checker::check(&ast).unwrap();
+        fold(&mut ast, false).map(|_| ast.statements)
+    }
+
+    #[test]
+    fn constants_are_replaced_by_their_values() {
+        let source = "const n : int := 2 * 3;\nconst s : string := int_to_string(n + 1);\n\
+                      var i : int;\nfor i in 1..n do print s; end for;\n";
+        let statements = fold_source(source).unwrap();
+        assert_eq!(statements[1].node, Statement::Constant {
+            identifier: "s".to_string(),
+            mpl_type: MplType::String,
+            value: Expression::Simple(Operand::String("7".to_string())),
+        });
+        match statements[3].node {
+            Statement::For { ref end, ref statements, .. } => {
+                assert_eq!(*end, Expression::Simple(Operand::Int(6)));
+                assert_eq!(statements[0].node, Statement::Print(Expression::Simple(Operand::String("7".to_string()))));
+            },
+            ref statement => panic!("expected a for loop, got {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn constants_are_scoped_to_their_blocks() {
+        let source = "var b : bool;\nif b then const c : int := 1; print c; else var c : int; print c; end if;\n\
+                      const d : int := 2;\nprocedure p() do var d : int; print d; end procedure;\n";
+        let statements = fold_source(source).unwrap();
+        let printed = |statement: &Located<Statement>| match statement.node {
+            Statement::Print(ref expr) => expr.to_string(),
+            ref statement => panic!("expected a print statement, got {:?}", statement),
+        };
+        match statements[1].node {
+            Statement::If { ref statements, ref else_statements, .. } => {
+                assert_eq!(printed(&statements[1]), "1");
+                assert_eq!(printed(&else_statements[1]), "c");
+            },
+            ref statement => panic!("expected an if statement, got {:?}", statement),
+        }
+        match statements[3].node {
+            Statement::Function(ref function) => assert_eq!(printed(&function.statements[1]), "d"),
+            ref statement => panic!("expected a procedure, got {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn errors_in_values() {
+        let source = "const big : int := 65536 * 65536;\nconst zero : int := 0;\nconst q : int := 1 / zero;\n";
+        assert_eq!(fold_source(source).map(|_| ()), Err([
+            "1:1: Value of constant big: Integer overflow: 65536 * 65536 does not fit in 32 bits",
+            "3:1: Value of constant q: Division by zero",
+        ].join("\n")));
+    }
+}
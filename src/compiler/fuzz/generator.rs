@@ -0,0 +1,675 @@
+//! A generator of random Mini-PL programs. A well-typed program follows the rules of the
+//! checker: every identifier is declared before it is used, in a visible scope, and every
+//! expression and statement is well typed. An ill-typed program is a well-typed one with one
+//! mistake in a random statement, which the checker must report.
+//!
+//! The programs are otherwise unconstrained, so they may fail at runtime, for example by
+//! dividing by zero or indexing out of bounds, and they may not terminate.
+//!
+//! The generator also makes trees of any shape the parser can produce, ignoring the rules of
+//! the checker, for testing that the printer and the parser agree.
+
+use super::super::interpreter::builtins;
+use super::super::parser::ast::*;
+
+/// A generator of pseudo-random numbers, the xorshift64* generator.
+pub struct Random(u64);
+
+impl Random {
+    /// A generator seeded with the number. Different seeds give unrelated sequences.
+    pub fn new(seed: u64) -> Random {
+        Random(seed.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number from 0 up to n, excluding n.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Whether an event with a chance of one in n happens.
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+const SCALARS: [MplType; 4] = [MplType::Int, MplType::Real, MplType::String, MplType::Bool];
+
+const COMPARISONS: [Operator; 6] = [
+    Operator::Equal, Operator::NotEqual, Operator::Less, Operator::LessEqual, Operator::Greater, Operator::GreaterEqual,
+];
+
+const BINARY_OPERATORS: [Operator; 13] = [
+    Operator::Plus, Operator::Minus, Operator::Times, Operator::Divide, Operator::Modulo, Operator::And,
+    Operator::Or, Operator::Equal, Operator::NotEqual, Operator::Less, Operator::LessEqual, Operator::Greater,
+    Operator::GreaterEqual,
+];
+
+/// Names of the untyped trees, which may be used before they are declared, or not at all.
+const SHAPE_IDENTIFIERS: [&str; 5] = ["x", "y1", "total", "a_b", "size"];
+
+/// The depth of nested blocks, and of nested expressions, that the generator stops at.
+const MAX_DEPTH: usize = 3;
+
+/// A declared variable or constant.
+struct Variable {
+    identifier: String,
+    mpl_type: MplType,
+    constant: bool,
+}
+
+/// The parameter types and the return type of a procedure or function.
+struct Signature {
+    identifier: String,
+    parameters: Vec<MplType>,
+    return_type: Option<MplType>, // None for a procedure
+}
+
+/// The generator holds the variables visible at the statement being generated, the procedures
+/// and functions of the program, and the context of the statement.
+pub struct Generator {
+    random: Random,
+    scopes: Vec<Vec<Variable>>, // innermost scope last
+    functions: Vec<Signature>,
+    names: usize, // the number of identifiers made so far
+    loops: usize,
+    returns: Option<Option<MplType>>, // return type of the enclosing procedure or function, if any
+    mistake: Mistake,
+}
+
+/// Whether the program gets a mistake.
+#[derive(Clone, Copy, PartialEq)]
+enum Mistake {
+    None,
+    Pending,
+    Made,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Generator {
+        Generator {
+            random: Random::new(seed),
+            scopes: Vec::new(),
+            functions: Vec::new(),
+            names: 0,
+            loops: 0,
+            returns: None,
+            mistake: Mistake::None,
+        }
+    }
+
+    /// A random program, well typed unless `ill_typed` is set.
+    pub fn program(&mut self, ill_typed: bool) -> Ast {
+        self.names = 0;
+        self.mistake = if ill_typed { Mistake::Pending } else { Mistake::None };
+        self.functions = (0..self.random.below(4)).map(|i| {
+            let parameters = (0..self.random.below(3)).map(|_| self.any_type()).collect();
+            let return_type = if self.random.one_in(3) { None } else { Some(self.scalar_type()) };
+            Signature { identifier: format!("f{}", i), parameters, return_type }
+        }).collect();
+
+        let functions: Vec<Statement> = (0..self.functions.len()).map(|i| self.function(i)).collect();
+        self.scopes = vec![Vec::new()];
+        let mut statements = self.statements(0, 8);
+        if self.mistake == Mistake::Pending {
+            statements.push(self.mistaken_statement());
+        }
+        for function in functions {
+            let at = self.random.below(statements.len() + 1);
+            statements.insert(at, function);
+        }
+        Ast { statements: statements.into_iter().map(located).collect() }
+    }
+
+    /// A random tree of any shape the parser can produce. It is not type checked.
+    pub fn shape(&mut self) -> Ast {
+        Ast { statements: self.any_statements(0) }
+    }
+
+    fn any_statements(&mut self, depth: usize) -> Vec<Located<Statement>> {
+        (0..self.random.below(4)).map(|_| located(self.any_statement(depth))).collect()
+    }
+
+    fn any_statement(&mut self, depth: usize) -> Statement {
+        // Only simple statements at the depth limit, so that the trees stay small.
+        let kinds = if depth >= MAX_DEPTH { 9 } else { 14 };
+        match self.random.below(kinds) {
+            0 => Statement::Declaration { identifier: self.any_identifier(), mpl_type: self.any_type(), value: self.any_optional(depth) },
+            1 => Statement::Constant { identifier: self.any_identifier(), mpl_type: self.any_type(), value: self.any_expression(depth) },
+            2 => Statement::Assignment { identifier: self.any_identifier(), index: self.any_optional(depth), expression: self.any_expression(depth) },
+            3 => Statement::Read { identifier: self.any_identifier(), index: self.any_optional(depth) },
+            4 => Statement::Print(self.any_expression(depth)),
+            5 => Statement::Assert(self.any_expression(depth)),
+            6 => Statement::Call {
+                identifier: self.any_identifier(),
+                arguments: (0..self.random.below(3)).map(|_| self.any_expression(depth)).collect(),
+            },
+            7 => Statement::Return(self.any_optional(depth)),
+            8 => self.random.choose(&[Statement::Break, Statement::Continue, Statement::Empty]).clone(),
+            9 => Statement::For {
+                identifier: self.any_identifier(),
+                begin: self.any_expression(depth),
+                end: self.any_expression(depth),
+                descending: self.random.one_in(2),
+                step: self.any_optional(depth),
+                statements: self.any_statements(depth + 1),
+            },
+            10 => Statement::If {
+                condition: self.any_expression(depth),
+                statements: self.any_statements(depth + 1),
+                else_statements: self.any_statements(depth + 1),
+            },
+            11 => Statement::While { condition: self.any_expression(depth), statements: self.any_statements(depth + 1) },
+            _ => {
+                let return_type = if self.random.one_in(2) { None } else { Some(self.any_type()) };
+                Statement::Function(Function {
+                    identifier: self.any_identifier(),
+                    parameters: (0..self.random.below(3))
+                        .map(|_| Parameter { identifier: self.any_identifier(), mpl_type: self.any_type() })
+                        .collect(),
+                    return_type,
+                    statements: self.any_statements(depth + 1),
+                })
+            },
+        }
+    }
+
+    fn any_optional(&mut self, depth: usize) -> Option<Expression> {
+        if self.random.one_in(2) { None } else { Some(self.any_expression(depth)) }
+    }
+
+    fn any_expression(&mut self, depth: usize) -> Expression {
+        match self.random.below(3) {
+            0 => Expression::Simple(self.any_operand(depth)),
+            1 => Expression::Binary {
+                left: self.any_operand(depth),
+                operator: *self.random.choose(&BINARY_OPERATORS),
+                right: self.any_operand(depth),
+            },
+            _ => Expression::Unary { operator: *self.random.choose(&[Operator::Not, Operator::Minus]), operand: self.any_operand(depth) },
+        }
+    }
+
+    fn any_operand(&mut self, depth: usize) -> Operand {
+        let kinds = if depth >= MAX_DEPTH { 3 } else { 6 };
+        match self.random.below(kinds) {
+            0 => {
+                let mpl_type = self.scalar_type();
+                self.literal(&mpl_type)
+            },
+            1 => Operand::Identifier(self.any_identifier()),
+            2 => Operand::Size(self.any_identifier()),
+            3 => Operand::Index { identifier: self.any_identifier(), index: Box::new(self.any_expression(depth + 1)) },
+            4 => Operand::Call {
+                identifier: self.any_identifier(),
+                arguments: (0..self.random.below(3)).map(|_| self.any_expression(depth + 1)).collect(),
+            },
+            _ => Operand::Expr(Box::new(self.any_expression(depth + 1))),
+        }
+    }
+
+    fn any_identifier(&mut self) -> String {
+        self.random.choose(&SHAPE_IDENTIFIERS).to_string()
+    }
+
+    fn function(&mut self, i: usize) -> Statement {
+        let parameters: Vec<Parameter> = self.functions[i].parameters.iter().enumerate()
+            .map(|(n, mpl_type)| Parameter { identifier: format!("p{}", n), mpl_type: mpl_type.clone() })
+            .collect();
+        let return_type = self.functions[i].return_type.clone();
+        self.scopes = vec![parameters.iter().map(|parameter| Variable {
+            identifier: parameter.identifier.clone(),
+            mpl_type: parameter.mpl_type.clone(),
+            constant: false,
+        }).collect()];
+        self.returns = Some(return_type.clone());
+        let mut statements = self.statements(1, 5);
+        if let Some(ref return_type) = return_type {
+            statements.push(Statement::Return(Some(self.expression(return_type, 0))));
+        }
+        self.returns = None;
+        Statement::Function(Function {
+            identifier: self.functions[i].identifier.clone(),
+            parameters,
+            return_type,
+            statements: statements.into_iter().map(located).collect(),
+        })
+    }
+
+    /// Up to `most` statements in the innermost scope, one of which may be the mistake.
+    fn statements(&mut self, depth: usize, most: usize) -> Vec<Statement> {
+        (0..self.random.below(most + 1)).map(|_| {
+            if self.mistake == Mistake::Pending && self.random.one_in(10) {
+                self.mistaken_statement()
+            } else {
+                self.statement(depth)
+            }
+        }).collect()
+    }
+
+    /// The statements of a block, in a new scope.
+    fn block(&mut self, depth: usize) -> Vec<Located<Statement>> {
+        self.scopes.push(Vec::new());
+        let statements = self.statements(depth, 4);
+        self.scopes.pop();
+        statements.into_iter().map(located).collect()
+    }
+
+    fn loop_block(&mut self, depth: usize) -> Vec<Located<Statement>> {
+        self.loops += 1;
+        let statements = self.block(depth);
+        self.loops -= 1;
+        statements
+    }
+
+    fn statement(&mut self, depth: usize) -> Statement {
+        let kinds = if depth >= MAX_DEPTH { 8 } else { 11 };
+        match self.random.below(kinds) {
+            0 => {
+                let mpl_type = self.any_type();
+                let value = match mpl_type {
+                    MplType::Array { .. } => self.variable_of(&mpl_type, false).map(|identifier| {
+                        Expression::Simple(Operand::Identifier(identifier))
+                    }),
+                    _ if self.random.one_in(3) => None,
+                    _ => Some(self.expression(&mpl_type, 0)),
+                };
+                let identifier = self.declare(mpl_type.clone(), false);
+                Statement::Declaration { identifier, mpl_type, value }
+            },
+            1 => {
+                let mpl_type = self.scalar_type();
+                let value = self.constant_expression(&mpl_type);
+                let identifier = self.declare(mpl_type.clone(), true);
+                Statement::Constant { identifier, mpl_type, value }
+            },
+            2 => match self.target() {
+                Some((identifier, index, mpl_type)) => {
+                    let expression = match mpl_type {
+                        MplType::Array { .. } => Expression::Simple(Operand::Identifier(
+                            self.variable_of(&mpl_type, false).expect("the target has the type"),
+                        )),
+                        _ => self.expression(&mpl_type, 0),
+                    };
+                    Statement::Assignment { identifier, index, expression }
+                },
+                None => self.print(),
+            },
+            3 => match self.target() {
+                Some((identifier, index, MplType::Array { .. })) if index.is_none() => {
+                    let index = Some(self.index(&identifier));
+                    Statement::Read { identifier, index }
+                },
+                Some((identifier, index, _)) => Statement::Read { identifier, index },
+                None => self.print(),
+            },
+            4 => self.print(),
+            5 => Statement::Assert(self.expression(&MplType::Bool, 0)),
+            6 => {
+                let callable: Vec<(String, Vec<MplType>)> = self.functions.iter()
+                    .filter(|function| self.can_pass(&function.parameters))
+                    .map(|function| (function.identifier.clone(), function.parameters.clone()))
+                    .collect();
+                if callable.is_empty() {
+                    return self.print();
+                }
+                let (identifier, parameters) = self.random.choose(&callable).clone();
+                Statement::Call { identifier, arguments: self.arguments(&parameters, 0) }
+            },
+            7 => match (self.loops, self.returns.clone()) {
+                (0, None) => Statement::Empty,
+                (0, Some(return_type)) => self.return_statement(return_type.as_ref()),
+                (_, return_type) => match self.random.below(4) {
+                    0 if return_type.is_some() => self.return_statement(return_type.unwrap().as_ref()),
+                    0 | 1 => Statement::Break,
+                    _ => Statement::Continue,
+                },
+            },
+            8 => Statement::If {
+                condition: self.expression(&MplType::Bool, 0),
+                statements: self.block(depth + 1),
+                else_statements: if self.random.one_in(2) { Vec::new() } else { self.block(depth + 1) },
+            },
+            9 => Statement::While { condition: self.expression(&MplType::Bool, 0), statements: self.loop_block(depth + 1) },
+            _ => match self.variable_of(&MplType::Int, false) {
+                Some(identifier) => Statement::For {
+                    identifier,
+                    begin: self.expression(&MplType::Int, 0),
+                    end: self.expression(&MplType::Int, 0),
+                    descending: self.random.one_in(3),
+                    step: if self.random.one_in(2) { None } else { Some(self.expression(&MplType::Int, 0)) },
+                    statements: self.loop_block(depth + 1),
+                },
+                None => self.print(),
+            },
+        }
+    }
+
+    fn print(&mut self) -> Statement {
+        let mpl_type = self.any_type();
+        match mpl_type {
+            MplType::Array { .. } => match self.variable_of(&mpl_type, true) {
+                Some(identifier) => Statement::Print(Expression::Simple(Operand::Identifier(identifier))),
+                None => Statement::Print(self.expression(&MplType::String, 0)),
+            },
+            _ => Statement::Print(self.expression(&mpl_type, 0)),
+        }
+    }
+
+    fn return_statement(&mut self, return_type: Option<&MplType>) -> Statement {
+        Statement::Return(return_type.map(|return_type| self.expression(return_type, 0)))
+    }
+
+    /// A statement with one mistake that the checker reports.
+    fn mistaken_statement(&mut self) -> Statement {
+        self.mistake = Mistake::Made;
+        let (expected, found) = self.different_types();
+        let condition = if found == MplType::Bool { expected.clone() } else { found.clone() };
+        match self.random.below(12) {
+            0 => Statement::Declaration {
+                identifier: self.declare(expected.clone(), false),
+                mpl_type: expected,
+                value: Some(self.expression(&found, 0)),
+            },
+            1 => match self.variable_of(&expected, false) {
+                Some(identifier) => Statement::Assignment { identifier, index: None, expression: self.expression(&found, 0) },
+                None => Statement::Print(Expression::Simple(Operand::Identifier(self.identifier()))),
+            },
+            2 => Statement::If {
+                condition: self.expression(&condition, 0),
+                statements: self.block(MAX_DEPTH),
+                else_statements: Vec::new(),
+            },
+            3 => Statement::While { condition: self.expression(&condition, 0), statements: self.loop_block(MAX_DEPTH) },
+            4 => Statement::Assert(self.expression(&condition, 0)),
+            5 => Statement::Print(Expression::Binary {
+                left: self.operand(&expected, 1),
+                operator: *self.random.choose(&BINARY_OPERATORS),
+                right: self.operand(&found, 1),
+            }),
+            6 => {
+                let operator = if found == MplType::Bool { Operator::Minus } else { *self.random.choose(&[Operator::Not, Operator::Minus]) };
+                Statement::Print(Expression::Unary { operator, operand: self.operand(&found, 1) })
+            },
+            7 => {
+                let builtin = self.random.choose(&builtins::BUILTINS);
+                let (identifier, mut parameters) = (builtin.identifier.to_string(), builtin.parameters.to_vec());
+                if parameters.len() > 1 && self.random.one_in(2) {
+                    parameters.pop();
+                } else {
+                    parameters.push(found);
+                }
+                Statement::Print(Expression::Simple(Operand::Call { arguments: self.arguments(&parameters, 1), identifier }))
+            },
+            8 => Statement::Print(Expression::Simple(Operand::Identifier(self.identifier()))),
+            9 => match (self.loops, self.returns.clone()) {
+                (0, _) => self.random.choose(&[Statement::Break, Statement::Continue]).clone(),
+                (_, None) => Statement::Return(None),
+                (_, Some(None)) => Statement::Return(Some(self.expression(&found, 0))),
+                (_, Some(Some(return_type))) => {
+                    let wrong = if return_type == found { expected } else { found };
+                    Statement::Return(Some(self.expression(&wrong, 0)))
+                },
+            },
+            10 => match self.variable_of(&found, false) {
+                Some(identifier) if found != MplType::Int => Statement::For {
+                    identifier,
+                    begin: self.expression(&MplType::Int, 0),
+                    end: self.expression(&MplType::Int, 0),
+                    descending: false,
+                    step: None,
+                    statements: self.loop_block(MAX_DEPTH),
+                },
+                _ => Statement::For {
+                    identifier: self.identifier(),
+                    begin: self.expression(&MplType::Int, 0),
+                    end: self.expression(&MplType::Real, 0),
+                    descending: false,
+                    step: None,
+                    statements: Vec::new(),
+                },
+            },
+            _ => match self.variable_of(&expected, false) {
+                Some(identifier) => Statement::Read { index: Some(self.expression(&MplType::Int, 0)), identifier },
+                None => Statement::Print(Expression::Simple(Operand::Size(self.identifier()))),
+            },
+        }
+    }
+
+    /// Two different basic types, where the second one is a mistake for the first one.
+    fn different_types(&mut self) -> (MplType, MplType) {
+        let expected = self.random.below(SCALARS.len());
+        let found = (expected + 1 + self.random.below(SCALARS.len() - 1)) % SCALARS.len();
+        (SCALARS[expected].clone(), SCALARS[found].clone())
+    }
+
+    fn scalar_type(&mut self) -> MplType {
+        self.random.choose(&SCALARS).clone()
+    }
+
+    /// A basic type, or sometimes an array of one.
+    fn any_type(&mut self) -> MplType {
+        match self.scalar_type() {
+            element if self.random.one_in(5) => MplType::Array { element: Box::new(element), size: 1 + self.random.below(4) },
+            mpl_type => mpl_type,
+        }
+    }
+
+    /// A new identifier, which is never declared.
+    fn identifier(&mut self) -> String {
+        self.names += 1;
+        format!("v{}", self.names)
+    }
+
+    /// Declare a new variable or constant of the type in the innermost scope.
+    fn declare(&mut self, mpl_type: MplType, constant: bool) -> String {
+        let identifier = self.identifier();
+        let variable = Variable { identifier: identifier.clone(), mpl_type, constant };
+        self.scopes.last_mut().expect("there is always a scope").push(variable);
+        identifier
+    }
+
+    /// A random visible variable of the type, which may be a constant if `constants` is set.
+    fn variable_of(&mut self, mpl_type: &MplType, constants: bool) -> Option<String> {
+        let candidates: Vec<String> = self.scopes.iter().flatten()
+            .filter(|variable| variable.mpl_type == *mpl_type && (constants || !variable.constant))
+            .map(|variable| variable.identifier.clone())
+            .collect();
+        if candidates.is_empty() {
+            None
+        } else {
+            Some(self.random.choose(&candidates).clone())
+        }
+    }
+
+    /// A random visible variable, or element of an array, that can be assigned: its
+    /// identifier, the index of the element, and its type.
+    fn target(&mut self) -> Option<(String, Option<Expression>, MplType)> {
+        let candidates: Vec<(String, MplType)> = self.scopes.iter().flatten()
+            .filter(|variable| !variable.constant)
+            .map(|variable| (variable.identifier.clone(), variable.mpl_type.clone()))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let (identifier, mpl_type) = self.random.choose(&candidates).clone();
+        match mpl_type {
+            MplType::Array { .. } if self.random.one_in(4) => Some((identifier, None, mpl_type)),
+            MplType::Array { element, .. } => {
+                let index = self.index(&identifier);
+                Some((identifier, Some(index), *element))
+            },
+            mpl_type => Some((identifier, None, mpl_type)),
+        }
+    }
+
+    /// An index of the array, usually within its bounds.
+    fn index(&mut self, identifier: &str) -> Expression {
+        let size = self.scopes.iter().flatten()
+            .find_map(|variable| match variable.mpl_type {
+                MplType::Array { size, .. } if variable.identifier == identifier => Some(size),
+                _ => None,
+            })
+            .unwrap_or(1);
+        if self.random.one_in(4) {
+            self.expression(&MplType::Int, 1)
+        } else {
+            Expression::Simple(Operand::Int(self.random.below(size + 1) as i32))
+        }
+    }
+
+    /// Whether there are visible arrays of the types of the array parameters.
+    fn can_pass(&self, parameters: &[MplType]) -> bool {
+        parameters.iter().all(|parameter| match *parameter {
+            MplType::Array { .. } => self.scopes.iter().flatten().any(|variable| variable.mpl_type == *parameter),
+            _ => true,
+        })
+    }
+
+    /// Arguments of the parameter types. An array is passed in a variable of its type, which
+    /// `can_pass` has found.
+    fn arguments(&mut self, parameters: &[MplType], depth: usize) -> Vec<Expression> {
+        parameters.iter().map(|parameter| match *parameter {
+            MplType::Array { .. } => Expression::Simple(Operand::Identifier(
+                self.variable_of(parameter, true).expect("an array of the type is visible"),
+            )),
+            _ => self.expression(parameter, depth),
+        }).collect()
+    }
+
+    /// An expression of the basic type.
+    fn expression(&mut self, mpl_type: &MplType, depth: usize) -> Expression {
+        if depth >= MAX_DEPTH || self.random.one_in(3) {
+            return Expression::Simple(self.operand(mpl_type, depth));
+        }
+        match *mpl_type {
+            MplType::Int => Expression::Binary {
+                left: self.operand(mpl_type, depth),
+                operator: *self.random.choose(&[Operator::Plus, Operator::Minus, Operator::Times, Operator::Divide, Operator::Modulo]),
+                right: self.operand(mpl_type, depth),
+            },
+            MplType::Real => Expression::Binary {
+                left: self.operand(mpl_type, depth),
+                operator: *self.random.choose(&[Operator::Plus, Operator::Minus, Operator::Times, Operator::Divide, Operator::Modulo]),
+                right: self.operand(mpl_type, depth),
+            },
+            MplType::String => Expression::Binary {
+                left: self.operand(mpl_type, depth),
+                operator: Operator::Plus,
+                right: self.operand(mpl_type, depth),
+            },
+            _ => match self.random.below(3) {
+                0 => Expression::Unary { operator: Operator::Not, operand: self.operand(mpl_type, depth) },
+                1 => Expression::Binary {
+                    left: self.operand(mpl_type, depth),
+                    operator: *self.random.choose(&[Operator::And, Operator::Or]),
+                    right: self.operand(mpl_type, depth),
+                },
+                _ => {
+                    let compared = self.scalar_type();
+                    Expression::Binary {
+                        left: self.operand(&compared, depth),
+                        operator: *self.random.choose(&COMPARISONS),
+                        right: self.operand(&compared, depth),
+                    }
+                },
+            },
+        }
+    }
+
+    /// An operand of the basic type.
+    fn operand(&mut self, mpl_type: &MplType, depth: usize) -> Operand {
+        let kinds = if depth >= MAX_DEPTH { 2 } else { 6 };
+        match self.random.below(kinds) {
+            1 => match self.variable_of(mpl_type, true) {
+                Some(identifier) => Operand::Identifier(identifier),
+                None => self.literal(mpl_type),
+            },
+            2 => {
+                let arrays: Vec<String> = self.scopes.iter().flatten()
+                    .filter(|variable| matches!(variable.mpl_type, MplType::Array { ref element, .. } if **element == *mpl_type))
+                    .map(|variable| variable.identifier.clone())
+                    .collect();
+                match (arrays.is_empty(), *mpl_type == MplType::Int && self.random.one_in(3)) {
+                    (true, _) => self.literal(mpl_type),
+                    (false, true) => Operand::Size(self.random.choose(&arrays).clone()),
+                    (false, false) => {
+                        let identifier = self.random.choose(&arrays).clone();
+                        Operand::Index { index: Box::new(self.index(&identifier)), identifier }
+                    },
+                }
+            },
+            3 => {
+                let mut callable: Vec<(String, Vec<MplType>)> = builtins::BUILTINS.iter()
+                    .filter(|builtin| builtin.return_type == *mpl_type)
+                    .map(|builtin| (builtin.identifier.to_string(), builtin.parameters.to_vec()))
+                    .collect();
+                callable.extend(self.functions.iter()
+                    .filter(|function| function.return_type.as_ref() == Some(mpl_type) && self.can_pass(&function.parameters))
+                    .map(|function| (function.identifier.clone(), function.parameters.clone())));
+                if callable.is_empty() {
+                    return self.literal(mpl_type);
+                }
+                let (identifier, parameters) = self.random.choose(&callable).clone();
+                Operand::Call { identifier, arguments: self.arguments(&parameters, depth + 1) }
+            },
+            4 | 5 => Operand::Expr(Box::new(self.expression(mpl_type, depth + 1))),
+            _ => self.literal(mpl_type),
+        }
+    }
+
+    /// A literal of the basic type. The values include the extremes.
+    fn literal(&mut self, mpl_type: &MplType) -> Operand {
+        match *mpl_type {
+            MplType::Int => Operand::Int(match self.random.below(8) {
+                0 => i32::MAX,
+                1 => 1 << (self.random.below(31)),
+                _ => self.random.below(12) as i32,
+            }),
+            MplType::Real => Operand::Real(match self.random.below(8) {
+                0 => 1e308,
+                1 => 2.5e-7,
+                _ => self.random.below(80) as f64 / 8.0,
+            }),
+            MplType::String => {
+                let words = ["", "a", "Hello", "12", "-3", "2.5", "true", "\"", "\\", "\n", "\t", "ä"];
+                Operand::String((0..self.random.below(3)).map(|_| *self.random.choose(&words)).collect())
+            },
+            _ => Operand::Bool(self.random.one_in(2)),
+        }
+    }
+
+    /// An expression whose value is known at compile time, for the value of a constant.
+    fn constant_expression(&mut self, mpl_type: &MplType) -> Expression {
+        let operand = |generator: &mut Generator| match generator.variable_of(mpl_type, true) {
+            Some(identifier) if generator.is_constant(&identifier) && generator.random.one_in(2) => Operand::Identifier(identifier),
+            _ => generator.literal(mpl_type),
+        };
+        match *mpl_type {
+            MplType::Int | MplType::Real | MplType::String if self.random.one_in(2) => Expression::Binary {
+                left: operand(self),
+                operator: Operator::Plus,
+                right: operand(self),
+            },
+            _ => Expression::Simple(operand(self)),
+        }
+    }
+
+    fn is_constant(&self, identifier: &str) -> bool {
+        self.scopes.iter().flatten().any(|variable| variable.identifier == identifier && variable.constant)
+    }
+}
+
+fn located(node: Statement) -> Located<Statement> {
+    Located { node, position: Position::default(), end: Position::default() }
+}
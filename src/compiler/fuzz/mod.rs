@@ -0,0 +1,250 @@
+//! Fuzzing of the whole pipeline with generated programs. Each program is printed as source,
+//! and the source is scanned, parsed, checked and run under fuel limits, which stop programs
+//! that do not terminate or whose values grow without bound. A program must never panic.
+//!
+//! The checker and the interpreter must agree. A program that the checker accepts must not
+//! fail at runtime with a type error, one of the errors that the checker rules out. A program
+//! fails with such an error only if the checker rejects it, so programs are also run without
+//! being checked. A checked program must fail in the same way whether or not its constants
+//! are folded and it is optimised.
+//!
+//! The scanner and the parser are also fed programs with random edits, which are usually not
+//! valid, and these must not panic either.
+//!
+//! The tests run a few hundred programs. Set PLINT_FUZZ_RUNS to run more, and PLINT_FUZZ_SEED
+//! to start from another seed.
+
+pub mod generator;
+
+use self::generator::Random;
+use super::interpreter::{is_type_error, Input, Interpreter, Monitor, MplValue};
+use super::parser::ast::*;
+use super::parser::printer;
+use super::{checker, constants, optimizer, parser, scanner};
+
+use std::env;
+use std::panic;
+
+/// The number of statements and loop iterations a program may execute.
+const FUEL: usize = 2000;
+
+/// The largest string, in characters, or integer, in digits, that a program may compute.
+const MAX_VALUE_LENGTH: usize = 10_000;
+
+/// The depth of nested calls a program may make.
+const MAX_DEPTH: usize = 50;
+
+/// The error of a program that ran out of fuel.
+const OUT_OF_FUEL: &str = "Out of fuel";
+
+/// A monitor that stops the program when it has used up its fuel, or computes a value larger
+/// than the limit.
+struct Fuel {
+    left: usize,
+}
+
+impl Fuel {
+    fn burn(&mut self) -> Result<(), String> {
+        match self.left.checked_sub(1) {
+            Some(left) => { self.left = left; Ok(()) },
+            None => Err(OUT_OF_FUEL.to_string()),
+        }
+    }
+}
+
+impl Monitor for Fuel {
+    fn before_statement(&mut self, _interpreter: &mut Interpreter, _statement: &Located<Statement>) -> Result<(), String> {
+        self.burn()
+    }
+
+    fn before_iteration(&mut self, _interpreter: &mut Interpreter) -> Result<(), String> {
+        self.burn()
+    }
+
+    fn after_expression(&mut self, _interpreter: &mut Interpreter, _expression: &Expression, value: &MplValue) -> Result<(), String> {
+        let length = match *value {
+            MplValue::String(ref s) => s.len(),
+            MplValue::BigInt(ref i) => i.to_string().len(),
+            _ => 0,
+        };
+        if length > MAX_VALUE_LENGTH {
+            return Err(format!("{}: a value of {} characters", OUT_OF_FUEL, length));
+        }
+        Ok(())
+    }
+}
+
+/// Input lines for read statements, some of which are not valid for the type read.
+struct Lines {
+    read: usize,
+}
+
+impl Input for Lines {
+    fn read_line(&mut self, _identifier: &str, _position: Position) -> Result<String, String> {
+        const LINES: [&str; 8] = ["3", "-12", "2.5", "true", "abc", "", "2147483648", " 7 "];
+        self.read += 1;
+        Ok(LINES[self.read % LINES.len()].to_string())
+    }
+}
+
+/// Run the AST under the fuel limits, in a thread with enough stack for the calls.
+fn run_with_fuel(ast: Ast, bigint: bool) -> Result<(), String> {
+    let mut result = Err("The interpreter did not run".to_string());
+    super::with_stack(MAX_DEPTH, || {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_bigint(bigint);
+        interpreter.set_max_depth(MAX_DEPTH);
+        interpreter.set_input(Box::new(Lines { read: 0 }));
+        interpreter.add_monitor(Box::new(Fuel { left: FUEL }));
+        result = interpreter.run(ast);
+    });
+    result
+}
+
+/// Feed the source through the pipeline, and check that the checker and the interpreter agree.
+/// `well_typed` tells whether the checker must accept or reject the program. Returns the
+/// disagreement found, if any.
+fn check_agreement(source: &str, well_typed: bool, bigint: bool) -> Result<(), String> {
+    let tokens = scanner::scan(source, bigint).map_err(|e| format!("Scanning failed: {}", e))?;
+    let ast = parser::parse(tokens).map_err(|e| format!("Parsing failed: {}", e))?;
+    let verdict = checker::check(&ast);
+    match (well_typed, verdict.as_ref()) {
+        (true, Err(e)) => return Err(format!("The checker rejected a well-typed program:\n{}", e)),
+        (false, Ok(())) => return Err("The checker accepted an ill-typed program".to_string()),
+        _ => (),
+    }
+
+    let unchecked = run_with_fuel(Ast { statements: ast.statements.clone() }, bigint);
+    if let Err(ref e) = unchecked {
+        if is_type_error(e) && verdict.is_ok() {
+            return Err(format!("The checker accepted a program that failed with: {}", e));
+        }
+    }
+    if verdict.is_err() {
+        return Ok(());
+    }
+
+    let mut folded = ast;
+    if constants::fold(&mut folded, bigint).is_err() {
+        return Ok(());
+    }
+    let mut optimized = Ast { statements: folded.statements.clone() };
+    optimizer::optimize(&mut optimized, bigint);
+    let results = [unchecked, run_with_fuel(folded, bigint), run_with_fuel(optimized, bigint)];
+    if results.iter().all(|result| !result.as_ref().is_err_and(|e| e.starts_with(OUT_OF_FUEL)))
+        && (results[0] != results[1] || results[1] != results[2])
+    {
+        return Err(format!(
+            "The program failed differently when run as written, with folded constants and optimised: {:?}",
+            results,
+        ));
+    }
+    Ok(())
+}
+
+/// Feed the source through the pipeline as far as it goes, ignoring the errors.
+fn run_pipeline(source: &str, bigint: bool) {
+    let mut ast = match scanner::scan(source, bigint).and_then(parser::parse) {
+        Ok(ast) => ast,
+        Err(_) => return,
+    };
+    if checker::check(&ast).is_ok() && constants::fold(&mut ast, bigint).is_ok() {
+        optimizer::optimize(&mut ast, bigint);
+        let _ = run_with_fuel(ast, bigint);
+    }
+}
+
+/// The source with a few random edits: a character is deleted, a piece of another program is
+/// inserted, or a part of the source is repeated.
+fn mutate(source: &str, random: &mut Random) -> String {
+    const PIECES: [&str; 22] = [
+        "\"", "\\", "..", ":=", ":", "1e", "1e400", "9999999999", "2.", "(", ")", ";", "end", "-", "!", "\n",
+        "ä", "//", "/*", "*/", "a[", "do",
+    ];
+    let mut chars: Vec<char> = source.chars().collect();
+    for _ in 0..1 + random.below(3) {
+        let at = random.below(chars.len() + 1);
+        match random.below(3) {
+            0 if at < chars.len() => { chars.remove(at); },
+            1 => {
+                let length = random.below(8).min(chars.len() - at);
+                let repeated: Vec<char> = chars[at..at + length].to_vec();
+                chars.splice(at..at, repeated);
+            },
+            _ => { chars.splice(at..at, random.choose(&PIECES).chars()); },
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// Generate and check programs with the seeds. Returns the failures, each with its seed and
+/// source. A panic is a failure.
+fn fuzz(seeds: std::ops::Range<u64>, ill_typed: bool) -> Vec<String> {
+    let mut failures = Vec::new();
+    for seed in seeds {
+        let mut generator = generator::Generator::new(seed);
+        let source = printer::print(&generator.program(ill_typed));
+        let bigint = seed % 4 == 0;
+        let result = panic::catch_unwind(|| check_agreement(&source, !ill_typed, bigint))
+            .unwrap_or_else(|_| Err("Panicked".to_string()));
+        if let Err(e) = result {
+            failures.push(format!("seed {}{}: {}\n{}", seed, if bigint { " with --bigint" } else { "" }, e, source));
+        }
+    }
+    failures
+}
+
+/// Edit generated programs with the seeds and run them. Returns the ones that panicked, each
+/// with its seed and source.
+fn fuzz_mutations(seeds: std::ops::Range<u64>) -> Vec<String> {
+    let mut failures = Vec::new();
+    for seed in seeds {
+        let mut generator = generator::Generator::new(seed);
+        let source = printer::print(&generator.program(seed % 2 == 0));
+        let source = mutate(&source, &mut Random::new(seed));
+        let bigint = seed % 4 == 1;
+        if panic::catch_unwind(|| run_pipeline(&source, bigint)).is_err() {
+            failures.push(format!("seed {}{}: Panicked\n{}", seed, if bigint { " with --bigint" } else { "" }, source));
+        }
+    }
+    failures
+}
+
+/// The seeds to fuzz with, set by the environment variables.
+fn seeds(default_runs: u64) -> std::ops::Range<u64> {
+    let variable = |name: &str, default: u64| env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default);
+    let seed = variable("PLINT_FUZZ_SEED", 0);
+    seed..seed + variable("PLINT_FUZZ_RUNS", default_runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn well_typed_programs() {
+        let failures = fuzz(seeds(300), false);
+        assert!(failures.is_empty(), "{} failures, the first one:\n{}", failures.len(), failures[0]);
+    }
+
+    #[test]
+    fn ill_typed_programs() {
+        let failures = fuzz(seeds(300), true);
+        assert!(failures.is_empty(), "{} failures, the first one:\n{}", failures.len(), failures[0]);
+    }
+
+    #[test]
+    fn edited_programs() {
+        let failures = fuzz_mutations(seeds(300));
+        assert!(failures.is_empty(), "{} failures, the first one:\n{}", failures.len(), failures[0]);
+    }
+
+    #[test]
+    fn fuel_stops_endless_programs() {
+        let endless = "var s : string := \"ab\";\nwhile true do s := s + s; end while;\n";
+        let ast = parser::parse(scanner::scan(endless, false).unwrap()).unwrap();
+        assert_eq!(run_with_fuel(ast, false), Err("Out of fuel: a value of 16384 characters".to_string()));
+        let ast = parser::parse(scanner::scan("while true do end while;", false).unwrap()).unwrap();
+        assert_eq!(run_with_fuel(ast, false), Err(OUT_OF_FUEL.to_string()));
+    }
+}
@@ -0,0 +1,264 @@
+use super::MplValue;
+use super::type_error;
+use super::super::parser::ast::{format_real, BigInt, MplType};
+
+/// A builtin function. Builtins are called like the functions of the program, and the checker
+/// checks their arguments against the parameter types. Strings are indexed by character,
+/// counting from 0.
+pub struct Builtin {
+    pub identifier: &'static str,
+    pub parameters: &'static [MplType],
+    pub return_type: MplType,
+    function: fn(Vec<MplValue>) -> Result<MplValue, String>,
+}
+
+impl Builtin {
+    /// Call the builtin with arguments of the parameter types. The number of arguments is
+    /// checked here, and their types when they are taken apart. A builtin may return an
+    /// integer of any size, which is an overflow unless `bigint` is set.
+    pub fn call(&self, arguments: Vec<MplValue>, bigint: bool) -> Result<MplValue, String> {
+        if arguments.len() != self.parameters.len() {
+            return Err(type_error(format!("{} expects {} arguments, got {}", self.identifier, self.parameters.len(), arguments.len())));
+        }
+        match (self.function)(arguments)? {
+            MplValue::BigInt(i) if !bigint
+                => Err(format!("Integer overflow: the result {} of {} does not fit in 32 bits", i, self.identifier)),
+            value => Ok(value),
+        }
+    }
+}
+
+/// Find the builtin function with the identifier.
+pub fn builtin(identifier: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.identifier == identifier)
+}
+
+/// The table of builtin functions.
+pub static BUILTINS: [Builtin; 13] = [
+    Builtin { identifier: "length", parameters: &[MplType::String], return_type: MplType::Int, function: length },
+    Builtin {
+        identifier: "substring",
+        parameters: &[MplType::String, MplType::Int, MplType::Int],
+        return_type: MplType::String,
+        function: substring,
+    },
+    Builtin {
+        identifier: "index_of",
+        parameters: &[MplType::String, MplType::String],
+        return_type: MplType::Int,
+        function: index_of,
+    },
+    Builtin { identifier: "to_upper", parameters: &[MplType::String], return_type: MplType::String, function: to_upper },
+    Builtin { identifier: "to_lower", parameters: &[MplType::String], return_type: MplType::String, function: to_lower },
+    Builtin {
+        identifier: "char_at",
+        parameters: &[MplType::String, MplType::Int],
+        return_type: MplType::String,
+        function: char_at,
+    },
+    Builtin {
+        identifier: "int_to_string",
+        parameters: &[MplType::Int],
+        return_type: MplType::String,
+        function: int_to_string,
+    },
+    Builtin {
+        identifier: "string_to_int",
+        parameters: &[MplType::String],
+        return_type: MplType::Int,
+        function: string_to_int,
+    },
+    Builtin { identifier: "int_to_real", parameters: &[MplType::Int], return_type: MplType::Real, function: int_to_real },
+    Builtin { identifier: "real_to_int", parameters: &[MplType::Real], return_type: MplType::Int, function: real_to_int },
+    Builtin {
+        identifier: "real_to_string",
+        parameters: &[MplType::Real],
+        return_type: MplType::String,
+        function: real_to_string,
+    },
+    Builtin {
+        identifier: "string_to_real",
+        parameters: &[MplType::String],
+        return_type: MplType::Real,
+        function: string_to_real,
+    },
+    Builtin {
+        identifier: "bool_to_string",
+        parameters: &[MplType::Bool],
+        return_type: MplType::String,
+        function: bool_to_string,
+    },
+];
+
+/// Helper. Take the arguments apart. The checker has checked their number and types.
+fn string(arguments: &mut Vec<MplValue>) -> Result<String, String> {
+    arguments.remove(0).to_string()
+}
+
+fn int(arguments: &mut Vec<MplValue>) -> Result<i32, String> {
+    arguments.remove(0).to_int()
+}
+
+/// length(s) is the number of characters in s.
+fn length(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    Ok(MplValue::Int(string(&mut arguments)?.chars().count() as i32))
+}
+
+/// substring(s, from, len) is the len characters of s starting at index from.
+fn substring(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    let s = string(&mut arguments)?;
+    let (from, len) = (int(&mut arguments)?, int(&mut arguments)?);
+    let length = s.chars().count();
+    if from < 0 || len < 0 || from as usize + len as usize > length {
+        return Err(format!("substring({}, {}) is out of bounds for a string of length {}", from, len, length));
+    }
+    Ok(MplValue::String(s.chars().skip(from as usize).take(len as usize).collect()))
+}
+
+/// index_of(s, t) is the index of the first occurrence of t in s, or -1 if t does not occur.
+fn index_of(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    let (s, t) = (string(&mut arguments)?, string(&mut arguments)?);
+    let index = match s.find(&t) {
+        Some(byte) => s[..byte].chars().count() as i32,
+        None => -1,
+    };
+    Ok(MplValue::Int(index))
+}
+
+fn to_upper(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    Ok(MplValue::String(string(&mut arguments)?.to_uppercase()))
+}
+
+fn to_lower(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    Ok(MplValue::String(string(&mut arguments)?.to_lowercase()))
+}
+
+/// char_at(s, i) is the character of s at index i, as a string.
+fn char_at(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    let (s, i) = (string(&mut arguments)?, int(&mut arguments)?);
+    let c = if i < 0 { None } else { s.chars().nth(i as usize) };
+    match c {
+        Some(c) => Ok(MplValue::String(c.to_string())),
+        None => Err(format!("char_at({}) is out of bounds for a string of length {}", i, s.chars().count())),
+    }
+}
+
+fn int_to_string(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    Ok(MplValue::String(arguments.remove(0).to_big()?.to_string()))
+}
+
+/// string_to_int(s) is the integer written in s. Fails if s is not an integer.
+fn string_to_int(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    let s = string(&mut arguments)?;
+    match BigInt::parse(&s) {
+        Some(i) => Ok(MplValue::from_big(i)),
+        None => Err(format!("string_to_int cannot convert {:?}: not an integer", s)),
+    }
+}
+
+fn int_to_real(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    Ok(MplValue::Real(arguments.remove(0).to_big()?.to_f64()))
+}
+
+/// real_to_int(r) is r rounded towards zero. Fails if r is infinite or NaN.
+fn real_to_int(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    let r = arguments.remove(0).to_real()?;
+    if !r.is_finite() {
+        return Err(format!("real_to_int cannot convert {}", format_real(r)));
+    }
+    Ok(MplValue::from_big(BigInt::from_f64(r)))
+}
+
+/// real_to_string(r) is r as it is printed.
+fn real_to_string(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    Ok(MplValue::String(format_real(arguments.remove(0).to_real()?)))
+}
+
+/// string_to_real(s) is the real written in s. Fails if s is not a real.
+fn string_to_real(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    let s = string(&mut arguments)?;
+    match s.parse() {
+        Ok(r) => Ok(MplValue::Real(r)),
+        Err(_) => Err(format!("string_to_real cannot convert {:?}: not a real", s)),
+    }
+}
+
+fn bool_to_string(mut arguments: Vec<MplValue>) -> Result<MplValue, String> {
+    Ok(MplValue::String(arguments.remove(0).to_bool()?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(identifier: &str, arguments: Vec<MplValue>) -> Result<MplValue, String> {
+        builtin(identifier).unwrap().call(arguments, false)
+    }
+
+    fn s(s: &str) -> MplValue {
+        MplValue::String(s.to_string())
+    }
+
+    #[test]
+    fn string_functions() {
+        assert_eq!(call("length", vec![s("häst")]), Ok(MplValue::Int(4)));
+        assert_eq!(call("substring", vec![s("häst"), MplValue::Int(1), MplValue::Int(2)]), Ok(s("äs")));
+        assert_eq!(call("substring", vec![s("häst"), MplValue::Int(4), MplValue::Int(0)]), Ok(s("")));
+        assert_eq!(
+            call("substring", vec![s("häst"), MplValue::Int(3), MplValue::Int(2)]),
+            Err("substring(3, 2) is out of bounds for a string of length 4".to_string()),
+        );
+        assert_eq!(call("index_of", vec![s("häst"), s("st")]), Ok(MplValue::Int(2)));
+        assert_eq!(call("index_of", vec![s("häst"), s("x")]), Ok(MplValue::Int(-1)));
+        assert_eq!(call("to_upper", vec![s("häst")]), Ok(s("HÄST")));
+        assert_eq!(call("to_lower", vec![s("HäST")]), Ok(s("häst")));
+        assert_eq!(call("char_at", vec![s("häst"), MplValue::Int(1)]), Ok(s("ä")));
+        assert_eq!(
+            call("char_at", vec![s("häst"), MplValue::Int(-1)]),
+            Err("char_at(-1) is out of bounds for a string of length 4".to_string()),
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(call("int_to_string", vec![MplValue::Int(-12)]), Ok(s("-12")));
+        assert_eq!(call("string_to_int", vec![s("-12")]), Ok(MplValue::Int(-12)));
+        assert_eq!(
+            call("string_to_int", vec![s("12a")]),
+            Err("string_to_int cannot convert \"12a\": not an integer".to_string()),
+        );
+        assert_eq!(
+            call("string_to_int", vec![s("99999999999")]),
+            Err("Integer overflow: the result 99999999999 of string_to_int does not fit in 32 bits".to_string()),
+        );
+        assert_eq!(call("bool_to_string", vec![MplValue::Bool(true)]), Ok(s("true")));
+        assert_eq!(call("int_to_real", vec![MplValue::Int(-3)]), Ok(MplValue::Real(-3.0)));
+        assert_eq!(call("real_to_int", vec![MplValue::Real(-3.9)]), Ok(MplValue::Int(-3)));
+        assert_eq!(
+            call("real_to_int", vec![MplValue::Real(1e10)]),
+            Err("Integer overflow: the result 10000000000 of real_to_int does not fit in 32 bits".to_string()),
+        );
+        assert_eq!(call("real_to_int", vec![MplValue::Real(f64::NAN)]), Err("real_to_int cannot convert NaN".to_string()));
+        assert_eq!(call("real_to_string", vec![MplValue::Real(0.5)]), Ok(s("0.5")));
+        assert_eq!(call("string_to_real", vec![s("2.5e3")]), Ok(MplValue::Real(2500.0)));
+        assert_eq!(call("string_to_real", vec![s("1,5")]), Err("string_to_real cannot convert \"1,5\": not a real".to_string()));
+    }
+
+    #[test]
+    fn conversions_with_bigint() {
+        let call = |identifier: &str, argument: MplValue| builtin(identifier).unwrap().call(vec![argument], true);
+        let big = |digits: &str| Ok(MplValue::BigInt(BigInt::parse(digits).unwrap()));
+        assert_eq!(call("string_to_int", s("-99999999999")), big("-99999999999"));
+        assert_eq!(call("string_to_int", s("2147483647")), Ok(MplValue::Int(i32::MAX)));
+        assert_eq!(call("real_to_int", MplValue::Real(-1e10)), big("-10000000000"));
+        assert_eq!(call("int_to_string", big("99999999999").unwrap()), Ok(s("99999999999")));
+    }
+
+    #[test]
+    fn wrong_arguments() {
+        assert_eq!(call("length", Vec::new()), Err("Type error: length expects 1 arguments, got 0".to_string()));
+        assert_eq!(call("length", vec![MplValue::Int(1)]), Err("Type error: Expected string, got integer".to_string()));
+        assert!(super::super::is_type_error(&call("length", Vec::new()).unwrap_err()));
+        assert!(!super::super::is_type_error(&call("string_to_int", vec![s("Expected ")]).unwrap_err()));
+    }
+}
@@ -0,0 +1,202 @@
+use super::{Interpreter, LoopFrame, Monitor};
+use super::super::parser::ast::*;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+
+/// How many times each outcome of a loop, an if statement or an assert statement was
+/// observed. For a loop the outcomes are "ran zero iterations" and "ran one or more
+/// iterations", for an if statement "took the then branch" and "took the else branch", and
+/// for an assert statement "held" and "failed".
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Branches {
+    first: usize,
+    second: usize,
+}
+
+/// A monitor that records which statements are executed and how many times, how many times the
+/// loops ran zero or more iterations, and the outcomes of the if and assert statements. When the
+/// program ends, the monitor writes an annotated copy of the source to standard error, and/or
+/// an LCOV tracefile.
+pub struct Coverage {
+    filename: String,
+    lines: Vec<String>,
+    statements: BTreeMap<Position, usize>,
+    loops: BTreeMap<Position, Branches>,
+    whiles: BTreeMap<Position, Branches>,
+    ifs: BTreeMap<Position, Branches>,
+    asserts: BTreeMap<Position, Branches>,
+    annotate: bool,
+    lcov: Option<String>,
+}
+
+impl Coverage {
+    /// Create a coverage monitor for the program. Every statement of the AST is counted as
+    /// not executed until the interpreter executes it. If `annotate` is set, the annotated
+    /// source is written when the program ends, and if `lcov` is given, a tracefile is written
+    /// to that path.
+    pub fn new(filename: &str, source: &str, ast: &Ast, annotate: bool, lcov: Option<String>) -> Coverage {
+        let mut coverage = Coverage {
+            filename: filename.to_string(),
+            lines: source.lines().map(|line| line.to_string()).collect(),
+            statements: BTreeMap::new(),
+            loops: BTreeMap::new(),
+            whiles: BTreeMap::new(),
+            ifs: BTreeMap::new(),
+            asserts: BTreeMap::new(),
+            annotate,
+            lcov,
+        };
+        coverage.visit_ast(ast);
+        coverage
+    }
+
+    /// The execution count of each line that has statements. The count of a line is the
+    /// largest count of the statements on it.
+    fn line_counts(&self) -> BTreeMap<usize, usize> {
+        let mut counts = BTreeMap::new();
+        for (position, &count) in &self.statements {
+            let line_count = counts.entry(position.line).or_insert(0);
+            *line_count = count.max(*line_count);
+        }
+        counts
+    }
+
+    /// The annotated source. Each line is prefixed by its execution count, "#####" if it has
+    /// statements that were never executed, or "-" if it has no statements. Lines with
+    /// loops, if statements or assert statements are followed by the counts of their outcomes.
+    pub fn annotated_source(&self) -> String {
+        let counts = self.line_counts();
+        let mut report = String::new();
+        for (i, text) in self.lines.iter().enumerate() {
+            let line = i + 1;
+            let count = match counts.get(&line) {
+                Some(&0) => "#####".to_string(),
+                Some(count) => count.to_string(),
+                None => "-".to_string(),
+            };
+            report.push_str(&format!("{:>9}:{:>5}:{}", count, line, text));
+
+            let mut notes = Vec::new();
+            for (_, branches) in self.loops.range(Position::new(line, 0) .. Position::new(line + 1, 0)) {
+                notes.push(format!("for: 0 iterations {}x, 1+ iterations {}x", branches.first, branches.second));
+            }
+            for (_, branches) in self.whiles.range(Position::new(line, 0) .. Position::new(line + 1, 0)) {
+                notes.push(format!("while: 0 iterations {}x, 1+ iterations {}x", branches.first, branches.second));
+            }
+            for (_, branches) in self.ifs.range(Position::new(line, 0) .. Position::new(line + 1, 0)) {
+                notes.push(format!("if: then {}x, else {}x", branches.first, branches.second));
+            }
+            for (_, branches) in self.asserts.range(Position::new(line, 0) .. Position::new(line + 1, 0)) {
+                notes.push(format!("assert: true {}x, false {}x", branches.first, branches.second));
+            }
+            if !notes.is_empty() {
+                report.push_str(&format!("    [{}]", notes.join("; ")));
+            }
+            report.push('\n');
+        }
+        report
+    }
+
+    /// The coverage in the LCOV tracefile format. Every loop, if statement and assert
+    /// statement is a block with two branches, in the order described in `Branches`.
+    pub fn lcov_report(&self) -> String {
+        let mut report = format!("TN:\nSF:{}\n", self.filename);
+
+        let mut branches: Vec<(&Position, &Branches)> = self.loops.iter()
+            .chain(self.whiles.iter())
+            .chain(self.ifs.iter())
+            .chain(self.asserts.iter())
+            .collect();
+        branches.sort_by_key(|&(position, _)| *position);
+        let mut hit = 0;
+        for (block, &(position, outcome)) in branches.iter().enumerate() {
+            let executed = self.statements.get(position).cloned().unwrap_or(0) > 0;
+            for (branch, &taken) in [outcome.first, outcome.second].iter().enumerate() {
+                let taken = if executed { taken.to_string() } else { "-".to_string() };
+                report.push_str(&format!("BRDA:{},{},{},{}\n", position.line, block, branch, taken));
+            }
+            hit += [outcome.first, outcome.second].iter().filter(|&&taken| taken > 0).count();
+        }
+        report.push_str(&format!("BRF:{}\nBRH:{}\n", branches.len() * 2, hit));
+
+        let counts = self.line_counts();
+        for (line, count) in &counts {
+            report.push_str(&format!("DA:{},{}\n", line, count));
+        }
+        let lines_hit = counts.values().filter(|&&count| count > 0).count();
+        report.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", counts.len(), lines_hit));
+        report
+    }
+}
+
+/// Registering the statements of the program, and the branches of its loops, ifs and
+/// assertions, as not executed.
+impl Visitor for Coverage {
+    fn visit_statement(&mut self, statement: &Located<Statement>) {
+        self.statements.insert(statement.position, 0);
+        let branches = match statement.node {
+            Statement::For { .. } => Some(&mut self.loops),
+            Statement::While { .. } => Some(&mut self.whiles),
+            Statement::If { .. } => Some(&mut self.ifs),
+            Statement::Assert(_) => Some(&mut self.asserts),
+            _ => None,
+        };
+        if let Some(branches) = branches {
+            branches.insert(statement.position, Branches::default());
+        }
+        walk_statement(self, statement);
+    }
+}
+
+impl Monitor for Coverage {
+    fn before_statement(&mut self, _interpreter: &mut Interpreter, statement: &Located<Statement>) -> Result<(), String> {
+        *self.statements.entry(statement.position).or_insert(0) += 1;
+        Ok(())
+    }
+
+    fn after_loop(&mut self, _interpreter: &mut Interpreter, frame: &LoopFrame) -> Result<(), String> {
+        let loops = if frame.identifier.is_some() { &mut self.loops } else { &mut self.whiles };
+        let branches = loops.entry(frame.position).or_default();
+        if frame.iteration == 0 {
+            branches.first += 1;
+        } else {
+            branches.second += 1;
+        }
+        Ok(())
+    }
+
+    fn condition(&mut self, interpreter: &mut Interpreter, holds: bool) -> Result<(), String> {
+        let branches = self.ifs.entry(interpreter.position()).or_default();
+        if holds {
+            branches.first += 1;
+        } else {
+            branches.second += 1;
+        }
+        Ok(())
+    }
+
+    fn assertion(&mut self, interpreter: &mut Interpreter, holds: bool) -> Result<(), String> {
+        let branches = self.asserts.entry(interpreter.position()).or_default();
+        if holds {
+            branches.first += 1;
+        } else {
+            branches.second += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _interpreter: &mut Interpreter) -> Result<(), String> {
+        if self.annotate {
+            write!(io::stderr(), "{}", self.annotated_source()).map_err(|e| e.to_string())?;
+        }
+        if let Some(ref path) = self.lcov {
+            File::create(path)
+                .and_then(|mut file| file.write_all(self.lcov_report().as_bytes()))
+                .map_err(|e| format!("Unable to write {}: {}", path, e))?;
+        }
+        Ok(())
+    }
+}
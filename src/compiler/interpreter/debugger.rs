@@ -0,0 +1,201 @@
+use super::{Frame, Interpreter, Monitor};
+use super::super::parser;
+use super::super::parser::ast::*;
+use super::super::scanner;
+
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Write;
+
+const HELP: &str = "\
+Commands:
+  break <line>          b   Stop whenever a statement on the line is about to be executed
+  delete <line>         d   Remove the breakpoint on the line
+  step                  s   Execute the next statement, stepping into loops and calls
+  next                  n   Execute the next statement, stepping over the bodies of loops and calls
+  continue              c   Run until the next breakpoint
+  print <expr>          p   Evaluate an expression and print its value
+  set <id> := <expr>        Assign a new value to a variable
+  vars                  v   Print all variables
+  watch <expr>          w   Print the value of the expression whenever the program stops
+  unwatch <n>               Remove the watch expression number n
+  where                     Print the current line, the running loops and the calls
+  list                  l   Print the source around the current line
+  help                  h   Print this help
+  quit                  q   Stop the program";
+
+/// How far the program runs before the debugger stops it again.
+enum Mode {
+    Step,
+    Next(usize), // stop at a stack depth of at most the given depth
+    Continue,
+}
+
+/// A command-line debugger. The debugger stops the program before statements, and reads
+/// commands from standard input until the user resumes the execution.
+pub struct Debugger {
+    lines: Vec<String>,
+    breakpoints: BTreeSet<usize>,
+    watches: Vec<(String, Expression)>,
+    mode: Mode,
+}
+
+impl Debugger {
+    /// Create a debugger for the program with the given source. The debugger stops before the
+    /// first statement.
+    pub fn new(source: &str) -> Debugger {
+        Debugger {
+            lines: source.lines().map(|line| line.to_string()).collect(),
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            mode: Mode::Step,
+        }
+    }
+
+    fn should_stop(&self, interpreter: &Interpreter, line: usize) -> bool {
+        let stop = match self.mode {
+            Mode::Step => true,
+            Mode::Next(depth) => interpreter.stack().len() <= depth,
+            Mode::Continue => false,
+        };
+        stop || self.breakpoints.contains(&line)
+    }
+
+    /// Print where the program stopped, the running loops and the watch expressions.
+    fn show_stop(&self, interpreter: &mut Interpreter, line: usize) {
+        println!("Stopped at line {}: {}", line, self.source_line(line).trim());
+        self.show_stack(interpreter);
+        for (i, (text, expr)) in self.watches.iter().enumerate() {
+            match interpreter.evaluate_expression(expr) {
+                Ok(value) => println!("  watch {}: {} = {}", i + 1, text, value),
+                Err(e) => println!("  watch {}: {} : {}", i + 1, text, e),
+            }
+        }
+    }
+
+    fn show_stack(&self, interpreter: &Interpreter) {
+        for frame in interpreter.stack() {
+            match frame {
+                Frame::Loop(frame) => match frame.identifier {
+                    // The control variable of a loop in a calling procedure is not visible.
+                    Some(ref identifier) => match interpreter.value(identifier) {
+                        Some(value) => println!("  for {}: iteration {} ({} = {})", identifier, frame.iteration, identifier, value),
+                        None => println!("  for {}: iteration {}", identifier, frame.iteration),
+                    },
+                    None => println!("  while: iteration {}", frame.iteration),
+                },
+                Frame::Call(call) => println!("  in {} called at {}", call.identifier, call.position),
+            }
+        }
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        self.lines.get(line.wrapping_sub(1)).map(|line| line.as_str()).unwrap_or("")
+    }
+
+    fn list(&self, current: usize) {
+        let first = if current > 3 { current - 3 } else { 1 };
+        for line in first ..= (current + 3).min(self.lines.len()) {
+            let marker = if line == current { "->" } else if self.breakpoints.contains(&line) { " *" } else { "  " };
+            println!("{} {:4} {}", marker, line, self.source_line(line));
+        }
+    }
+
+    /// Read and execute commands until the user resumes the program.
+    fn prompt(&mut self, interpreter: &mut Interpreter, line: usize) -> Result<(), String> {
+        loop {
+            print!("(plint) ");
+            io::stdout().flush().map_err(|e| e.to_string())?;
+
+            let mut input = String::new();
+            match io::stdin().read_line(&mut input) {
+                Ok(0) => {
+                    // No more commands, let the program run to the end.
+                    self.breakpoints.clear();
+                    self.mode = Mode::Continue;
+                    return Ok(());
+                },
+                Ok(_) => (),
+                Err(e) => return Err(format!("IO error: {}", e)),
+            }
+
+            let input = input.trim();
+            let (command, argument) = match input.find(' ') {
+                Some(i) => (&input[..i], input[i..].trim()),
+                None => (input, ""),
+            };
+            match command {
+                "step" | "s" => { self.mode = Mode::Step; return Ok(()) },
+                "next" | "n" => { self.mode = Mode::Next(interpreter.stack().len()); return Ok(()) },
+                "continue" | "c" => { self.mode = Mode::Continue; return Ok(()) },
+                "quit" | "q" => return Err("Program stopped by the debugger".to_string()),
+                "break" | "b" => match argument.parse() {
+                    Ok(line) => { self.breakpoints.insert(line); println!("Breakpoint at line {}", line) },
+                    Err(_) => println!("Expected a line number, got '{}'", argument),
+                },
+                "delete" | "d" => match argument.parse() {
+                    Ok(line) if self.breakpoints.remove(&line) => println!("Removed breakpoint at line {}", line),
+                    _ => println!("No breakpoint at line '{}'", argument),
+                },
+                "print" | "p" => match parse_expression(argument)
+                    .and_then(|expr| interpreter.evaluate_expression(&expr)) {
+                    Ok(value) => println!("{} = {}", argument, value),
+                    Err(e) => println!("{}", e),
+                },
+                "set" => match self.set(interpreter, argument) {
+                    Ok(()) => (),
+                    Err(e) => println!("{}", e),
+                },
+                "vars" | "v" => for (name, value) in interpreter.bindings() {
+                    println!("  {} : {} = {}", name, value.mpl_type(), value);
+                },
+                "watch" | "w" => match parse_expression(argument) {
+                    Ok(expr) => {
+                        self.watches.push((argument.to_string(), expr));
+                        println!("Watch {}: {}", self.watches.len(), argument);
+                    },
+                    Err(e) => println!("{}", e),
+                },
+                "unwatch" => match argument.parse::<usize>() {
+                    Ok(n) if n >= 1 && n <= self.watches.len() => { self.watches.remove(n - 1); },
+                    _ => println!("No watch expression '{}'", argument),
+                },
+                "where" => {
+                    println!("Line {}: {}", line, self.source_line(line).trim());
+                    self.show_stack(interpreter);
+                },
+                "list" | "l" => self.list(line),
+                "help" | "h" => println!("{}", HELP),
+                "" => (),
+                _ => println!("Unknown command '{}'. Type help for a list of commands.", command),
+            }
+        }
+    }
+
+    /// Execute the set command. The argument has the form `<identifier> := <expression>`.
+    fn set(&self, interpreter: &mut Interpreter, argument: &str) -> Result<(), String> {
+        let i = argument.find(":=").ok_or("Usage: set <identifier> := <expression>")?;
+        let identifier = argument[..i].trim();
+        let value = parse_expression(&argument[i + 2..])
+            .and_then(|expr| interpreter.evaluate_expression(&expr))?;
+        interpreter.set_value(identifier, value)
+    }
+}
+
+impl Monitor for Debugger {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, statement: &Located<Statement>) -> Result<(), String> {
+        let line = statement.position.line;
+        if !self.should_stop(interpreter, line) {
+            return Ok(());
+        }
+        self.show_stop(interpreter, line);
+        self.prompt(interpreter, line)
+    }
+}
+
+/// Parse an expression typed by the user.
+fn parse_expression(text: &str) -> Result<Expression, String> {
+    // The scanner only finishes a token when it sees the character following it.
+    let tokens = scanner::scan(&format!("{}\n", text), false)?;
+    parser::parse_expression(tokens)
+}
@@ -1,52 +1,135 @@
-
 use super::MplValue;
+use super::type_error;
+use super::super::parser::ast::{BigInt, Operator};
+
+use std::cmp::Ordering;
 
-/// An MplFunction represents the function defined by the operator
+/// An MplFunction represents the function defined by the operator. Integers are 32 bits, and
+/// an overflow is an error, unless `bigint` is set, when they are unbounded.
 pub struct MplFunction {
-    pub sign: char,
+    pub sign: Operator,
+    pub bigint: bool,
 }
 
 impl MplFunction {
     /// Call a unary function. Perform type checking. If the operator is not a unary operator, return an error.
     pub fn call_unary(&self, operand: MplValue) -> Result<MplValue, String> {
         match self.sign {
-            '!' => Ok(MplValue::Bool(!operand.to_bool()?)),
-            // '-' => Ok(MplValue::Int(-operand.to_int()?)),
-            _ => Err(format!("Operator {} is not a unary operator", self.sign)),
+            Operator::Not => Ok(MplValue::Bool(!operand.to_bool()?)),
+            _ => Err(type_error(format!("Operator {} is not a unary operator", self.sign))),
         }
     }
 
     /// Call a binary function. Performs type checking. If the operator is not a binary operator, return an error.
+    /// The short-circuiting of & and | is left to the caller, these evaluate both operands.
     pub fn call(&self, left: MplValue, right: MplValue) -> Result<MplValue, String>{
         match self.sign {
-            '+' => plus(left, right),
-            '-' => Ok(MplValue::Int(left.to_int()? - right.to_int()?)),
-            '/' => Ok(MplValue::Int(left.to_int()? / right.to_int()?)),
-            '*' => Ok(MplValue::Int(left.to_int()? * right.to_int()?)),
-            '&' => Ok(MplValue::Bool(left.to_bool()? && right.to_bool()?)),
-            '=' => Ok(MplValue::Bool(left == right)),
-            '<' => compare(left, right),
-            '!' => Err("! is a unary operator".to_string()),
-            wrong => Err(format!("Unknown operator: {}", wrong)),
+            Operator::Plus => self.plus(left, right),
+            Operator::Minus => self.arithmetic(left, right, i32::checked_sub, BigInt::subtract, |l, r| l - r),
+            Operator::Divide => self.divide(left, right, i32::checked_div, |l, r| l.divide(r).0, |l, r| l / r),
+            Operator::Modulo => self.divide(left, right, i32::checked_rem, |l, r| l.divide(r).1, |l, r| l % r),
+            Operator::Times => self.arithmetic(left, right, i32::checked_mul, BigInt::multiply, |l, r| l * r),
+            Operator::And => Ok(MplValue::Bool(left.to_bool()? && right.to_bool()?)),
+            Operator::Or => Ok(MplValue::Bool(left.to_bool()? || right.to_bool()?)),
+            Operator::Equal => Ok(MplValue::Bool(left == right)),
+            Operator::NotEqual => Ok(MplValue::Bool(left != right)),
+            Operator::Less => Ok(MplValue::Bool(compare(left, right)? == Some(Ordering::Less))),
+            Operator::LessEqual => Ok(MplValue::Bool(matches!(compare(left, right)?, Some(Ordering::Less | Ordering::Equal)))),
+            Operator::Greater => Ok(MplValue::Bool(compare(left, right)? == Some(Ordering::Greater))),
+            Operator::GreaterEqual => Ok(MplValue::Bool(matches!(compare(left, right)?, Some(Ordering::Greater | Ordering::Equal)))),
+            Operator::Not => Err(type_error("! is a unary operator")),
+        }
+    }
+
+    /// Helper function for the arithmetic of integers and of reals. Integers are computed in
+    /// 32 bits, and with BigInts only if the result does not fit and bigint is set.
+    fn arithmetic<I, B, R>(&self, left: MplValue, right: MplValue, int: I, big: B, real: R) -> Result<MplValue, String>
+        where I: Fn(i32, i32) -> Option<i32>, B: Fn(&BigInt, &BigInt) -> BigInt, R: Fn(f64, f64) -> f64
+    {
+        match (left, right) {
+            (MplValue::Real(l), right) => Ok(MplValue::Real(real(l, right.to_real()?))),
+            (MplValue::Int(l), MplValue::Int(r)) => match int(l, r) {
+                Some(i) => Ok(MplValue::Int(i)),
+                None if self.bigint => Ok(MplValue::from_big(big(&BigInt::from(i64::from(l)), &BigInt::from(i64::from(r))))),
+                None => Err(format!("Integer overflow: {} {} {} does not fit in 32 bits", l, self.sign, r)),
+            },
+            (left, right) => Ok(MplValue::from_big(big(&left.to_big()?, &right.to_big()?))),
+        }
+    }
+
+    /// Helper function for division and modulo, which fail if the divisor is zero. Modulo is the
+    /// remainder of the division, which is rounded towards zero, so it has the sign of the dividend.
+    fn divide<I, B, R>(&self, left: MplValue, right: MplValue, int: I, big: B, real: R) -> Result<MplValue, String>
+        where I: Fn(i32, i32) -> Option<i32>, B: Fn(&BigInt, &BigInt) -> BigInt, R: Fn(f64, f64) -> f64
+    {
+        let zero = match right {
+            MplValue::Int(r) => r == 0,
+            MplValue::Real(r) => r == 0.0,
+            _ => false, // a BigInt is never zero
+        };
+        if zero {
+            return Err("Division by zero".to_string());
         }
+        self.arithmetic(left, right, int, big, real)
     }
 
+    /// Helper function for addition. Handles addition of integers, reals and strings.
+    fn plus(&self, left: MplValue, right: MplValue) -> Result<MplValue, String> {
+        match left {
+            MplValue::String(s) => Ok(MplValue::String(format!("{}{}", s, right.to_string()?))),
+            MplValue::Bool(_) => Err(type_error("Expected integer, real or string, got boolean")),
+            MplValue::Array(..) => Err(type_error("Expected integer, real or string, got array")),
+            left => self.arithmetic(left, right, i32::checked_add, BigInt::add, |l, r| l + r),
+        }
+    }
 }
 
-/// Helper functions for comparisons. Internal use only.
-fn compare(left: MplValue, right: MplValue) -> Result<MplValue, String> {
+/// Helper functions for comparisons. Internal use only. Booleans are ordered false < true.
+/// Reals are compared as IEEE numbers, so a NaN is neither smaller, equal nor greater.
+fn compare(left: MplValue, right: MplValue) -> Result<Option<Ordering>, String> {
     match left {
-        MplValue::Int(i) => Ok(MplValue::Bool(i < right.to_int()?)),
-        MplValue::String(s) => Ok(MplValue::Bool(s < right.to_string()?)),
-        MplValue::Bool(b) => Ok(MplValue::Bool(b < right.to_bool()?)),
+        MplValue::Int(i) => match right {
+            MplValue::Int(r) => Ok(Some(i.cmp(&r))),
+            right => Ok(Some(BigInt::from(i64::from(i)).cmp(&right.to_big()?))),
+        },
+        MplValue::BigInt(i) => Ok(Some(i.cmp(&right.to_big()?))),
+        MplValue::Real(r) => Ok(r.partial_cmp(&right.to_real()?)),
+        MplValue::String(s) => Ok(Some(s.cmp(&right.to_string()?))),
+        MplValue::Bool(b) => Ok(Some(b.cmp(&right.to_bool()?))),
+        MplValue::Array(..) => Err(type_error("Arrays cannot be compared")),
     }
 }
 
-/// Helper function for addition. Handles addition of both integers and strings.
-fn plus(left: MplValue, right: MplValue) -> Result<MplValue, String> {
-    match left {
-        MplValue::Int(i) => Ok(MplValue::Int(i + right.to_int()?)),
-        MplValue::String(s) => Ok(MplValue::String(format!("{}{}", s, right.to_string()?))),
-        MplValue::Bool(_) => Err("Expected integer or string, got boolean".to_string()),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(sign: Operator, bigint: bool, left: i32, right: i32) -> Result<MplValue, String> {
+        MplFunction { sign, bigint }.call(MplValue::Int(left), MplValue::Int(right))
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(call(Operator::Times, false, 65536, 32767), Ok(MplValue::Int(2147418112)));
+        assert_eq!(
+            call(Operator::Times, false, 65536, 32768),
+            Err("Integer overflow: 65536 * 32768 does not fit in 32 bits".to_string()),
+        );
+        assert_eq!(
+            call(Operator::Divide, false, i32::MIN, -1),
+            Err("Integer overflow: -2147483648 / -1 does not fit in 32 bits".to_string()),
+        );
+        assert_eq!(call(Operator::Modulo, false, 1, 0), Err("Division by zero".to_string()));
+    }
+
+    #[test]
+    fn bigint_arithmetic() {
+        let big = call(Operator::Plus, true, i32::MAX, 1).unwrap();
+        assert_eq!(big, MplValue::BigInt(BigInt::parse("2147483648").unwrap()));
+        let function = MplFunction { sign: Operator::Minus, bigint: true };
+        // Results that fit in 32 bits are Ints again.
+        assert_eq!(function.call(big.clone(), MplValue::Int(1)), Ok(MplValue::Int(i32::MAX)));
+        let function = MplFunction { sign: Operator::Greater, bigint: true };
+        assert_eq!(function.call(big, MplValue::Int(i32::MAX)), Ok(MplValue::Bool(true)));
     }
 }
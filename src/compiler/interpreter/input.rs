@@ -0,0 +1,158 @@
+use super::super::parser::ast::Position;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+
+/// The first line of a session log.
+const HEADER: &str = "# plint session log";
+
+/// A source of input lines for read statements.
+pub trait Input {
+    /// Read a line for the read statement at `position`, which reads into `identifier`. The
+    /// line is returned without its line terminator.
+    fn read_line(&mut self, identifier: &str, position: Position) -> Result<String, String>;
+
+    /// Called when the program has ended successfully.
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Reads input lines interactively from standard input.
+pub struct Stdin;
+
+impl Input for Stdin {
+    fn read_line(&mut self, _identifier: &str, _position: Position) -> Result<String, String> {
+        let mut line = String::new();
+        if let Err(e) = io::stdin().read_line(&mut line) {
+            return Err(format!("IO error: {}", e));
+        };
+        Ok(line.trim_end_matches(['\n', '\r']).to_string())
+    }
+}
+
+/// Reads input lines from standard input, and records them to a session log. Every entry
+/// of the log is a line with the position of the read statement, the identifier read into
+/// and the input, separated by tabs. Tabs, newlines and backslashes in the input are escaped
+/// with backslashes.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    /// Create a recorder that writes the session log to the path.
+    pub fn create(path: &str) -> Result<Recorder, String> {
+        let mut file = File::create(path).map_err(|e| format!("Unable to create {}: {}", path, e))?;
+        writeln!(file, "{}", HEADER).map_err(|e| e.to_string())?;
+        Ok(Recorder { file })
+    }
+}
+
+impl Input for Recorder {
+    fn read_line(&mut self, identifier: &str, position: Position) -> Result<String, String> {
+        let line = Stdin.read_line(identifier, position)?;
+        // Written immediately, so that the log is complete even if the program fails later.
+        writeln!(self.file, "{}\t{}\t{}", position, identifier, escape(&line))
+            .map_err(|e| format!("Unable to record input: {}", e))?;
+        Ok(line)
+    }
+}
+
+/// An entry of a session log.
+struct Entry {
+    position: String,
+    identifier: String,
+    line: String,
+}
+
+/// Feeds the input lines of a session log to the program. Fails if the program reads in a
+/// different order than recorded, or does not read all of the recorded lines.
+pub struct Replayer {
+    path: String,
+    entries: VecDeque<Entry>,
+}
+
+impl Replayer {
+    /// Load the session log from the path.
+    pub fn open(path: &str) -> Result<Replayer, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        let mut entries = VecDeque::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("Unable to read {}: {}", path, e))?;
+            if line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(3, '\t').collect();
+            if fields.len() != 3 {
+                return Err(format!("{}:{}: malformed session log entry", path, i + 1));
+            }
+            entries.push_back(Entry {
+                position: fields[0].to_string(),
+                identifier: fields[1].to_string(),
+                line: unescape(fields[2]),
+            });
+        }
+        Ok(Replayer { path: path.to_string(), entries })
+    }
+}
+
+impl Input for Replayer {
+    fn read_line(&mut self, identifier: &str, position: Position) -> Result<String, String> {
+        let entry = match self.entries.pop_front() {
+            Some(entry) => entry,
+            None => return Err(format!(
+                "Replay failed: read {} at {}, but {} has no more input",
+                identifier, position, self.path,
+            )),
+        };
+        if entry.identifier != identifier || entry.position != position.to_string() {
+            return Err(format!(
+                "Replay failed: read {} at {}, but {} recorded read {} at {}",
+                identifier, position, self.path, entry.identifier, entry.position,
+            ));
+        }
+        Ok(entry.line)
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        match self.entries.front() {
+            None => Ok(()),
+            Some(entry) => Err(format!(
+                "Replay failed: the program ended, but {} recorded {} more reads, the next one of {} at {}",
+                self.path, self.entries.len(), entry.identifier, entry.position,
+            )),
+        }
+    }
+}
+
+fn escape(line: &str) -> String {
+    line.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(field: &str) -> String {
+    let mut line = String::new();
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('t')) => { line.push('\t'); chars.next(); },
+            ('\\', Some('n')) => { line.push('\n'); chars.next(); },
+            ('\\', Some('\\')) => { line.push('\\'); chars.next(); },
+            _ => line.push(c),
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaping_round_trips() {
+        let line = "a\tb\\n c\\";
+        assert_eq!(escape(line), "a\\tb\\\\n c\\\\");
+        assert_eq!(unescape(&escape(line)), line);
+    }
+}
@@ -2,218 +2,774 @@
 use super::parser::ast::*;
 
 use std::collections::HashMap;
+use std::fmt;
 use std::io;
 use std::io::Write;
-use std::ops::Range;
-use std::error::Error;
+use std::mem;
+use std::rc::Rc;
+use std::slice;
 
+pub mod builtins;
+mod coverage;
+mod debugger;
 mod functions;
+mod input;
+mod monitor;
+mod profiler;
+mod trace;
 mod value;
 
-use self::value::MplValue;
+pub use self::value::MplValue;
+pub use self::monitor::Monitor;
+pub use self::coverage::Coverage;
+pub use self::debugger::Debugger;
+pub use self::input::{Input, Recorder, Replayer};
+pub use self::profiler::Profiler;
+pub use self::trace::{Tracer, TraceFormat};
 
-// pub fn static_analysis(ast: Ast) -> Result<(), String> {
-//     // TODO
-// }
+/// The default limit of nested procedure and function calls.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
 
-/// Evaluate the AST.
-pub fn evaluate(ast: Ast) -> Result<(), String> {
-    let mut interpreter = Interpreter { names: HashMap::new() };
-    for stmt in ast.statements {
-        match interpreter.evaluate_statement(stmt) {
-            Ok(_) => (),
-            Err(e) => return Err(e),
+/// The beginning of the errors that the checker rules out, such as a value of the wrong type or
+/// an undeclared identifier. Only a program that has not been checked fails with them.
+const TYPE_ERROR: &str = "Type error: ";
+
+/// An error that the checker rules out.
+pub fn type_error<M: fmt::Display>(message: M) -> String {
+    format!("{}{}", TYPE_ERROR, message)
+}
+
+/// Whether the error is one that the checker rules out.
+#[cfg(test)]
+pub fn is_type_error(error: &str) -> bool {
+    error.starts_with(TYPE_ERROR)
+}
+
+/// The interpreter holds the bindings of identifiers, the procedures and functions, the loops
+/// and calls currently being executed, the monitors observing the execution, and the source of
+/// input for read statements.
+///
+/// The bindings form a chain of scopes. The body of a loop or an if statement is executed in a
+/// new scope, so its declarations end with it. A call gets a new chain, which only the called
+/// procedure or function can see.
+pub struct Interpreter {
+    scopes: Vec<HashMap<String, MplValue>>, // innermost scope last
+    functions: HashMap<String, Rc<Function>>,
+    loops: Vec<LoopFrame>,
+    calls: Vec<CallFrame>,
+    max_depth: usize,
+    bigint: bool, // whether integers are unbounded instead of 32 bits
+    monitors: Vec<Box<dyn Monitor>>,
+    input: Box<dyn Input>,
+    position: Position, // position of the statement being executed
+}
+
+/// The values that the operands of an expression had when it was evaluated, formatted in the
+/// order in which the operands are visited. The expressions inside the operands are not visited.
+struct OperandValues<'v> {
+    recorded: slice::Iter<'v, Option<MplValue>>,
+    values: Vec<String>,
+}
+
+impl<'v> Visitor for OperandValues<'v> {
+    fn visit_operand(&mut self, _operand: &Operand) {
+        let value = match self.recorded.next() {
+            Some(Some(value)) => format!("{}", value),
+            _ => "?".to_string(), // skipped by the short-circuit
+        };
+        self.values.push(value);
+    }
+}
+
+/// A loop that is currently being executed.
+#[derive(Clone, Debug)]
+pub struct LoopFrame {
+    pub identifier: Option<String>, // control variable of a for loop, None for a while loop
+    pub iteration: usize, // counted from 1
+    pub position: Position, // position of the loop statement
+}
+
+impl LoopFrame {
+    /// Describes the loop as "for <identifier>" or "while".
+    pub fn label(&self) -> String {
+        match self.identifier {
+            Some(ref identifier) => format!("for {}", identifier),
+            None => "while".to_string(),
         }
     }
-    Ok(())
 }
 
-/// The interpreter holds the bindings of identifiers.
-struct Interpreter {
-    names: HashMap<String, MplValue>,
+/// A procedure or function call that is currently being executed.
+#[derive(Clone, Debug)]
+pub struct CallFrame {
+    pub identifier: String,
+    pub position: Position, // position of the statement making the call
+    pub loops: usize, // number of loops being executed when the call was made
+}
+
+/// A frame of the execution stack.
+#[derive(Clone, Copy, Debug)]
+pub enum Frame<'a> {
+    Loop(&'a LoopFrame),
+    Call(&'a CallFrame),
+}
+
+/// How the execution continues after a statement.
+#[derive(Clone, Debug, PartialEq)]
+enum Flow {
+    /// Continue with the next statement.
+    Next,
+    /// Leave the innermost loop.
+    Break,
+    /// Begin the next iteration of the innermost loop.
+    Continue,
+    /// Leave the procedure or function, with the return value of a function.
+    Return(Option<MplValue>),
 }
 
 impl Interpreter {
-    fn evaluate_statement(&mut self, statement: Statement) -> Result<(), String> {
-        let status = match statement {
-            Statement::Declaration { identifier, mpl_type, value }
-                => self.evaluate_declaration(identifier, &mpl_type, value),
-            Statement::Assignment { identifier, expression }
-                => self.evaluate_assign(identifier, expression),
-            Statement::For { identifier, begin, end, statements } => {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            loops: Vec::new(),
+            calls: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            bigint: false,
+            monitors: Vec::new(),
+            input: Box::new(input::Stdin),
+            position: Position::default(),
+        }
+    }
+
+    /// Attach a monitor that is notified of the execution of the program.
+    pub fn add_monitor(&mut self, monitor: Box<dyn Monitor>) {
+        self.monitors.push(monitor);
+    }
+
+    /// Replace the source of input for read statements, which is standard input by default.
+    pub fn set_input(&mut self, input: Box<dyn Input>) {
+        self.input = input;
+    }
+
+    /// Limit the depth of nested procedure and function calls. Exceeding it is a runtime error.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Make integers unbounded, instead of 32 bits where an overflow is an error.
+    pub fn set_bigint(&mut self, bigint: bool) {
+        self.bigint = bigint;
+    }
+
+    /// Execute all statements of the AST in order. The monitors are notified when the
+    /// execution ends, even if it ends in an error.
+    pub fn run(&mut self, ast: Ast) -> Result<(), String> {
+        for statement in &ast.statements {
+            if let Statement::Function(ref function) = statement.node {
+                self.functions.insert(function.identifier.clone(), Rc::new(function.clone()));
+            }
+        }
+        let result = self.evaluate_block(&ast.statements);
+        let finished = self.notify(|monitor, interpreter| monitor.finish(interpreter));
+        result.and(finished)?;
+        self.input.finish()
+    }
+
+    /// The position of the statement being executed.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The current value of an identifier, if it has been declared in a visible scope.
+    pub fn value(&self, identifier: &str) -> Option<&MplValue> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(identifier))
+    }
+
+    /// All visible identifiers and their values, sorted by name.
+    pub fn bindings(&self) -> Vec<(&str, &MplValue)> {
+        let mut visible = HashMap::new();
+        for scope in &self.scopes {
+            visible.extend(scope.iter().map(|(name, value)| (name.as_str(), value)));
+        }
+        let mut bindings: Vec<_> = visible.into_iter().collect();
+        bindings.sort_by(|a, b| a.0.cmp(b.0));
+        bindings
+    }
+
+    /// Change the value of a declared identifier. The type of the value must match the
+    /// type of the identifier.
+    pub fn set_value(&mut self, identifier: &str, value: MplValue) -> Result<(), String> {
+        let mpl_type = self.get_type(identifier)?;
+        if !value.is(&mpl_type) {
+            return Err(type_error(format!("Type {} does not match value {}", mpl_type, value)));
+        }
+        self.bind(identifier, value)
+    }
+
+    /// The loops currently being executed, outermost first.
+    pub fn loops(&self) -> &[LoopFrame] {
+        &self.loops
+    }
+
+    /// The loops and calls currently being executed, outermost first.
+    pub fn stack(&self) -> Vec<Frame<'_>> {
+        let mut stack = Vec::new();
+        let mut loops = self.loops.iter();
+        let mut outer_loops = 0;
+        for call in &self.calls {
+            stack.extend(loops.by_ref().take(call.loops - outer_loops).map(Frame::Loop));
+            outer_loops = call.loops;
+            stack.push(Frame::Call(call));
+        }
+        stack.extend(loops.map(Frame::Loop));
+        stack
+    }
+
+    /// Call a hook on every monitor. The monitors are detached while the hooks run, so
+    /// that they can freely use the interpreter.
+    fn notify<F>(&mut self, mut hook: F) -> Result<(), String>
+        where F: FnMut(&mut dyn Monitor, &mut Interpreter) -> Result<(), String>
+    {
+        let mut monitors = mem::take(&mut self.monitors);
+        let result = monitors.iter_mut()
+            .try_for_each(|monitor| hook(monitor.as_mut(), self));
+        self.monitors = monitors;
+        result
+    }
+
+    /// Declare an identifier in the innermost scope, notifying the monitors.
+    fn declare(&mut self, identifier: &str, value: MplValue) -> Result<(), String> {
+        self.notify(|monitor, _| monitor.binding(identifier, None, &value))?;
+        self.scopes.last_mut()
+            .expect("there is always a scope")
+            .insert(identifier.to_string(), value);
+        Ok(())
+    }
+
+    /// Bind a new value to a declared identifier, in the scope where it was declared,
+    /// notifying the monitors of the change.
+    fn bind(&mut self, identifier: &str, value: MplValue) -> Result<(), String> {
+        let old = match self.value(identifier) {
+            Some(old) => old.clone(),
+            None => return Err(type_error(format!("Identifier {} used before declaration", identifier))),
+        };
+        self.notify(|monitor, _| monitor.binding(identifier, Some(&old), &value))?;
+        if let Some(slot) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(identifier)) {
+            *slot = value;
+        }
+        Ok(())
+    }
+
+    /// Bind a new value to an element of a declared array, at an index within its bounds. The
+    /// monitors are notified of the change of the whole array.
+    fn bind_element(&mut self, identifier: &str, element: usize, value: MplValue) -> Result<(), String> {
+        if !self.monitors.is_empty() {
+            let old = self.value(identifier).cloned().expect("array checked by evaluate_index");
+            let mut new = old.clone();
+            if let MplValue::Array(_, ref mut elements) = new {
+                elements[element] = value.clone();
+            }
+            self.notify(|monitor, _| monitor.binding(identifier, Some(&old), &new))?;
+        }
+        if let Some(&mut MplValue::Array(_, ref mut elements)) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(identifier)) {
+            elements[element] = value;
+        }
+        Ok(())
+    }
+
+    /// Execute the statements of a block in a new scope.
+    fn evaluate_scope(&mut self, statements: &[Located<Statement>]) -> Result<Flow, String> {
+        self.scopes.push(HashMap::new());
+        let flow = self.evaluate_block(statements);
+        self.scopes.pop();
+        flow
+    }
+
+    /// Execute the statements in order, until one of them breaks or continues a loop.
+    fn evaluate_block(&mut self, statements: &[Located<Statement>]) -> Result<Flow, String> {
+        for stmt in statements {
+            match self.evaluate_statement(stmt)? {
+                Flow::Next => (),
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn evaluate_statement(&mut self, statement: &Located<Statement>) -> Result<Flow, String> {
+        self.position = statement.position;
+        self.notify(|monitor, interpreter| monitor.before_statement(interpreter, statement))?;
+        let flow = match statement.node {
+            Statement::Declaration { ref identifier, ref mpl_type, ref value } => {
+                self.evaluate_declaration(identifier, mpl_type, value.as_ref())?;
+                Flow::Next
+            },
+            Statement::Constant { ref identifier, ref mpl_type, ref value } => {
+                self.evaluate_declaration(identifier, mpl_type, Some(value))?;
+                Flow::Next
+            },
+            Statement::Assignment { ref identifier, ref index, ref expression } => {
+                self.evaluate_assign(identifier, index.as_ref(), expression)?;
+                Flow::Next
+            },
+            Statement::For { ref identifier, ref begin, ref end, descending, ref step, ref statements } => {
                 let begin = self.expect_int_expr(begin)?;
-                let end = self.expect_int_expr(end)? + 1;
-                self.evaluate_for(identifier, begin .. end, statements)
+                let end = self.expect_int_expr(end)?;
+                let step = match *step {
+                    Some(ref step) => self.expect_int_expr(step)?,
+                    None => MplValue::Int(1),
+                };
+                if self.function(Operator::LessEqual).call(step.clone(), MplValue::Int(0))? == MplValue::Bool(true) {
+                    return Err(format!("{}: Step of for loop must be positive, got {}", statement.position, step));
+                }
+                let step = if descending { self.function(Operator::Minus).call(MplValue::Int(0), step)? } else { step };
+                self.evaluate_for(identifier, begin, end, step, statements, statement.position)?
+            },
+            Statement::If { ref condition, ref statements, ref else_statements }
+                => self.evaluate_if(condition, statements, else_statements)?,
+            Statement::While { ref condition, ref statements }
+                => self.evaluate_while(condition, statements, statement.position)?,
+            Statement::Break => Flow::Break,
+            Statement::Continue => Flow::Continue,
+            Statement::Function(_) => Flow::Next, // declared when the program starts
+            Statement::Call { ref identifier, ref arguments } => {
+                self.evaluate_call(identifier, arguments)?;
+                Flow::Next
+            },
+            Statement::Return(ref value) => match *value {
+                Some(ref expr) => Flow::Return(Some(self.evaluate_expression(expr)?)),
+                None => Flow::Return(None),
             },
-            Statement::Read(identifier) => self.evaluate_read(identifier),
-            Statement::Print(expr) => self.evaluate_print(expr),
-            Statement::Assert(expr) => self.evaluate_assert(expr),
-            Statement::Empty => Ok(()),
+            Statement::Read { ref identifier, ref index } => {
+                self.evaluate_read(identifier, index.as_ref())?;
+                Flow::Next
+            },
+            Statement::Print(ref expr) => { self.evaluate_print(expr)?; Flow::Next },
+            Statement::Assert(ref expr) => { self.evaluate_assert(expr)?; Flow::Next },
+            Statement::Empty => Flow::Next,
         };
-        match status {
-            Ok(()) => Ok(()),
-            Err(e) => Err(e),
+        self.position = statement.position;
+        self.notify(|monitor, interpreter| monitor.after_statement(interpreter, statement))?;
+        Ok(flow)
+    }
+
+    /// Execute a loop, with its frame on the loop stack while it runs. The monitors are
+    /// notified when the loop has finished.
+    fn evaluate_loop<F>(&mut self, identifier: Option<&str>, position: Position, mut iterate: F) -> Result<Flow, String>
+        where F: FnMut(&mut Interpreter) -> Result<Flow, String>
+    {
+        let identifier = identifier.map(|identifier| identifier.to_string());
+        self.loops.push(LoopFrame { identifier, iteration: 0, position });
+        let result = iterate(self);
+        let frame = self.loops.pop().expect("loop frame pushed above");
+        let flow = result?;
+        self.notify(|monitor, interpreter| monitor.after_loop(interpreter, &frame))?;
+        Ok(flow)
+    }
+
+    /// Count a new iteration of the innermost loop, and notify the monitors.
+    fn begin_iteration(&mut self) -> Result<(), String> {
+        if let Some(frame) = self.loops.last_mut() {
+            frame.iteration += 1;
         }
+        self.notify(|monitor, interpreter| monitor.before_iteration(interpreter))
+    }
+
+    /// Execute a for loop. The bounds and the step are evaluated once, before the loop. The
+    /// control variable starts at the beginning, and moves by the step, which is negative when
+    /// counting down, after every iteration. The loop ends when the control variable passes
+    /// the end, and then holds the first value past it: after `for i in 1..3` i is 4, and after
+    /// `for i in 3..1` it is 3. After a break the control variable keeps its value in the
+    /// iteration that broke.
+    ///
+    /// The control variable steps in unbounded integers, so a range may end at the largest or
+    /// the smallest integer. Without bigint the value past such an end does not fit in 32 bits,
+    /// and reading it after the loop is an overflow.
+    fn evaluate_for(&mut self, identifier: &str, begin: MplValue, end: MplValue, step: MplValue, statements: &[Located<Statement>], position: Position) -> Result<Flow, String> {
+        if self.value(identifier).is_none() {
+            return Err(type_error(format!("Identifier {} used before assignment", identifier)))
+        }
+        let descending = self.function(Operator::Less).call(step.clone(), MplValue::Int(0))? == MplValue::Bool(true);
+        let within = self.function(if descending { Operator::GreaterEqual } else { Operator::LessEqual });
+        let plus = functions::MplFunction { sign: Operator::Plus, bigint: true };
+        self.evaluate_loop(Some(identifier), position, |interpreter| {
+            let mut i = begin.clone();
+            while within.call(i.clone(), end.clone())? == MplValue::Bool(true) {
+                interpreter.begin_iteration()?;
+                interpreter.bind(identifier, i.clone())?;
+                match interpreter.evaluate_scope(statements)? {
+                    Flow::Break => return Ok(Flow::Next),
+                    Flow::Return(value) => return Ok(Flow::Return(value)),
+                    Flow::Next | Flow::Continue => (),
+                }
+                i = plus.call(i, step.clone())?;
+            }
+            interpreter.bind(identifier, i)?;
+            Ok(Flow::Next)
+        })
+    }
+
+    fn evaluate_while(&mut self, condition: &Expression, statements: &[Located<Statement>], position: Position) -> Result<Flow, String> {
+        self.evaluate_loop(None, position, |interpreter| {
+            loop {
+                // The statements of the body moved the position, so restore it for the condition.
+                interpreter.position = position;
+                match interpreter.evaluate_expression(condition)? {
+                    MplValue::Bool(true) => (),
+                    MplValue::Bool(false) => return Ok(Flow::Next),
+                    value => return Err(type_error(format!("While expected boolean condition, got {}", value.mpl_type()))),
+                }
+                interpreter.begin_iteration()?;
+                match interpreter.evaluate_scope(statements)? {
+                    Flow::Break => return Ok(Flow::Next),
+                    Flow::Return(value) => return Ok(Flow::Return(value)),
+                    Flow::Next | Flow::Continue => (),
+                }
+            }
+        })
     }
 
-    fn evaluate_for(&mut self, identifier: String, range: Range<i32>, statements: Vec<Statement>) -> Result<(), String> {
-        if !self.names.contains_key(&identifier) {
-            return Err(format!("Identifier {} used before assignment", identifier))
+    fn evaluate_if(&mut self, condition: &Expression, statements: &[Located<Statement>], else_statements: &[Located<Statement>]) -> Result<Flow, String> {
+        let holds = match self.evaluate_expression(condition)? {
+            MplValue::Bool(b) => b,
+            value => return Err(type_error(format!("If expected boolean condition, got {}", value.mpl_type()))),
+        };
+        self.notify(|monitor, interpreter| monitor.condition(interpreter, holds))?;
+        let branch = if holds { statements } else { else_statements };
+        self.evaluate_scope(branch)
+    }
+
+    /// Call a procedure or function. The arguments are evaluated in the calling context, and
+    /// bound to the parameters in a new context. Returns the return value of a function, or
+    /// None for a procedure. An error from the call gets a line of the stack trace added.
+    fn evaluate_call(&mut self, identifier: &str, arguments: &[Expression]) -> Result<Option<MplValue>, String> {
+        if let Some(builtin) = builtins::builtin(identifier) {
+            let mut values = Vec::new();
+            for argument in arguments {
+                values.push(self.evaluate_expression(argument)?);
+            }
+            return builtin.call(values, self.bigint).map(Some);
+        }
+        let function = match self.functions.get(identifier) {
+            Some(function) => function.clone(),
+            None => return Err(type_error(format!("Procedure or function {} is not declared", identifier))),
+        };
+        if arguments.len() != function.parameters.len() {
+            return Err(type_error(format!("{} expects {} arguments, got {}", identifier, function.parameters.len(), arguments.len())));
         }
-        for i in range {
-            self.names.insert(identifier.clone(), MplValue::Int(i));
-            for stmt in statements.iter() {
-                self.evaluate_statement(stmt.clone())?;
+        let mut values = Vec::new();
+        for (argument, parameter) in arguments.iter().zip(&function.parameters) {
+            let value = self.evaluate_expression(argument)?;
+            if !value.is(&parameter.mpl_type) {
+                return Err(type_error(format!("Type {} of parameter {} does not match value {}", parameter.mpl_type, parameter.identifier, value)));
             }
+            values.push(value);
+        }
+        if self.calls.len() >= self.max_depth {
+            return Err(format!("Call depth limit of {} exceeded", self.max_depth));
+        }
+
+        let caller = mem::replace(&mut self.scopes, vec![HashMap::new()]);
+        self.calls.push(CallFrame { identifier: identifier.to_string(), position: self.position, loops: self.loops.len() });
+        let result = self.evaluate_function(&function, values);
+        let frame = self.calls.pop().expect("call frame pushed above");
+        let failed_at = self.position;
+        self.scopes = caller;
+        self.position = frame.position;
+        result.map_err(|e| self.stack_trace(e, &frame, failed_at))
+    }
+
+    fn evaluate_function(&mut self, function: &Function, values: Vec<MplValue>) -> Result<Option<MplValue>, String> {
+        for (parameter, value) in function.parameters.iter().zip(values) {
+            self.declare(&parameter.identifier, value)?;
+        }
+        let value = match self.evaluate_block(&function.statements)? {
+            Flow::Return(value) => value,
+            _ => None,
+        };
+        match (value, function.return_type.as_ref()) {
+            (Some(value), Some(mpl_type)) if !value.is(mpl_type)
+                => Err(type_error(format!("Function {} must return {}, got {}", function.identifier, mpl_type, value))),
+            (None, Some(_)) => Err(type_error(format!("Function {} ended without returning a value", function.identifier))),
+            (Some(_), None) => Err(type_error(format!("Procedure {} cannot return a value", function.identifier))),
+            (value, _) => Ok(value),
         }
-        Ok(())
     }
 
-    fn evaluate_declaration(&mut self, identifier: String, mpl_type: &MplType, value: Option<Expression>) -> Result<(), String> {
+    /// Add the line of a call, which failed at the position, to the stack trace of the error.
+    /// When the error leaves the outermost call, the line of the main program is added, and
+    /// the repeated lines of a deep recursion are collapsed.
+    fn stack_trace(&self, error: String, frame: &CallFrame, position: Position) -> String {
+        let trace = format!("{}\n  in {} at {}", error, frame.identifier, position);
+        if !self.calls.is_empty() {
+            return trace;
+        }
+        let mut lines = trace.lines().peekable();
+        let mut collapsed = Vec::new();
+        while let Some(line) = lines.next() {
+            let mut repeats = 0;
+            while lines.peek() == Some(&line) {
+                lines.next();
+                repeats += 1;
+            }
+            collapsed.push(line.to_string());
+            if repeats > 0 {
+                collapsed.push(format!("  [previous line repeated {} more times]", repeats));
+            }
+        }
+        collapsed.push(format!("  in the main program at {}", frame.position));
+        collapsed.join("\n")
+    }
+
+    fn evaluate_declaration(&mut self, identifier: &str, mpl_type: &MplType, value: Option<&Expression>) -> Result<(), String> {
         let init = match value {
             Some(expr) => self.evaluate_expression(expr)?,
-            None => MplValue::default(mpl_type), // initialize to default
+            None => MplValue::default(mpl_type).map_err(|e| format!("{}: {}", self.position, e))?, // initialize to default
         };
         if !init.is(mpl_type) {
-            return Err(format!("Type {} does not match value {}", mpl_type, init));
+            return Err(type_error(format!("Type {} does not match value {}", mpl_type, init)));
         }
-        self.names.insert(identifier, init);
-        Ok(())
+        self.declare(identifier, init)
     }
 
-    fn evaluate_assign(&mut self, identifier: String, val_expr: Expression) -> Result<(), String> {
+    fn evaluate_assign(&mut self, identifier: &str, index: Option<&Expression>, val_expr: &Expression) -> Result<(), String> {
+        let element = match index {
+            Some(index) => Some(self.evaluate_index(identifier, index)?),
+            None => None,
+        };
         let value = self.evaluate_expression(val_expr)?;
-        if self.names.contains_key(&identifier) {
-            self.names.insert(identifier, value);
-            Ok(())
-        } else {
-            Err(format!("Identifier {}, used before declaration", identifier))
+        match element {
+            Some(element) => self.bind_element(identifier, element, value),
+            None => self.bind(identifier, value),
         }
     }
 
-    fn evaluate_read(&mut self, identifier: String) -> Result<(), String> {
-        let mut line = String::new();
-        if let Err(e) = io::stdin().read_line(&mut line) {
-            return Err(format!("IO error: {}", e));
+    fn evaluate_read(&mut self, identifier: &str, index: Option<&Expression>) -> Result<(), String> {
+        let element = match index {
+            Some(index) => Some(self.evaluate_index(identifier, index)?),
+            None => None,
+        };
+        let mpl_type = match (self.get_type(identifier)?, element) {
+            (MplType::Array { element, .. }, Some(_)) => *element,
+            (MplType::Array { .. }, None) => return Err(type_error(format!("Cannot read into array {}", identifier))),
+            (mpl_type, _) => mpl_type,
         };
+
+        let line = self.input.read_line(identifier, self.position)?;
         let input = line.trim().to_string();
-        let value = match self.get_type(&identifier)? {
+        let value = match mpl_type {
             MplType::Int => self.parse_int(input)?,
+            MplType::Real => self.parse_real(input)?,
             MplType::String => MplValue::String(input),
             MplType::Bool => self.parse_bool(input)?,
+            MplType::Array { .. } => unreachable!("arrays have elements of the basic types"),
         };
+        match element {
+            Some(element) => self.bind_element(identifier, element, value),
+            None => self.bind(identifier, value),
+        }
+    }
 
-        if self.names.contains_key(&identifier) {
-            self.names.insert(identifier, value);
-            Ok(())
-        } else {
-            Err("Identifier {} used before declaration".to_string())
+    /// Evaluate the index of an element of an array. Fails if the identifier is not an array,
+    /// or if the index is out of its bounds.
+    fn evaluate_index(&mut self, identifier: &str, index: &Expression) -> Result<usize, String> {
+        let index = self.expect_int_expr(index)?;
+        let size = match self.value(identifier) {
+            Some(MplValue::Array(_, elements)) => elements.len(),
+            Some(value) => return Err(type_error(format!("{} is not an array, but {}", identifier, value.mpl_type()))),
+            None => return Err(type_error(format!("Identifier {} used before declaration", identifier))),
+        };
+        match index {
+            MplValue::Int(i) if i >= 0 && (i as usize) < size => Ok(i as usize),
+            index => Err(format!(
+                "Index {} is out of bounds for array {} of size {} at {}",
+                index, identifier, size, self.position,
+            )),
         }
     }
 
-    fn evaluate_print(&self, print: Expression) -> Result<(), String> {
+    fn evaluate_print(&mut self, print: &Expression) -> Result<(), String> {
         self.evaluate_expression(print)
             .map(|value| print!("{}", value))?;
         io::stdout().flush().map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    fn evaluate_assert(&self, assertion: Expression) -> Result<(), String> {
-        let diag = self.diagnostics(&assertion);
-        self.evaluate_expression(assertion).and_then(|value| {
-            match value {
-                MplValue::Bool(true) => Ok(()),
-                MplValue::Bool(false) => { println!("{}", diag); Ok(()) },
-                value => Err(format!("Assert expected boolean argument, got {}", value.mpl_type())),
-            }
-        })
+    fn evaluate_assert(&mut self, assertion: &Expression) -> Result<(), String> {
+        let mut operands = Vec::new();
+        let holds = match self.evaluate_recording(assertion, Some(&mut operands))? {
+            MplValue::Bool(b) => b,
+            value => return Err(type_error(format!("Assert expected boolean argument, got {}", value.mpl_type()))),
+        };
+        self.notify(|monitor, interpreter| monitor.assertion(interpreter, holds))?;
+        if !holds {
+            println!("{}", self.diagnostics(assertion, &operands));
+        }
+        Ok(())
     }
 
-    fn diagnostics(&self, assertion: &Expression) -> String {
-        let expr = match assertion {
-            &Expression::Simple(ref opnd) => format!(
-                "{} ({})",
-                opnd,
-                self.evaluate_operand(opnd.clone()).unwrap(),
-            ),
-            &Expression::Unary { operator, ref operand } => format!(
+    /// The message of a failed assertion, with the values that the operands of its expression
+    /// had when it was evaluated. The right operand of an & or | skipped by the short-circuit
+    /// is shown as ?.
+    fn diagnostics(&self, assertion: &Expression, operands: &[Option<MplValue>]) -> String {
+        let mut operands = OperandValues { recorded: operands.iter(), values: Vec::new() };
+        operands.visit_expression(assertion);
+        let values = operands.values;
+        let expr = match *assertion {
+            Expression::Simple(ref opnd) => format!("{} ({})", opnd, values[0]),
+            Expression::Unary { operator, ref operand } => format!(
                 "{} {} ({} {})",
                 operator,
                 operand,
                 operator,
-                self.evaluate_operand(operand.clone()).unwrap()
+                values[0],
             ),
-            &Expression::Binary { ref left, operator, ref right} => format!(
+            Expression::Binary { ref left, operator, ref right} => format!(
                 "{} {} {} ({} {} {})",
                 left,
                 operator,
                 right,
-                self.evaluate_operand(left.clone()).unwrap(),
+                values[0],
                 operator,
-                self.evaluate_operand(right.clone()).unwrap(),
+                values[1],
             ),
         };
         format!("Assertion {} was false", expr)
     }
 
-    fn evaluate_expression(&self, expr: Expression) -> Result<MplValue, String> {
-        match expr {
-            Expression::Simple(opnd) => self.evaluate_operand(opnd),
-            Expression::Binary { operator, left, right} => {
-                let func = functions::MplFunction { sign: operator };
-                func.call(self.evaluate_operand(left)?, self.evaluate_operand(right)?)
+    /// Evaluate an expression, notifying the monitors before and after.
+    pub fn evaluate_expression(&mut self, expr: &Expression) -> Result<MplValue, String> {
+        self.evaluate_recording(expr, None)
+    }
+
+    /// Evaluate an expression, notifying the monitors before and after. If `operands` is given,
+    /// the values of the operands of the expression are pushed to it, with None for the right
+    /// operand of an & or | skipped by the short-circuit.
+    fn evaluate_recording(&mut self, expr: &Expression, mut operands: Option<&mut Vec<Option<MplValue>>>) -> Result<MplValue, String> {
+        let mut record = |value: Option<&MplValue>| if let Some(ref mut operands) = operands {
+            operands.push(value.cloned());
+        };
+        self.notify(|monitor, interpreter| monitor.before_expression(interpreter, expr))?;
+        let value = match *expr {
+            Expression::Simple(ref opnd) => {
+                let value = self.evaluate_operand(opnd)?;
+                record(Some(&value));
+                value
             },
-            Expression::Unary { operator, operand } => {
-                let func = functions::MplFunction { sign: operator };
-                func.call_unary(self.evaluate_operand(operand)?)
+            Expression::Binary { operator, ref left, ref right} => {
+                let func = self.function(operator);
+                let left = self.evaluate_operand(left)?;
+                record(Some(&left));
+                // & and | short-circuit: the right operand is not evaluated if the left one
+                // decides the result.
+                match (operator, &left) {
+                    (Operator::And, &MplValue::Bool(false)) | (Operator::Or, &MplValue::Bool(true)) => {
+                        record(None);
+                        left
+                    },
+                    _ => {
+                        let right = self.evaluate_operand(right)?;
+                        record(Some(&right));
+                        func.call(left, right)?
+                    },
+                }
             },
-        }
+            Expression::Unary { operator, ref operand } => {
+                let func = self.function(operator);
+                let value = self.evaluate_operand(operand)?;
+                record(Some(&value));
+                func.call_unary(value)?
+            },
+        };
+        self.notify(|monitor, interpreter| monitor.after_expression(interpreter, expr, &value))?;
+        Ok(value)
     }
 
-    fn evaluate_operand(&self, operand: Operand) -> Result<MplValue, String> {
-        match operand {
+    fn evaluate_operand(&mut self, operand: &Operand) -> Result<MplValue, String> {
+        match *operand {
             Operand::Int(i) => Ok(MplValue::Int(i)),
-            Operand::String(s) => Ok(MplValue::String(s)),
-            Operand::Identifier(id) => {
-                match self.names.get(&id) {
+            Operand::BigInt(ref i) => Ok(MplValue::BigInt(i.clone())),
+            Operand::Real(r) => Ok(MplValue::Real(r)),
+            Operand::Bool(b) => Ok(MplValue::Bool(b)),
+            Operand::String(ref s) => Ok(MplValue::String(s.clone())),
+            Operand::Identifier(ref id) => {
+                match self.value(id) {
+                    // Only the control variable of a for loop that ended at the largest or the
+                    // smallest integer can hold a BigInt without bigint.
+                    Some(MplValue::BigInt(i)) if !self.bigint => Err(format!(
+                        "{}: Integer overflow: {} is {} after the loop, which does not fit in 32 bits",
+                        self.position, id, i,
+                    )),
                     Some(value) => Ok(value.clone()),
-                    None => Err(format!("Identifier {} used before assignment", id))
+                    None => Err(type_error(format!("Identifier {} used before assignment", id)))
                 }
             },
-            Operand::Expr(expr) => self.evaluate_expression(*expr),
+            Operand::Index { ref identifier, ref index } => {
+                let element = self.evaluate_index(identifier, index)?;
+                match self.value(identifier) {
+                    Some(MplValue::Array(_, elements)) => Ok(elements[element].clone()),
+                    _ => unreachable!("array checked by evaluate_index"),
+                }
+            },
+            Operand::Size(ref identifier) => match self.value(identifier) {
+                Some(MplValue::Array(_, elements)) => Ok(MplValue::Int(elements.len() as i32)),
+                Some(value) => Err(type_error(format!("{} is not an array, but {}", identifier, value.mpl_type()))),
+                None => Err(type_error(format!("Identifier {} used before declaration", identifier))),
+            },
+            Operand::Call { ref identifier, ref arguments } => self.evaluate_call(identifier, arguments)?
+                .ok_or_else(|| type_error(format!("Procedure {} does not return a value", identifier))),
+            Operand::Expr(ref expr) => self.evaluate_expression(expr),
         }
     }
 
-    /// Evaluates an expression into a rust integer. If this is not possible, for example is
+    /// Evaluates an expression into an integer value. If this is not possible, for example is
     /// the type is wrong, return an error.
-    fn expect_int_expr(&self, expr: Expression) -> Result<i32, String> {
+    fn expect_int_expr(&mut self, expr: &Expression) -> Result<MplValue, String> {
         self.evaluate_expression(expr).and_then(|value| match value {
-            MplValue::Int(i) => Ok(i),
-            MplValue::String(_) => Err("Expected int here, got string".to_string()),
-            MplValue::Bool(_) => Err("Expected int here, got bool".to_string()),
+            MplValue::Int(_) | MplValue::BigInt(_) => Ok(value),
+            MplValue::Real(_) => Err(type_error("Expected int here, got real")),
+            MplValue::String(_) => Err(type_error("Expected int here, got string")),
+            MplValue::Bool(_) => Err(type_error("Expected int here, got bool")),
+            MplValue::Array(..) => Err(type_error("Expected int here, got array")),
         })
     }
 
+    /// The function of an operator, with the integer semantics of the interpreter.
+    fn function(&self, sign: Operator) -> functions::MplFunction {
+        functions::MplFunction { sign, bigint: self.bigint }
+    }
+
     /// Parsing helper. Attempts to parse string into an integer MplValue.
     fn parse_int(&self, input: String) -> Result<MplValue, String> {
         match input.parse() {
             Ok(i) => Ok(MplValue::Int(i)),
-            Err(e) => Err(e.description().to_string()),
+            Err(e) => match BigInt::parse(&input) {
+                Some(i) if self.bigint => Ok(MplValue::from_big(i)),
+                _ => Err(e.to_string()),
+            },
+        }
+    }
+    /// Parsing helper. Attempts to parse string into a real MplValue.
+    fn parse_real(&self, input: String) -> Result<MplValue, String> {
+        match input.parse() {
+            Ok(r) => Ok(MplValue::Real(r)),
+            Err(e) => Err(e.to_string()),
         }
     }
     /// Parsing helper. Attempts to parse string into an boolean MplValue.
     fn parse_bool(&self, input: String) -> Result<MplValue, String> {
         match input.parse() {
             Ok(b) => Ok(MplValue::Bool(b)),
-            Err(e) => Err(e.description().to_string()),
+            Err(e) => Err(e.to_string()),
         }
     }
 
     /// Get the type of a given identifier. If the identifier has not been declared,
     /// return an error.
-    fn get_type(&self, identifier: &String) -> Result<MplType, String> {
-        self.names.get(identifier)
+    fn get_type(&self, identifier: &str) -> Result<MplType, String> {
+        self.value(identifier)
             .map(|value| value.mpl_type())
-            .ok_or(format!("Identifier {} not initialized", identifier))
+            .ok_or_else(|| type_error(format!("Identifier {} not initialized", identifier)))
     }
 }
@@ -0,0 +1,64 @@
+use super::{Interpreter, LoopFrame};
+use super::value::MplValue;
+use super::super::parser::ast::*;
+
+/// A monitor observes the execution of a program, for example to debug it. The interpreter
+/// calls the hooks of its monitors at the corresponding points of the execution. The hooks
+/// do nothing by default, so a monitor only implements the ones it needs.
+pub trait Monitor {
+    /// Called before a statement is executed. The monitor may inspect and change the state of
+    /// the interpreter. Returning an error stops the execution of the program.
+    fn before_statement(&mut self, _interpreter: &mut Interpreter, _statement: &Located<Statement>) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after a statement has been executed successfully. For a loop, this is after the
+    /// whole loop.
+    fn after_statement(&mut self, _interpreter: &mut Interpreter, _statement: &Located<Statement>) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called before an expression, or a parenthesized subexpression, is evaluated.
+    fn before_expression(&mut self, _interpreter: &mut Interpreter, _expression: &Expression) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after an expression has been evaluated to `value`.
+    fn after_expression(&mut self, _interpreter: &mut Interpreter, _expression: &Expression, _value: &MplValue) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called when an iteration of a loop begins, before the control variable of a for loop is
+    /// updated, or after the condition of a while loop has held.
+    /// The innermost loop of `interpreter.loops()` is the one being iterated.
+    fn before_iteration(&mut self, _interpreter: &mut Interpreter) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called when a loop has finished. The iteration of the frame is the total number of
+    /// iterations, which may be zero.
+    fn after_loop(&mut self, _interpreter: &mut Interpreter, _frame: &LoopFrame) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after the expression of an assert statement has been evaluated to `holds`.
+    fn assertion(&mut self, _interpreter: &mut Interpreter, _holds: bool) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after the condition of an if statement has been evaluated to `holds`.
+    fn condition(&mut self, _interpreter: &mut Interpreter, _holds: bool) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called when a value is bound to an identifier by a declaration, an assignment, a read,
+    /// a for loop or a call. `old` is None when the identifier is declared.
+    fn binding(&mut self, _identifier: &str, _old: Option<&MplValue>, _new: &MplValue) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called once when the execution of the program ends, whether or not it ended in an error.
+    fn finish(&mut self, _interpreter: &mut Interpreter) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use super::{Frame, Interpreter, Monitor};
+use super::value::MplValue;
+use super::super::parser::ast::*;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// The number of rows printed in each hot-spot table.
+const TABLE_ROWS: usize = 20;
+
+/// Execution count and accumulated time of a node. The self time of a statement excludes the
+/// time spent in the statements of a for loop body. Expressions only have a total time.
+#[derive(Clone, Copy, Debug, Default)]
+struct Stats {
+    count: usize,
+    total: Duration,
+    own: Duration,
+}
+
+/// A statement being executed.
+struct Timer {
+    start: Instant,
+    children: Duration, // time spent in nested statements
+}
+
+/// A monitor that counts executions and measures the time spent in every statement and every
+/// expression. When the program ends, it prints the hot spots to standard error, and can write
+/// the self times as folded stacks for flamegraph tools. The stacks are formed by the loops
+/// and the procedure and function calls being executed, outermost first.
+pub struct Profiler {
+    lines: Vec<String>,
+    statements: HashMap<Position, Stats>,
+    expressions: HashMap<(Position, String), Stats>,
+    folded: HashMap<String, Duration>,
+    statement_timers: Vec<Timer>,
+    expression_timers: Vec<Instant>,
+    folded_path: Option<String>,
+}
+
+impl Profiler {
+    /// Create a profiler for the program with the given source. If `folded_path` is given,
+    /// the folded stacks are written to that path when the program ends.
+    pub fn new(source: &str, folded_path: Option<String>) -> Profiler {
+        Profiler {
+            lines: source.lines().map(|line| line.trim().to_string()).collect(),
+            statements: HashMap::new(),
+            expressions: HashMap::new(),
+            folded: HashMap::new(),
+            statement_timers: Vec::new(),
+            expression_timers: Vec::new(),
+            folded_path,
+        }
+    }
+
+    fn source_line(&self, position: Position) -> &str {
+        self.lines.get(position.line - 1).map(|line| line.as_str()).unwrap_or("")
+    }
+
+    /// The hot-spot tables of statements and expressions, sorted by total time.
+    pub fn report(&self) -> String {
+        let mut report = String::from("Statements by total time\n");
+        report.push_str(&format!("{:>10} {:>10} {:>12} {:>12}  {}\n", "position", "count", "total ms", "self ms", "statement"));
+        let mut statements: Vec<_> = self.statements.iter().collect();
+        statements.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+        for (position, stats) in statements.into_iter().take(TABLE_ROWS) {
+            report.push_str(&format!(
+                "{:>10} {:>10} {:>12.3} {:>12.3}  {}\n",
+                position.to_string(),
+                stats.count,
+                millis(stats.total),
+                millis(stats.own),
+                self.source_line(*position),
+            ));
+        }
+
+        report.push_str("\nExpressions by total time\n");
+        report.push_str(&format!("{:>10} {:>10} {:>12}  {}\n", "position", "count", "total ms", "expression"));
+        let mut expressions: Vec<_> = self.expressions.iter().collect();
+        expressions.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+        for (&(position, ref text), stats) in expressions.into_iter().take(TABLE_ROWS) {
+            report.push_str(&format!(
+                "{:>10} {:>10} {:>12.3}  {}\n",
+                position.to_string(),
+                stats.count,
+                millis(stats.total),
+                text,
+            ));
+        }
+        report
+    }
+
+    /// The self times in nanoseconds as folded stacks, one stack per line, sorted by stack.
+    pub fn folded_stacks(&self) -> String {
+        let mut stacks: Vec<_> = self.folded.iter().collect();
+        stacks.sort();
+        stacks.into_iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_nanos()))
+            .collect()
+    }
+
+    /// The folded stack of a statement: the program, the running loops and calls, and the
+    /// statement. A loop statement is labeled like the loop frames, so that its own time joins
+    /// its loop.
+    fn stack(&self, interpreter: &Interpreter, statement: &Located<Statement>) -> String {
+        let mut frames = vec!["program".to_string()];
+        for frame in interpreter.stack() {
+            frames.push(match frame {
+                Frame::Loop(frame) => loop_label(&frame.label(), frame.position),
+                Frame::Call(call) => call.identifier.clone(),
+            });
+        }
+        frames.push(match statement.node {
+            Statement::For { ref identifier, .. } => loop_label(&format!("for {}", identifier), statement.position),
+            Statement::While { .. } => loop_label("while", statement.position),
+            _ => {
+                let text = self.source_line(statement.position).trim_end_matches(';');
+                format!("{} {}", statement.position, text)
+            },
+        });
+        // Semicolons separate the frames of the stack.
+        frames.iter().map(|frame| frame.replace(';', ",")).collect::<Vec<_>>().join(";")
+    }
+}
+
+impl Monitor for Profiler {
+    fn before_statement(&mut self, _interpreter: &mut Interpreter, _statement: &Located<Statement>) -> Result<(), String> {
+        self.statement_timers.push(Timer { start: Instant::now(), children: Duration::new(0, 0) });
+        Ok(())
+    }
+
+    fn after_statement(&mut self, interpreter: &mut Interpreter, statement: &Located<Statement>) -> Result<(), String> {
+        let timer = match self.statement_timers.pop() {
+            Some(timer) => timer,
+            None => return Ok(()),
+        };
+        let elapsed = timer.start.elapsed();
+        let own = elapsed.checked_sub(timer.children).unwrap_or_default();
+        if let Some(parent) = self.statement_timers.last_mut() {
+            parent.children += elapsed;
+        }
+
+        let stats = self.statements.entry(statement.position).or_default();
+        stats.count += 1;
+        stats.total += elapsed;
+        stats.own += own;
+
+        let stack = self.stack(interpreter, statement);
+        *self.folded.entry(stack).or_default() += own;
+        Ok(())
+    }
+
+    fn before_expression(&mut self, _interpreter: &mut Interpreter, _expression: &Expression) -> Result<(), String> {
+        self.expression_timers.push(Instant::now());
+        Ok(())
+    }
+
+    fn after_expression(&mut self, interpreter: &mut Interpreter, expression: &Expression, _value: &MplValue) -> Result<(), String> {
+        if let Some(start) = self.expression_timers.pop() {
+            let elapsed = start.elapsed();
+            let stats = self.expressions.entry((interpreter.position(), expression.to_string())).or_default();
+            stats.count += 1;
+            stats.total += elapsed;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _interpreter: &mut Interpreter) -> Result<(), String> {
+        write!(io::stderr(), "{}", self.report()).map_err(|e| e.to_string())?;
+        if let Some(ref path) = self.folded_path {
+            File::create(path)
+                .and_then(|mut file| file.write_all(self.folded_stacks().as_bytes()))
+                .map_err(|e| format!("Unable to write {}: {}", path, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn loop_label(label: &str, position: Position) -> String {
+    format!("{} {}", label, position)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0
+}
@@ -0,0 +1,214 @@
+use super::{Interpreter, LoopFrame, Monitor};
+use super::value::MplValue;
+use super::super::parser::ast::*;
+use super::super::serial::json::quote as json_string;
+
+use std::io;
+use std::io::Write;
+
+/// The output format of an execution trace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    /// Human readable lines.
+    Text,
+    /// One JSON object per line. Every object has an "event" field, which is one of
+    /// - "statement": a statement is about to be executed. Fields "line", "column", "source"
+    ///   (the source line), "operands" (object mapping the identifiers used by the statement to
+    ///   their current values) and "loops" (array of {"identifier", "iteration"} objects,
+    ///   outermost loop first, where the identifier of a while loop is null).
+    /// - "iteration": an iteration of a loop begins. Fields "identifier" (null for a while
+    ///   loop) and "iteration".
+    /// - "binding": a value was bound to an identifier. Fields "identifier", "old" (null for a
+    ///   declaration) and "new".
+    ///
+    /// Values are JSON numbers, strings, booleans and arrays. A real that is NaN or infinite is null.
+    Json,
+}
+
+/// A monitor that writes every executed statement and every change of a binding to standard
+/// error, so that the trace does not mix with the output of the program.
+pub struct Tracer {
+    lines: Vec<String>,
+    format: TraceFormat,
+}
+
+impl Tracer {
+    pub fn new(source: &str, format: TraceFormat) -> Tracer {
+        Tracer { lines: source.lines().map(|line| line.trim().to_string()).collect(), format }
+    }
+
+    fn emit(&self, text: String) -> Result<(), String> {
+        writeln!(io::stderr(), "{}", text).map_err(|e| e.to_string())
+    }
+}
+
+impl Monitor for Tracer {
+    fn before_statement(&mut self, interpreter: &mut Interpreter, statement: &Located<Statement>) -> Result<(), String> {
+        let position = statement.position;
+        let source = self.lines.get(position.line - 1).map(|line| line.as_str()).unwrap_or("");
+
+        let mut identifiers = Vec::new();
+        operand_identifiers(&statement.node, &mut identifiers);
+        let operands: Vec<(&str, &MplValue)> = identifiers.iter()
+            .filter_map(|id| interpreter.value(id).map(|value| (id.as_str(), value)))
+            .collect();
+
+        let text = match self.format {
+            TraceFormat::Text => {
+                let mut text = format!("[{}] {}", position, source);
+                if !operands.is_empty() {
+                    let values: Vec<String> = operands.iter()
+                        .map(|&(id, value)| format!("{} = {}", id, text_value(value)))
+                        .collect();
+                    text.push_str(&format!("  {{{}}}", values.join(", ")));
+                }
+                text
+            },
+            TraceFormat::Json => {
+                let values: Vec<String> = operands.iter()
+                    .map(|&(id, value)| format!("{}:{}", json_string(id), json_value(value)))
+                    .collect();
+                let loops: Vec<String> = interpreter.loops().iter()
+                    .map(|frame| format!(
+                        "{{\"identifier\":{},\"iteration\":{}}}",
+                        json_identifier(frame),
+                        frame.iteration,
+                    ))
+                    .collect();
+                format!(
+                    "{{\"event\":\"statement\",\"line\":{},\"column\":{},\"source\":{},\"operands\":{{{}}},\"loops\":[{}]}}",
+                    position.line,
+                    position.column,
+                    json_string(source),
+                    values.join(","),
+                    loops.join(","),
+                )
+            },
+        };
+        self.emit(text)
+    }
+
+    fn before_iteration(&mut self, interpreter: &mut Interpreter) -> Result<(), String> {
+        let frame = match interpreter.loops().last() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let text = match self.format {
+            TraceFormat::Text => format!("    {}: iteration {}", frame.label(), frame.iteration),
+            TraceFormat::Json => format!(
+                "{{\"event\":\"iteration\",\"identifier\":{},\"iteration\":{}}}",
+                json_identifier(frame),
+                frame.iteration,
+            ),
+        };
+        self.emit(text)
+    }
+
+    fn binding(&mut self, identifier: &str, old: Option<&MplValue>, new: &MplValue) -> Result<(), String> {
+        let text = match self.format {
+            TraceFormat::Text => match old {
+                Some(old) => format!("    {}: {} -> {}", identifier, text_value(old), text_value(new)),
+                None => format!("    {}: {}", identifier, text_value(new)),
+            },
+            TraceFormat::Json => format!(
+                "{{\"event\":\"binding\",\"identifier\":{},\"old\":{},\"new\":{}}}",
+                json_string(identifier),
+                old.map(json_value).unwrap_or_else(|| "null".to_string()),
+                json_value(new),
+            ),
+        };
+        self.emit(text)
+    }
+}
+
+/// Collect the identifiers used by the expressions of the statement, in order of appearance.
+/// The statements in the bodies of loops and if statements are not included.
+fn operand_identifiers(statement: &Statement, identifiers: &mut Vec<String>) {
+    match *statement {
+        Statement::Declaration { value: Some(ref expr), .. } | Statement::Constant { value: ref expr, .. }
+            => expression_identifiers(expr, identifiers),
+        Statement::Assignment { ref index, ref expression, .. } => {
+            if let Some(ref index) = *index {
+                expression_identifiers(index, identifiers);
+            }
+            expression_identifiers(expression, identifiers);
+        },
+        Statement::Read { index: Some(ref index), .. } => expression_identifiers(index, identifiers),
+        Statement::For { ref begin, ref end, ref step, .. } => {
+            expression_identifiers(begin, identifiers);
+            expression_identifiers(end, identifiers);
+            if let Some(ref step) = *step {
+                expression_identifiers(step, identifiers);
+            }
+        },
+        Statement::If { ref condition, .. } | Statement::While { ref condition, .. }
+            => expression_identifiers(condition, identifiers),
+        Statement::Print(ref expr) | Statement::Assert(ref expr) | Statement::Return(Some(ref expr))
+            => expression_identifiers(expr, identifiers),
+        Statement::Call { ref arguments, .. } => for argument in arguments {
+            expression_identifiers(argument, identifiers);
+        },
+        Statement::Declaration { value: None, .. } | Statement::Read { index: None, .. } | Statement::Break
+        | Statement::Continue | Statement::Function(_) | Statement::Return(None) | Statement::Empty => (),
+    }
+}
+
+fn expression_identifiers(expression: &Expression, identifiers: &mut Vec<String>) {
+    match *expression {
+        Expression::Simple(ref operand) | Expression::Unary { ref operand, .. }
+            => operand_identifier(operand, identifiers),
+        Expression::Binary { ref left, ref right, .. } => {
+            operand_identifier(left, identifiers);
+            operand_identifier(right, identifiers);
+        },
+    }
+}
+
+fn operand_identifier(operand: &Operand, identifiers: &mut Vec<String>) {
+    match *operand {
+        Operand::Identifier(ref id) | Operand::Size(ref id) if !identifiers.contains(id) => identifiers.push(id.clone()),
+        Operand::Index { ref identifier, ref index } => {
+            if !identifiers.contains(identifier) {
+                identifiers.push(identifier.clone());
+            }
+            expression_identifiers(index, identifiers);
+        },
+        Operand::Call { ref arguments, .. } => for argument in arguments {
+            expression_identifiers(argument, identifiers);
+        },
+        Operand::Expr(ref expr) => expression_identifiers(expr, identifiers),
+        _ => (),
+    }
+}
+
+/// Format a value for the text trace. Strings are quoted, so that they can be told apart.
+fn text_value(value: &MplValue) -> String {
+    match *value {
+        MplValue::String(ref s) => format!("{:?}", s),
+        MplValue::Array(_, ref elements) => {
+            let elements: Vec<String> = elements.iter().map(text_value).collect();
+            format!("[{}]", elements.join(", "))
+        },
+        _ => value.to_string(),
+    }
+}
+
+/// The control variable of a loop frame as JSON, null for a while loop.
+fn json_identifier(frame: &LoopFrame) -> String {
+    frame.identifier.as_ref().map(|identifier| json_string(identifier)).unwrap_or_else(|| "null".to_string())
+}
+
+fn json_value(value: &MplValue) -> String {
+    match *value {
+        MplValue::Int(i) => i.to_string(),
+        MplValue::BigInt(ref i) => i.to_string(),
+        MplValue::Real(r) if r.is_finite() => value.to_string(),
+        MplValue::Real(_) => "null".to_string(), // JSON has no NaN or infinities
+        MplValue::String(ref s) => json_string(s),
+        MplValue::Bool(b) => b.to_string(),
+        MplValue::Array(_, ref elements) => {
+            let elements: Vec<String> = elements.iter().map(json_value).collect();
+            format!("[{}]", elements.join(","))
+        },
+    }
+}
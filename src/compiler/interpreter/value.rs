@@ -1,35 +1,53 @@
 use std::fmt;
 use std::fmt::Display;
 
-use super::super::parser::ast::MplType;
+use super::super::parser::ast::{format_real, BigInt, MplType};
+use super::type_error;
 
 /// A value in the MiniPl language.
 #[derive(Clone, Debug, PartialEq)]
 pub enum MplValue {
     Int(i32),
+    BigInt(BigInt), // an integer outside the range of Int, only with --bigint or past the end of a for loop
+    Real(f64),
     String(String),
     Bool(bool),
+    Array(MplType, Vec<MplValue>), // the element type and the elements
 }
 
 impl Display for MplValue {
     // Define fmt to make MplValues printable.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &MplValue::Int(i) => write!(f, "{}", i),
-            &MplValue::String(ref s) => write!(f, "{}", s),
-            &MplValue::Bool(b) => write!(f, "{}", b),
+        match *self {
+            MplValue::Int(i) => write!(f, "{}", i),
+            MplValue::BigInt(ref i) => write!(f, "{}", i),
+            MplValue::Real(r) => write!(f, "{}", format_real(r)),
+            MplValue::String(ref s) => write!(f, "{}", s),
+            MplValue::Bool(b) => write!(f, "{}", b),
+            MplValue::Array(_, ref elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            },
         }
     }
 }
 
+// The conversions consume the value, so that strings need not be copied.
+#[allow(clippy::wrong_self_convention)]
 impl MplValue {
     /// Check if the value of a certain type.
     pub fn is(&self, mpl_type: &MplType) -> bool {
         match (self, mpl_type) {
-            (&MplValue::Int(_), &MplType::Int) => true,
-            (&MplValue::String(_), &MplType::String) => true,
-            (&MplValue::Bool(_), &MplType::Bool) => true,
-            _ => false,
+            (MplValue::Array(element_type, elements), MplType::Array { element, size })
+                => **element == *element_type && elements.len() == *size,
+            _ => matches!(
+                (self, mpl_type),
+                (&MplValue::Int(_), &MplType::Int)
+                    | (&MplValue::BigInt(_), &MplType::Int)
+                    | (&MplValue::Real(_), &MplType::Real)
+                    | (&MplValue::String(_), &MplType::String)
+                    | (&MplValue::Bool(_), &MplType::Bool)
+            ),
         }
     }
 
@@ -37,46 +55,98 @@ impl MplValue {
     pub fn to_int(self) -> Result<i32, String> {
         match self {
             MplValue::Int(i) => Ok(i),
-            MplValue::String(_) => Err("Expected integer, got string".to_string()),
-            MplValue::Bool(_) => Err("Expected integer, got boolean".to_string()),
+            MplValue::BigInt(i) => Err(format!("Integer {} does not fit in 32 bits", i)),
+            MplValue::Real(_) => Err(type_error("Expected integer, got real")),
+            MplValue::String(_) => Err(type_error("Expected integer, got string")),
+            MplValue::Bool(_) => Err(type_error("Expected integer, got boolean")),
+            MplValue::Array(..) => Err(type_error("Expected integer, got array")),
+        }
+    }
+    /// Attempt to convert the value to an integer of any size. Return Error if the type is incompatible.
+    pub fn to_big(self) -> Result<BigInt, String> {
+        match self {
+            MplValue::Int(i) => Ok(BigInt::from(i64::from(i))),
+            MplValue::BigInt(i) => Ok(i),
+            MplValue::Real(_) => Err(type_error("Expected integer, got real")),
+            MplValue::String(_) => Err(type_error("Expected integer, got string")),
+            MplValue::Bool(_) => Err(type_error("Expected integer, got boolean")),
+            MplValue::Array(..) => Err(type_error("Expected integer, got array")),
+        }
+    }
+    /// Attempt to convert the value to a rust float. Return Error if the type is incompatible.
+    pub fn to_real(self) -> Result<f64, String> {
+        match self {
+            MplValue::Int(_) | MplValue::BigInt(_) => Err(type_error("Expected real, got integer")),
+            MplValue::Real(r) => Ok(r),
+            MplValue::String(_) => Err(type_error("Expected real, got string")),
+            MplValue::Bool(_) => Err(type_error("Expected real, got boolean")),
+            MplValue::Array(..) => Err(type_error("Expected real, got array")),
         }
     }
     /// Attempt to convert the value to a rust string. Return Error if the type is incompatible.
     pub fn to_string(self) -> Result<String, String> {
         match self {
-            MplValue::Int(_) => Err("Expected string, got integer".to_string()),
+            MplValue::Int(_) | MplValue::BigInt(_) => Err(type_error("Expected string, got integer")),
+            MplValue::Real(_) => Err(type_error("Expected string, got real")),
             MplValue::String(s) => Ok(s),
-            MplValue::Bool(_) => Err("Expected string, got boolean".to_string()),
+            MplValue::Bool(_) => Err(type_error("Expected string, got boolean")),
+            MplValue::Array(..) => Err(type_error("Expected string, got array")),
         }
     }
     /// Attempt to convert the value to a rust boolean. Return Error if the type is incompatible.
     pub fn to_bool(self) -> Result<bool, String> {
         match self {
-            MplValue::Int(_) => Err("Expected boolean, got integer".to_string()),
-            MplValue::String(_) => Err("Expected boolean, got string".to_string()),
+            MplValue::Int(_) | MplValue::BigInt(_) => Err(type_error("Expected boolean, got integer")),
+            MplValue::Real(_) => Err(type_error("Expected boolean, got real")),
+            MplValue::String(_) => Err(type_error("Expected boolean, got string")),
             MplValue::Bool(b) => Ok(b),
+            MplValue::Array(..) => Err(type_error("Expected boolean, got array")),
+        }
+    }
+
+    /// The value of an integer of any size. Only the integers that do not fit in 32 bits are
+    /// BigInts, so that every integer has a single representation.
+    pub fn from_big(i: BigInt) -> MplValue {
+        match i.to_i32() {
+            Some(i) => MplValue::Int(i),
+            None => MplValue::BigInt(i),
         }
     }
 
     /// Return the type of the value. This allows cleaner code than matching on the MplValue.
     pub fn mpl_type(&self) -> MplType {
-        match self {
-            &MplValue::Int(_) => MplType::Int,
-            &MplValue::String(_) => MplType::String,
-            &MplValue::Bool(_) => MplType::Bool,
+        match *self {
+            MplValue::Int(_) | MplValue::BigInt(_) => MplType::Int,
+            MplValue::Real(_) => MplType::Real,
+            MplValue::String(_) => MplType::String,
+            MplValue::Bool(_) => MplType::Bool,
+            MplValue::Array(ref element, ref elements)
+                => MplType::Array { element: Box::new(element.clone()), size: elements.len() },
         }
     }
 
     /// Return the default value for the corresponding type.
     /// # Defaults
     /// - 0 is the default for Int
+    /// - 0.0 is the default for Real
     /// - "" is the default for String
     /// - false is the default for Bool
-    pub fn default(mpl_type: &MplType) -> MplValue {
-        match mpl_type {
-            &MplType::Int => MplValue::Int(0),
-            &MplType::String => MplValue::String("".to_string()),
-            &MplType::Bool => MplValue::Bool(false),
+    /// - an array of default elements is the default for Array
+    ///
+    /// Fails if the memory for the elements of an array cannot be allocated.
+    pub fn default(mpl_type: &MplType) -> Result<MplValue, String> {
+        match *mpl_type {
+            MplType::Int => Ok(MplValue::Int(0)),
+            MplType::Real => Ok(MplValue::Real(0.0)),
+            MplType::String => Ok(MplValue::String("".to_string())),
+            MplType::Bool => Ok(MplValue::Bool(false)),
+            MplType::Array { ref element, size } => {
+                let mut elements = Vec::new();
+                elements.try_reserve_exact(size)
+                    .map_err(|_| format!("Not enough memory for an array of {} elements", size))?;
+                elements.resize(size, MplValue::default(element)?);
+                Ok(MplValue::Array((**element).clone(), elements))
+            },
         }
     }
 }
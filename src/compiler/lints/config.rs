@@ -0,0 +1,242 @@
+//! Configuration of the lints. Each lint is allowed, warned about or denied. The levels are
+//! set in files named plint.toml, on the command line, and by comments in the program.
+//!
+//! A plint.toml file sets levels in its `[lints]` table:
+//!
+//! ```toml
+//! # Comments start with a hash.
+//! [lints]
+//! unused-variable = "allow"
+//! dead-store = "deny"
+//! ```
+//!
+//! The files in the directory of the program and in each directory above it apply to the
+//! program, and a file in a nearer directory overrides the levels set farther away. Other
+//! tables of the files are ignored, so that plint.toml can be shared with other tools. A
+//! comment `// plint-allow: unused-variable, dead-store` allows the lints for the statements
+//! on the next line.
+//!
+//! A problem in the configuration, such as an unknown lint, leaves out the level or the
+//! comment with it, and is reported as a warning. It does not keep the program from running.
+
+use super::Lint;
+use super::super::scanner::token::Comment;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The name of the configuration files.
+pub const FILE_NAME: &str = "plint.toml";
+
+/// The level of a lint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Allow, // not reported
+    Warn, // reported as a warning
+    Deny, // reported as an error, which keeps the program from running
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Level::Allow => write!(f, "allowed"),
+            Level::Warn => write!(f, "warning"),
+            Level::Deny => write!(f, "error"),
+        }
+    }
+}
+
+/// The levels of the lints, and the lints allowed on single lines of the program. Lints are
+/// warned about by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    levels: HashMap<Lint, Level>,
+    allowed_lines: HashMap<usize, Vec<Lint>>,
+}
+
+impl Config {
+    /// The configuration of the files that apply to the program at the path, and the problems
+    /// found in them.
+    pub fn load(program: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut problems = Vec::new();
+        let directory = match Path::new(program).parent() {
+            Some(directory) if directory != Path::new("") => directory,
+            _ => Path::new("."),
+        };
+        let directory = match fs::canonicalize(directory) {
+            Ok(directory) => directory,
+            Err(e) => {
+                problems.push(format!("Finding the directory of {} failed: {}", program, e));
+                return (config, problems);
+            },
+        };
+        // The farthest file is applied first, so that the nearer ones override it.
+        let mut directories: Vec<&Path> = directory.ancestors().collect();
+        directories.reverse();
+        for directory in directories {
+            let path = directory.join(FILE_NAME);
+            match fs::read_to_string(&path) {
+                Ok(text) => problems.extend(config.parse(&text).iter()
+                    .map(|problem| format!("{}:{}", path.display(), problem))),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+                Err(e) => problems.push(format!("Reading {} failed: {}", path.display(), e)),
+            }
+        }
+        (config, problems)
+    }
+
+    /// Set the levels of a configuration file on top of the current ones. Returns the problems
+    /// found, each with the line it was found on. The lines with problems are left out.
+    pub fn parse(&mut self, text: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut table = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let result = if line.starts_with('[') {
+                table = Some(line == "[lints]");
+                Ok(())
+            } else {
+                match table {
+                    Some(true) => self.parse_level(line),
+                    Some(false) => Ok(()), // the table of another tool
+                    None => Err("Expected [lints] before the levels".to_string()),
+                }
+            };
+            if let Err(e) = result {
+                problems.push(format!("{}: {}", i + 1, e));
+            }
+        }
+        problems
+    }
+
+    /// Parse a line `name = "level"`.
+    fn parse_level(&mut self, line: &str) -> Result<(), String> {
+        let (name, level) = match line.find('=') {
+            Some(i) => (unquote(line[..i].trim()), line[i + 1..].trim()),
+            None => return Err(format!("Expected name = \"level\", got {}", line)),
+        };
+        let lint = Lint::from_name(name).ok_or(format!("Unknown lint {}", name))?;
+        let level = match level.strip_prefix('"').and_then(|level| level.strip_suffix('"')) {
+            Some(level) => Level::from_name(level).ok_or(format!("Unknown level {}", level))?,
+            None => return Err(format!("Level of {} must be a string, not {}", name, level)),
+        };
+        self.set(lint, level);
+        Ok(())
+    }
+
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    /// Allow the lints named by the plint-allow comments on the lines after them. Returns the
+    /// unknown lints named, each with the position of its comment.
+    pub fn allow_comments(&mut self, comments: &[Comment]) -> Vec<String> {
+        let mut problems = Vec::new();
+        for comment in comments {
+            let names = match comment.text.trim().strip_prefix("plint-allow:") {
+                Some(names) => names,
+                None => continue,
+            };
+            for name in names.split(',').map(str::trim) {
+                match Lint::from_name(name) {
+                    Some(lint) => self.allowed_lines.entry(comment.position.line + 1).or_default().push(lint),
+                    None => problems.push(format!("{}: Unknown lint {}", comment.position, name)),
+                }
+            }
+        }
+        problems
+    }
+
+    /// Whether some lint is denied.
+    pub fn denies_any(&self) -> bool {
+        self.levels.values().any(|&level| level == Level::Deny)
+    }
+
+    /// The level of the lint for a statement on the line.
+    pub fn level(&self, lint: Lint, line: usize) -> Level {
+        if self.allowed_lines.get(&line).is_some_and(|lints| lints.contains(&lint)) {
+            return Level::Allow;
+        }
+        self.levels.get(&lint).cloned().unwrap_or(Level::Warn)
+    }
+}
+
+/// Remove a # comment from a line, unless the # is in a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+fn unquote(key: &str) -> &str {
+    key.strip_prefix('"').and_then(|key| key.strip_suffix('"')).unwrap_or(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::scanner::token::Position;
+
+    #[test]
+    fn levels_from_files() {
+        let mut config = Config::default();
+        assert!(config.parse("# defaults\n[lints]\nunused-variable = \"allow\"\n\"dead-store\" = \"deny\" # strict\n").is_empty());
+        assert!(config.parse("[package]\nname = \"x\"\n[lints]\ndead-store = \"warn\"\n[other]\ndead-store = \"deny\"\n").is_empty());
+        assert_eq!(config.level(Lint::UnusedVariable, 1), Level::Allow);
+        assert_eq!(config.level(Lint::DeadStore, 1), Level::Warn);
+        assert_eq!(config.level(Lint::SelfAssignment, 1), Level::Warn);
+    }
+
+    #[test]
+    fn errors_in_files() {
+        let parse = |text: &str| Config::default().parse(text);
+        assert_eq!(parse("dead-store = \"deny\""), ["1: Expected [lints] before the levels"]);
+        assert_eq!(parse("[lints]\nunused = \"deny\""), ["2: Unknown lint unused"]);
+        assert_eq!(parse("[lints]\ndead-store = \"forbid\""), ["2: Unknown level forbid"]);
+        assert_eq!(parse("[lints]\ndead-store = deny"), ["2: Level of dead-store must be a string, not deny"]);
+        assert_eq!(parse("[lints]\ndead-store"), ["2: Expected name = \"level\", got dead-store"]);
+
+        let mut config = Config::default();
+        assert_eq!(config.parse("[lints]\nunused = \"deny\"\ndead-store = \"deny\"\n"), ["2: Unknown lint unused"]);
+        assert_eq!(config.level(Lint::DeadStore, 1), Level::Deny);
+    }
+
+    #[test]
+    fn inline_comments() {
+        let comment = |text: &str, line| Comment { text: text.to_string(), position: Position::new(line, 1) };
+        let mut config = Config::default();
+        config.set(Lint::DeadStore, Level::Deny);
+        assert!(config.allow_comments(&[comment(" plint-allow: dead-store, unused-variable", 3), comment(" other", 5)]).is_empty());
+        assert_eq!(config.level(Lint::DeadStore, 4), Level::Allow);
+        assert_eq!(config.level(Lint::UnusedVariable, 4), Level::Allow);
+        assert_eq!(config.level(Lint::DeadStore, 3), Level::Deny);
+        assert_eq!(config.level(Lint::DeadStore, 6), Level::Deny);
+        let mut config = Config::default();
+        assert_eq!(config.allow_comments(&[comment("plint-allow: unused, dead-store", 2)]), ["2:1: Unknown lint unused"]);
+        assert_eq!(config.level(Lint::DeadStore, 3), Level::Allow);
+    }
+}
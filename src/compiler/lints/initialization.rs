@@ -0,0 +1,226 @@
+//! Definite assignment. A variable declared without an initial value gets the default value of
+//! its type, which is rarely what the programmer meant to use. A forward data flow analysis
+//! finds the uses of such variables that some path reaches before any assignment or read into
+//! the variable. A for loop may run zero times, so an assignment in its body does not count
+//! after the loop, but the loop itself always assigns its control variable. The elements of
+//! arrays are not followed.
+
+use super::{Lint, Warning};
+use super::super::parser::ast::*;
+
+use std::collections::{HashMap, HashSet};
+
+/// Warn about the uses of variables that may not have been assigned, in the program and in
+/// each procedure and function.
+pub fn uninitialized(ast: &Ast) -> Vec<Warning> {
+    let mut warnings = uninitialized_in(&ast.statements);
+    for statement in &ast.statements {
+        if let Statement::Function(ref function) = statement.node {
+            warnings.extend(uninitialized_in(&function.statements));
+        }
+    }
+    warnings.sort_by_key(|warning| warning.position);
+    warnings
+}
+
+fn uninitialized_in(statements: &[Located<Statement>]) -> Vec<Warning> {
+    let mut initialization = Initialization { loops: Vec::new(), warnings: HashMap::new() };
+    initialization.block(statements, HashSet::new());
+    initialization.warnings.into_values().flatten().collect()
+}
+
+/// The state at a point of the program is the set of variables that may be unassigned there.
+/// The state of a point that cannot be reached is empty.
+type State = HashSet<String>;
+
+/// The analysis holds the states at the breaks and continues of the enclosing loops, and the
+/// warnings by statement. A statement is visited again each time the analysis of a loop around
+/// it is repeated, and the last visit decides.
+struct Initialization {
+    loops: Vec<(State, State)>, // the states at the breaks and at the continues
+    warnings: HashMap<Position, Vec<Warning>>,
+}
+
+impl Initialization {
+    /// The state after the statements, given the state before them.
+    fn block(&mut self, statements: &[Located<Statement>], before: State) -> State {
+        statements.iter().fold(before, |state, statement| self.statement(statement, state))
+    }
+
+    fn statement(&mut self, statement: &Located<Statement>, mut state: State) -> State {
+        // The uses of a statement happen before its assignment.
+        let mut before = state.clone();
+        let mut uses = Vec::new();
+        let after = match statement.node {
+            Statement::Declaration { ref identifier, ref mpl_type, ref value } => {
+                match *value {
+                    Some(ref expr) => { uses.push(expr); state.remove(identifier); },
+                    None if !is_array(mpl_type) => { state.insert(identifier.clone()); },
+                    None => (),
+                }
+                state.clone()
+            },
+            Statement::Assignment { ref identifier, ref index, ref expression } => {
+                uses.extend(index.as_ref());
+                uses.push(expression);
+                if index.is_none() {
+                    state.remove(identifier);
+                }
+                state.clone()
+            },
+            Statement::Read { ref identifier, ref index } => {
+                uses.extend(index.as_ref());
+                if index.is_none() {
+                    state.remove(identifier);
+                }
+                state.clone()
+            },
+            Statement::For { ref identifier, ref begin, ref end, ref step, ref statements, .. } => {
+                uses.push(begin);
+                uses.push(end);
+                uses.extend(step.as_ref());
+                // The control variable is assigned before each iteration, and after the last one.
+                let (head, breaks) = self.repeat(statements, state.clone(), Some(identifier));
+                head.union(&breaks).filter(|variable| *variable != identifier).cloned().collect()
+            },
+            Statement::While { ref condition, ref statements } => {
+                uses.push(condition);
+                let (head, breaks) = self.repeat(statements, state.clone(), None);
+                let after = head.union(&breaks).cloned().collect();
+                // The condition is evaluated at the head of each iteration.
+                before = head;
+                after
+            },
+            Statement::If { ref condition, ref statements, ref else_statements } => {
+                uses.push(condition);
+                let mut after = self.block(statements, state.clone());
+                after.extend(self.block(else_statements, state.clone()));
+                after
+            },
+            Statement::Break | Statement::Continue => {
+                if let Some(&mut (ref mut breaks, ref mut continues)) = self.loops.last_mut() {
+                    let target = if statement.node == Statement::Break { breaks } else { continues };
+                    target.extend(state.iter().cloned());
+                }
+                State::new()
+            },
+            Statement::Return(ref value) => {
+                uses.extend(value.as_ref());
+                State::new()
+            },
+            Statement::Call { ref arguments, .. } => { uses.extend(arguments); state.clone() },
+            Statement::Print(ref expr) | Statement::Assert(ref expr) => { uses.push(expr); state.clone() },
+            // Procedures and functions are analysed on their own, and constants always have a value.
+            Statement::Constant { .. } | Statement::Function(_) | Statement::Empty => state.clone(),
+        };
+        let mut variables = Vec::new();
+        for expr in uses {
+            used_variables(expr, &mut variables);
+        }
+        let mut reported = HashSet::new();
+        let mut warnings = Vec::new();
+        for variable in variables {
+            if before.contains(variable) && reported.insert(variable) {
+                let message = format!("Variable {} may be used before it is assigned", variable);
+                warnings.push(Warning::new(Lint::Uninitialized, statement, message));
+            }
+        }
+        self.warnings.insert(statement.position, warnings);
+        after
+    }
+
+    /// The state at the head of a loop with the body, that is entered with the given state,
+    /// and the state at its breaks. The head is reached from before the loop, from the end of
+    /// the body and from the continues, and the analysis is repeated until it does not change.
+    /// A for loop assigns its control variable at the head.
+    fn repeat(&mut self, statements: &[Located<Statement>], before: State, control: Option<&String>) -> (State, State) {
+        let mut head = before.clone();
+        if let Some(control) = control {
+            head.remove(control);
+        }
+        loop {
+            self.loops.push((State::new(), State::new()));
+            let end = self.block(statements, head.clone());
+            let (breaks, continues) = self.loops.pop().expect("the loop was pushed");
+            let mut next: State = before.union(&end).chain(continues.iter()).cloned().collect();
+            if let Some(control) = control {
+                next.remove(control);
+            }
+            if next == head {
+                return (head, breaks);
+            }
+            head = next;
+        }
+    }
+}
+
+fn is_array(mpl_type: &MplType) -> bool {
+    matches!(*mpl_type, MplType::Array { .. })
+}
+
+/// The variables whose values the expression uses, in order.
+fn used_variables<'a>(expr: &'a Expression, variables: &mut Vec<&'a str>) {
+    match *expr {
+        Expression::Simple(ref operand) | Expression::Unary { ref operand, .. } => operand_variables(operand, variables),
+        Expression::Binary { ref left, ref right, .. } => {
+            operand_variables(left, variables);
+            operand_variables(right, variables);
+        },
+    }
+}
+
+fn operand_variables<'a>(operand: &'a Operand, variables: &mut Vec<&'a str>) {
+    match *operand {
+        Operand::Identifier(ref identifier) => variables.push(identifier),
+        Operand::Index { ref index, .. } => used_variables(index, variables),
+        Operand::Call { ref arguments, .. } => for argument in arguments {
+            used_variables(argument, variables);
+        },
+        Operand::Expr(ref expr) => used_variables(expr, variables),
+        Operand::Size(_) | Operand::Int(_) | Operand::BigInt(_) | Operand::Real(_) | Operand::String(_)
+        | Operand::Bool(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::{checker, parser, scanner};
+
+    fn uninitialized_source(source: &str) -> Vec<String> {
+        let ast = parser::parse(scanner::scan(source, false).unwrap()).unwrap();
+        checker::check(&ast).unwrap();
+        uninitialized(&ast).iter().map(|warning| format!("{}: {}", warning.position, warning.message)).collect()
+    }
+
+    #[test]
+    fn assignments_on_all_paths() {
+        let source = "var x : int;\nvar y : int;\nvar b : bool := true;\nif b then x := 1; read y; else read x; end if;\n\
+                      print x + y;\nvar i : int;\nprint i;\nvar c : int;\nc := c + 1;\n";
+        assert_eq!(uninitialized_source(source), vec![
+            "5:1: Variable y may be used before it is assigned",
+            "7:1: Variable i may be used before it is assigned",
+            "9:1: Variable c may be used before it is assigned",
+        ]);
+    }
+
+    #[test]
+    fn loops_may_run_zero_times() {
+        let source = "var n : int := 0;\nvar x : int;\nvar i : int;\nfor i in 1..n do x := i; end for;\nprint x;\nprint i;\n\
+                      var y : int;\nwhile n < 3 do\n  if n > 0 then print y; end if;\n  y := n;\n  n := n + 1;\nend while;\n";
+        assert_eq!(uninitialized_source(source), vec!["5:1: Variable x may be used before it is assigned", "9:17: Variable y may be used before it is assigned"]);
+    }
+
+    #[test]
+    fn breaks_continues_and_functions() {
+        let source = "var b : bool;\nread b;\nvar x : int;\nwhile b do\n  if b then break; end if;\n  x := 1;\nend while;\nprint x;\n\
+                      var z : int;\nwhile (!b) do\n  if b then continue; end if;\n  z := 1;\n  break;\nend while;\nprint z;\n\
+                      function f(p : int) : int do var q : int; if p > 0 then return p; end if; return q; end function;\n\
+                      var a : array [2] of int;\nprint a[0];\n";
+        assert_eq!(uninitialized_source(source), vec![
+            "8:1: Variable x may be used before it is assigned",
+            "15:1: Variable z may be used before it is assigned",
+            "16:75: Variable q may be used before it is assigned",
+        ]);
+    }
+}
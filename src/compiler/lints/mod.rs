@@ -0,0 +1,515 @@
+//! Lints: warnings about programs that are well typed, but probably do not do what the
+//! programmer meant. Each lint has a stable name, by which it can be allowed, and every
+//! warning covers the statement it was found in.
+//!
+//! Whether a stored value is ever used is decided by a liveness analysis, which follows the
+//! control flow backwards: the branches of an if statement, loops that run any number of times
+//! (also zero), break, continue and return. The variables of a program and of each procedure
+//! and function are analysed separately.
+
+mod config;
+mod initialization;
+
+use super::interpreter::builtins;
+use super::parser::ast::*;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+
+pub use self::config::{Config, Level};
+pub use self::initialization::uninitialized;
+
+/// The lints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable, // a variable or constant is declared but its value is never used
+    DeadStore, // a value assigned to a variable is never used
+    OverwrittenRead, // a value read into a variable is overwritten before it is used
+    SelfAssignment, // a variable is assigned to itself
+    ConstantAssert, // an assertion does not depend on the variables of the program
+    Uninitialized, // a variable may be used before it is assigned
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::UnusedVariable,
+        Lint::DeadStore,
+        Lint::OverwrittenRead,
+        Lint::SelfAssignment,
+        Lint::ConstantAssert,
+        Lint::Uninitialized,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::DeadStore => "dead-store",
+            Lint::OverwrittenRead => "overwritten-read",
+            Lint::SelfAssignment => "self-assignment",
+            Lint::ConstantAssert => "constant-assert",
+            Lint::Uninitialized => "uninitialized",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().cloned().find(|lint| lint.name() == name)
+    }
+}
+
+/// A warning of a lint about the statement from position to end. A denied lint is reported as
+/// an error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub level: Level,
+    pub position: Position,
+    pub end: Position,
+    pub message: String,
+}
+
+impl Warning {
+    fn new(lint: Lint, statement: &Located<Statement>, message: String) -> Warning {
+        Warning { lint, level: Level::Warn, position: statement.position, end: statement.end, message }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}: {}: {} [{}]", self.position, self.end, self.level, self.message, self.lint.name())
+    }
+}
+
+/// Lint a checked AST. Returns the warnings of the lints that are not allowed, at the levels
+/// of the configuration, in the order of the statements.
+pub fn lint(ast: &Ast, config: &Config) -> Vec<Warning> {
+    let mut linter = Linter { scopes: Vec::new(), warnings: Vec::new() };
+    linter.visit_block(&ast.statements);
+    let mut warnings = linter.warnings;
+    warnings.extend(unused_stores(&ast.statements));
+    warnings.extend(uninitialized(ast));
+    for statement in &ast.statements {
+        if let Statement::Function(ref function) = statement.node {
+            warnings.extend(unused_stores(&function.statements));
+        }
+    }
+    for warning in &mut warnings {
+        warning.level = config.level(warning.lint, warning.position.line);
+    }
+    warnings.retain(|warning| warning.level != Level::Allow);
+    warnings.sort_by_key(|warning| warning.position);
+    warnings
+}
+
+/// A declared variable or constant.
+struct Declared {
+    statement: Located<Statement>, // the declaration
+    constant: bool,
+    used: bool,
+}
+
+/// The linter holds the chain of scopes of the declared variables, and the warnings found so
+/// far. It finds the lints that need no data flow.
+struct Linter {
+    scopes: Vec<HashMap<String, Declared>>, // innermost scope last
+    warnings: Vec<Warning>,
+}
+
+impl Visitor for Linter {
+    fn visit_statement(&mut self, statement: &Located<Statement>) {
+        walk_statement(self, statement);
+        match statement.node {
+            Statement::Declaration { ref identifier, .. } => self.declare(identifier, statement, false),
+            Statement::Constant { ref identifier, .. } => self.declare(identifier, statement, true),
+            Statement::Assignment { ref identifier, ref index, ref expression }
+                if is_self_assignment(identifier, index.as_ref(), expression) => {
+                let message = format!("{} is assigned to itself", expression);
+                self.warnings.push(Warning::new(Lint::SelfAssignment, statement, message));
+            },
+            // The loop uses the control variable to count its iterations.
+            Statement::For { ref identifier, .. } => self.use_variable(identifier),
+            Statement::Assert(ref expr) if self.is_constant_expression(expr) => {
+                let message = format!("Assertion {} does not depend on the variables of the program", expr);
+                self.warnings.push(Warning::new(Lint::ConstantAssert, statement, message));
+            },
+            _ => (),
+        }
+    }
+
+    /// Lint the statements of a block in a new scope, and warn about the variables of the
+    /// scope that were not used.
+    fn visit_block(&mut self, statements: &[Located<Statement>]) {
+        self.scopes.push(HashMap::new());
+        walk_block(self, statements);
+        let mut unused: Vec<Declared> = self.scopes.pop().expect("the scope was pushed")
+            .into_values()
+            .filter(|declared| !declared.used)
+            .collect();
+        unused.sort_by_key(|declared| declared.statement.position);
+        for declared in unused {
+            let message = match declared.statement.node {
+                Statement::Constant { ref identifier, .. } => format!("Constant {} is never used", identifier),
+                Statement::Declaration { ref identifier, .. } => format!("Variable {} is never used", identifier),
+                _ => unreachable!("only declarations are declared"),
+            };
+            self.warnings.push(Warning::new(Lint::UnusedVariable, &declared.statement, message));
+        }
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        let globals = mem::take(&mut self.scopes);
+        walk_function(self, function);
+        self.scopes = globals;
+    }
+
+    fn visit_operand(&mut self, operand: &Operand) {
+        match *operand {
+            Operand::Identifier(ref identifier) | Operand::Size(ref identifier) | Operand::Index { ref identifier, .. }
+                => self.use_variable(identifier),
+            _ => (),
+        }
+        walk_operand(self, operand);
+    }
+}
+
+impl Linter {
+    fn declare(&mut self, identifier: &str, statement: &Located<Statement>, constant: bool) {
+        let declared = Declared { statement: statement.clone(), constant, used: false };
+        self.scopes.last_mut().expect("there is always a scope").insert(identifier.to_string(), declared);
+    }
+
+    fn lookup(&mut self, identifier: &str) -> Option<&mut Declared> {
+        self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(identifier))
+    }
+
+    /// Mark the variable as used. Parameters are not declared in the scopes, and are ignored.
+    fn use_variable(&mut self, identifier: &str) {
+        if let Some(declared) = self.lookup(identifier) {
+            declared.used = true;
+        }
+    }
+
+    /// Whether the expression is made of literals, constants and builtin functions only.
+    fn is_constant_expression(&mut self, expr: &Expression) -> bool {
+        match *expr {
+            Expression::Simple(ref operand) | Expression::Unary { ref operand, .. } => self.is_constant_operand(operand),
+            Expression::Binary { ref left, ref right, .. } => self.is_constant_operand(left) && self.is_constant_operand(right),
+        }
+    }
+
+    fn is_constant_operand(&mut self, operand: &Operand) -> bool {
+        match *operand {
+            Operand::Int(_) | Operand::BigInt(_) | Operand::Real(_) | Operand::String(_) | Operand::Bool(_) => true,
+            Operand::Identifier(ref identifier) => self.lookup(identifier).is_some_and(|declared| declared.constant),
+            Operand::Call { ref identifier, ref arguments } => builtins::builtin(identifier).is_some()
+                && arguments.iter().all(|argument| self.is_constant_expression(argument)),
+            Operand::Expr(ref expr) => self.is_constant_expression(expr),
+            Operand::Index { .. } | Operand::Size(_) => false,
+        }
+    }
+}
+
+/// Whether the assignment assigns a variable, or an element of an array, to itself.
+fn is_self_assignment(identifier: &str, index: Option<&Expression>, expression: &Expression) -> bool {
+    match (index, expression) {
+        (None, Expression::Simple(Operand::Identifier(source))) => source == identifier,
+        (Some(index), Expression::Simple(Operand::Index { identifier: source, index: source_index }))
+            => source == identifier && **source_index == *index,
+        _ => false,
+    }
+}
+
+/// Warn about the values assigned or read into variables that are never used. The values of
+/// variables that are never used at all are left to the unused-variable lint.
+fn unused_stores(statements: &[Located<Statement>]) -> Vec<Warning> {
+    // A value read at the end of the program is used to skip input, so a read value is only
+    // unused if every path overwrites it.
+    let mut read_targets = ReadTargets(HashSet::new());
+    read_targets.visit_block(statements);
+    let read_targets = read_targets.0;
+    let mut assignments = Liveness::new(Lint::DeadStore, HashSet::new());
+    assignments.live_block(statements, HashSet::new());
+    let mut reads = Liveness::new(Lint::OverwrittenRead, read_targets.clone());
+    reads.live_block(statements, read_targets);
+
+    let used = assignments.used;
+    let mut warnings: Vec<Warning> = assignments.unused.into_values()
+        .chain(reads.unused.into_values())
+        .flatten()
+        .filter(|(identifier, _)| used.contains(identifier))
+        .map(|(_, warning)| warning)
+        .collect();
+    warnings.sort_by_key(|warning| warning.position);
+    warnings
+}
+
+/// The identifiers read into, outside of procedures and functions.
+struct ReadTargets(HashSet<String>);
+
+impl Visitor for ReadTargets {
+    fn visit_statement(&mut self, statement: &Located<Statement>) {
+        if let Statement::Read { ref identifier, index: None } = statement.node {
+            self.0.insert(identifier.clone());
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_function(&mut self, _function: &Function) {}
+}
+
+/// A backwards liveness analysis. The live variables at a point of the program are the ones
+/// whose value may be used after it.
+struct Liveness {
+    lint: Lint, // the lint of the stores that are reported
+    exit: HashSet<String>, // live when the program, procedure or function ends
+    loops: Vec<(HashSet<String>, HashSet<String>)>, // live after and at the head of the enclosing loops
+    used: HashSet<String>, // the variables whose value is used anywhere
+    // The stores by statement, and the variable and warning of the unused ones. A store is
+    // visited again each time the analysis of a loop around it is repeated, and the last visit
+    // decides.
+    unused: HashMap<Position, Option<(String, Warning)>>,
+}
+
+impl Liveness {
+    fn new(lint: Lint, exit: HashSet<String>) -> Liveness {
+        Liveness { lint, exit, loops: Vec::new(), used: HashSet::new(), unused: HashMap::new() }
+    }
+
+    /// The live variables before the statements, given the live variables after them.
+    fn live_block(&mut self, statements: &[Located<Statement>], after: HashSet<String>) -> HashSet<String> {
+        statements.iter().rev().fold(after, |live, statement| self.live_statement(statement, live))
+    }
+
+    fn live_statement(&mut self, statement: &Located<Statement>, mut live: HashSet<String>) -> HashSet<String> {
+        match statement.node {
+            Statement::Declaration { ref identifier, ref value, .. } => {
+                if let Some(ref expr) = *value {
+                    let message = format!("Initial value of {} is never used", identifier);
+                    self.store(statement, identifier, Lint::DeadStore, message, &live);
+                    live.remove(identifier);
+                    self.uses(expr, &mut live);
+                } else {
+                    live.remove(identifier);
+                }
+                live
+            },
+            Statement::Constant { ref identifier, .. } => { live.remove(identifier); live },
+            Statement::Assignment { ref identifier, index: None, ref expression } => {
+                if !is_self_assignment(identifier, None, expression) {
+                    let message = format!("Value assigned to {} is never used", identifier);
+                    self.store(statement, identifier, Lint::DeadStore, message, &live);
+                }
+                live.remove(identifier);
+                self.uses(expression, &mut live);
+                live
+            },
+            Statement::Assignment { ref identifier, index: Some(ref index), ref expression } => {
+                // The other elements of the array keep their values.
+                live.insert(identifier.clone());
+                self.uses(index, &mut live);
+                self.uses(expression, &mut live);
+                live
+            },
+            Statement::Read { ref identifier, index: None } => {
+                let message = format!("Value read into {} is overwritten before it is used", identifier);
+                self.store(statement, identifier, Lint::OverwrittenRead, message, &live);
+                live.remove(identifier);
+                live
+            },
+            Statement::Read { ref identifier, index: Some(ref index) } => {
+                live.insert(identifier.clone());
+                self.uses(index, &mut live);
+                live
+            },
+            Statement::For { ref identifier, ref begin, ref end, ref step, ref statements, .. } => {
+                // The control variable is assigned before each iteration, and after the last one.
+                let mut head = live.clone();
+                head.remove(identifier);
+                loop {
+                    self.loops.push((live.clone(), head.clone()));
+                    let body = self.live_block(statements, head.clone());
+                    self.loops.pop();
+                    let mut next: HashSet<String> = live.union(&body).cloned().collect();
+                    next.remove(identifier);
+                    if next == head {
+                        break;
+                    }
+                    head = next;
+                }
+                for expr in Some(begin).into_iter().chain(Some(end)).chain(step.as_ref()) {
+                    self.uses(expr, &mut head);
+                }
+                head
+            },
+            Statement::If { ref condition, ref statements, ref else_statements } => {
+                let mut before = self.live_block(statements, live.clone());
+                before.extend(self.live_block(else_statements, live));
+                self.uses(condition, &mut before);
+                before
+            },
+            Statement::While { ref condition, ref statements } => {
+                let mut head = live.clone();
+                self.uses(condition, &mut head);
+                loop {
+                    self.loops.push((live.clone(), head.clone()));
+                    let body = self.live_block(statements, head.clone());
+                    self.loops.pop();
+                    let mut next: HashSet<String> = live.union(&body).cloned().collect();
+                    self.uses(condition, &mut next);
+                    if next == head {
+                        break;
+                    }
+                    head = next;
+                }
+                head
+            },
+            Statement::Break => self.loops.last().map_or(live, |(after, _)| after.clone()),
+            Statement::Continue => self.loops.last().map_or(live, |(_, head)| head.clone()),
+            Statement::Return(ref value) => {
+                let mut exit = self.exit.clone();
+                if let Some(ref expr) = *value {
+                    self.uses(expr, &mut exit);
+                }
+                exit
+            },
+            Statement::Call { ref arguments, .. } => {
+                for argument in arguments {
+                    self.uses(argument, &mut live);
+                }
+                live
+            },
+            Statement::Print(ref expr) | Statement::Assert(ref expr) => { self.uses(expr, &mut live); live },
+            // Procedures and functions are analysed on their own.
+            Statement::Function(_) | Statement::Empty => live,
+        }
+    }
+
+    /// Record whether the value stored into the variable by the statement is used.
+    fn store(&mut self, statement: &Located<Statement>, identifier: &str, lint: Lint, message: String, live: &HashSet<String>) {
+        if lint != self.lint {
+            return;
+        }
+        let unused = if live.contains(identifier) {
+            None
+        } else {
+            Some((identifier.to_string(), Warning::new(lint, statement, message)))
+        };
+        self.unused.insert(statement.position, unused);
+    }
+
+    /// Add the variables used by the expression to the live ones.
+    fn uses(&mut self, expr: &Expression, live: &mut HashSet<String>) {
+        match *expr {
+            Expression::Simple(ref operand) | Expression::Unary { ref operand, .. } => self.operand_uses(operand, live),
+            Expression::Binary { ref left, ref right, .. } => {
+                self.operand_uses(left, live);
+                self.operand_uses(right, live);
+            },
+        }
+    }
+
+    fn operand_uses(&mut self, operand: &Operand, live: &mut HashSet<String>) {
+        match *operand {
+            Operand::Identifier(ref identifier) => {
+                self.used.insert(identifier.clone());
+                live.insert(identifier.clone());
+            },
+            Operand::Index { ref identifier, ref index } => {
+                self.used.insert(identifier.clone());
+                live.insert(identifier.clone());
+                self.uses(index, live);
+            },
+            Operand::Call { ref arguments, .. } => for argument in arguments {
+                self.uses(argument, live);
+            },
+            Operand::Expr(ref expr) => self.uses(expr, live),
+            // The size of an array does not depend on its value.
+            Operand::Size(_) | Operand::Int(_) | Operand::BigInt(_) | Operand::Real(_) | Operand::String(_)
+            | Operand::Bool(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{checker, parser, scanner};
+
+    fn lint_source(source: &str) -> Vec<String> {
+        let ast = parser::parse(scanner::scan(source, false).unwrap()).unwrap();
+        checker::check(&ast).unwrap();
+        // The variables of these tests rely on their default values.
+        let mut config = Config::default();
+        config.set(Lint::Uninitialized, Level::Allow);
+        lint(&ast, &config).iter().map(|warning| warning.to_string()).collect()
+    }
+
+    #[test]
+    fn unused_variables() {
+        let source = "var x : int;\nvar y : int := 1;\nconst c : int := 2;\nvar i : int;\n\
+                      for i in 1..3 do var z : string; end for;\nprint y;\n\
+                      procedure p(n : int) do var x : bool; end procedure;\n";
+        assert_eq!(lint_source(source), vec![
+            "1:1-1:12: warning: Variable x is never used [unused-variable]",
+            "3:1-3:19: warning: Constant c is never used [unused-variable]",
+            "5:18-5:32: warning: Variable z is never used [unused-variable]",
+            "7:25-7:37: warning: Variable x is never used [unused-variable]",
+        ]);
+    }
+
+    #[test]
+    fn dead_stores() {
+        let source = "var x : int := 1;\nx := 2;\nif x > 1 then x := 3; else print x; end if;\n\
+                      var i : int;\nvar sum : int;\nfor i in 1..x do sum := sum + i; end for;\n\
+                      x := sum;\nprint i;\n";
+        assert_eq!(lint_source(source), vec![
+            "1:1-1:17: warning: Initial value of x is never used [dead-store]",
+            "7:1-7:9: warning: Value assigned to x is never used [dead-store]",
+        ]);
+    }
+
+    #[test]
+    fn stores_in_loops() {
+        // The values are used by the next iteration, after a continue, or after a break.
+        let source = "var n : int;\nvar last : int;\nvar b : bool;\n\
+                      while n < 10 do\n  n := n + 1;\n  last := n;\n  if b then continue; end if;\n\
+                      if n = 5 then break; end if;\nend while;\nprint last;\n";
+        assert_eq!(lint_source(source), Vec::<String>::new());
+        let source = "var n : int;\nwhile n < 10 do\n  n := n + 1;\n  break;\nend while;\n";
+        assert_eq!(lint_source(source), vec!["3:3-3:13: warning: Value assigned to n is never used [dead-store]"]);
+    }
+
+    #[test]
+    fn overwritten_reads() {
+        let source = "var x : int;\nread x;\nread x;\nprint x;\nread x;\n\
+                      var s : string;\nread s;\nif x > 0 then s := \"\"; end if;\nprint s;\n";
+        assert_eq!(lint_source(source), vec!["2:1-2:7: warning: Value read into x is overwritten before it is used [overwritten-read]"]);
+    }
+
+    #[test]
+    fn self_assignments_and_constant_asserts() {
+        let source = "var x : int;\nvar a : array [2] of int;\nx := x;\na[x] := a[x];\na[0] := a[1];\n\
+                      const c : int := 1;\nassert (c = 1);\nassert (length(\"abc\") = 3);\nassert (x = 1);\n";
+        assert_eq!(lint_source(source), vec![
+            "3:1-3:7: warning: x is assigned to itself [self-assignment]",
+            "4:1-4:13: warning: a[x] is assigned to itself [self-assignment]",
+            "7:1-7:15: warning: Assertion c = 1 does not depend on the variables of the program [constant-assert]",
+            "8:1-8:27: warning: Assertion length(\"abc\") = 3 does not depend on the variables of the program [constant-assert]",
+        ]);
+    }
+
+    #[test]
+    fn allowed_lints() {
+        let source = "var x : int;\nvar y : int;\ny := y;\n";
+        let ast = parser::parse(scanner::scan(source, false).unwrap()).unwrap();
+        let mut config = Config::default();
+        config.set(Lint::SelfAssignment, Level::Allow);
+        config.set(Lint::Uninitialized, Level::Allow);
+        config.set(Lint::UnusedVariable, Level::Deny);
+        let warnings = lint(&ast, &config);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "1:1-1:12: error: Variable x is never used [unused-variable]");
+        assert_eq!(Lint::from_name("dead-store"), Some(Lint::DeadStore));
+        assert_eq!(Lint::from_name("dead_store"), None);
+    }
+}
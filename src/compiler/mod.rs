@@ -1,24 +1,346 @@
+mod bigint;
 mod scanner;
 mod parser;
+mod checker;
+mod lints;
+mod constants;
+mod optimizer;
+mod cfg;
+mod serial;
 mod interpreter;
+#[cfg(test)]
+mod fuzz;
 
+use self::parser::ast::Ast;
 
-/// Run and Mpl program defined by the source.
+use std::thread;
+
+pub use self::interpreter::TraceFormat;
+
+/// The native stack reserved for each nested procedure or function call of the program. The
+/// interpreter recurses over the AST, so a deep recursion needs a deep stack. The frames of an
+/// unoptimised build are several times larger.
+#[cfg(not(debug_assertions))]
+const STACK_PER_CALL: usize = 32 * 1024;
+#[cfg(debug_assertions)]
+const STACK_PER_CALL: usize = 128 * 1024;
+
+/// Options for running a program.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Write a trace of the execution to standard error.
+    pub trace: Option<TraceFormat>,
+    /// Write the source annotated with execution counts to standard error.
+    pub coverage: bool,
+    /// Write the coverage as an LCOV tracefile to the path.
+    pub lcov: Option<String>,
+    /// Write a table of the statements and expressions taking the most time to standard error.
+    pub profile: bool,
+    /// Write the profile as folded stacks for flamegraph tools to the path.
+    pub profile_folded: Option<String>,
+    /// Record the input lines read by the program to a session log at the path.
+    pub record: Option<String>,
+    /// Read the input lines from a session log at the path instead of standard input.
+    pub replay: Option<String>,
+    /// Limit the depth of nested procedure and function calls, instead of the default limit.
+    pub max_depth: Option<usize>,
+    /// Make integers unbounded, instead of 32 bits where an overflow is an error.
+    pub bigint: bool,
+    /// Run the program as written, without optimising it. The trace, coverage and profile
+    /// always describe the program as written.
+    pub no_optimize: bool,
+    /// Refuse to run a program that may use a variable before it is assigned.
+    pub strict_init: bool,
+    /// Levels of lints by name, such as ("deny", "dead-store"), that override the levels of the
+    /// configuration files. A program with a denied lint is not run.
+    pub lint_levels: Vec<(String, String)>,
+    /// Read the program as an AST in JSON, instead of as Mini-PL source.
+    pub from_json: bool,
+    /// The format of the control-flow graphs, the AST and the tokens.
+    pub format: Format,
+}
+
+/// The formats the control-flow graphs, the AST and the tokens are shown in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Dot, // the DOT language of Graphviz
+    Json,
+    SExpression,
+}
+
+impl Options {
+    /// Whether a monitor reports on the statements and expressions of the program.
+    fn is_monitored(&self) -> bool {
+        self.trace.is_some() || self.coverage || self.lcov.is_some() || self.profile || self.profile_folded.is_some()
+    }
+}
+
+/// Run and Mpl program defined by the source, read from the file with the given name.
 /// Upon an error terminates execution and prints the error to standard output
-pub fn run(source: String) {
-    let tokens = match scanner::scan(&source) {
+pub fn run(filename: &str, source: String, options: &Options) {
+    let max_depth = options.max_depth.unwrap_or(interpreter::DEFAULT_MAX_DEPTH);
+    with_stack(max_depth, || run_program(filename, source, options));
+}
+
+fn run_program(filename: &str, source: String, options: &Options) {
+    if options.from_json && options.is_monitored() {
+        println!("Cannot trace, cover or profile a program read from JSON, which has no source");
+        return;
+    }
+    let mut ast = match read_ast(&source, options) {
+        Ok(ast) => ast,
+        Err(e) => { println!("{}", e); return; },
+    };
+    let (config, problems) = match lint_config(filename, &source, options) {
+        Ok(config) => config,
+        Err(e) => { println!("{}", e); return; },
+    };
+    for problem in problems {
+        eprintln!("warning: {}", problem);
+    }
+    if let Err(e) = check_denied_lints(&ast, &config) {
+        println!("{}", e);
+        return;
+    }
+    if let Err(e) = fold_constants(&mut ast, options.bigint) {
+        println!("{}", e);
+        return;
+    }
+    if options.strict_init {
+        let errors: Vec<String> = lints::uninitialized(&ast).iter()
+            .map(|warning| format!("{}: {}", warning.position, warning.message))
+            .collect();
+        if !errors.is_empty() {
+            println!("Initialization check failed:\n{}", errors.join("\n"));
+            return;
+        }
+    }
+    if !(options.no_optimize || options.is_monitored()) {
+        optimizer::optimize(&mut ast, options.bigint);
+    }
+
+    let mut interpreter = interpreter::Interpreter::new();
+    interpreter.set_bigint(options.bigint);
+    if let Err(e) = set_input(&mut interpreter, options) {
+        println!("{}", e);
+        return;
+    }
+    if let Some(max_depth) = options.max_depth {
+        interpreter.set_max_depth(max_depth);
+    }
+    if let Some(format) = options.trace {
+        interpreter.add_monitor(Box::new(interpreter::Tracer::new(&source, format)));
+    }
+    if options.coverage || options.lcov.is_some() {
+        let coverage = interpreter::Coverage::new(filename, &source, &ast, options.coverage, options.lcov.clone());
+        interpreter.add_monitor(Box::new(coverage));
+    }
+    if options.profile || options.profile_folded.is_some() {
+        interpreter.add_monitor(Box::new(interpreter::Profiler::new(&source, options.profile_folded.clone())));
+    }
+    let result = interpreter.run(ast);
+    match result {
+        Ok(_) => (),
+        Err(e) => println!("Runtime error: {}", e),
+    }
+}
+
+/// Report the lints of an Mpl program, read from the file with the given name, one per line.
+/// The levels of the lints are set by the configuration files, the options, and the
+/// plint-allow comments of the program. The problems of the configuration are reported first.
+pub fn lint(filename: &str, source: String, options: &Options) {
+    let ast = match read_ast(&source, options) {
+        Ok(ast) => ast,
+        Err(e) => { println!("{}", e); return; },
+    };
+    let (config, problems) = match lint_config(filename, &source, options) {
+        Ok(config) => config,
+        Err(e) => { println!("{}", e); return; },
+    };
+    for problem in problems {
+        println!("warning: {}", problem);
+    }
+    for warning in lints::lint(&ast, &config) {
+        println!("{}", warning);
+    }
+}
+
+/// The configuration of the lints of the program: the configuration files, overridden by the
+/// options, overridden by the comments of the program. Also returns the problems found in the
+/// files and the comments, which leave out the levels they are found in. Only an unknown lint
+/// in the options is an error.
+fn lint_config(filename: &str, source: &str, options: &Options) -> Result<(lints::Config, Vec<String>), String> {
+    let (mut config, mut problems) = lints::Config::load(filename);
+    for (level, name) in &options.lint_levels {
+        let level = lints::Level::from_name(level).ok_or(format!("Unknown level {}", level))?;
+        let lint = lints::Lint::from_name(name).ok_or(format!("Unknown lint {}", name))?;
+        config.set(lint, level);
+    }
+    if !options.from_json {
+        match scanner::scan_with_comments(source, options.bigint) {
+            Ok((_, comments)) => problems.extend(config.allow_comments(&comments)),
+            Err(e) => problems.push(format!("Scanning failed: {}", e)),
+        }
+    }
+    Ok((config, problems))
+}
+
+/// Fail if the program has lints that are denied.
+fn check_denied_lints(ast: &Ast, config: &lints::Config) -> Result<(), String> {
+    if !config.denies_any() {
+        return Ok(());
+    }
+    let errors: Vec<String> = lints::lint(ast, config).iter()
+        .filter(|warning| warning.level == lints::Level::Deny)
+        .map(|warning| format!("{}: {} [{}]", warning.position, warning.message, warning.lint.name()))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Lint check failed:\n{}", errors.join("\n")))
+    }
+}
+
+/// Print the control-flow graphs of the basic blocks of an Mpl program and of each of its
+/// procedures and functions, as text or in the DOT language.
+pub fn cfg(source: String, options: &Options) {
+    let ast = match read_ast(&source, options) {
+        Ok(ast) => ast,
+        Err(e) => { println!("{}", e); return; },
+    };
+    let graphs = cfg::build(&ast);
+    match options.format {
+        Format::Text => for graph in graphs {
+            print!("{}", graph);
+        },
+        Format::Dot => print!("{}", cfg::to_dot(&graphs)),
+        Format::Json | Format::SExpression => println!("The control-flow graphs can be shown as text or in DOT"),
+    }
+}
+
+/// Print the AST of an Mpl program as an indented tree, in the DOT language, or serialised
+/// with the types of its expressions.
+pub fn ast(source: String, options: &Options) {
+    let ast = match read_ast(&source, options) {
+        Ok(ast) => ast,
+        Err(e) => { println!("{}", e); return; },
+    };
+    match options.format {
+        Format::Text => print!("{}", parser::tree::tree(&ast)),
+        Format::Dot => print!("{}", parser::tree::tree(&ast).to_dot()),
+        Format::Json => println!("{}", serial::ast_to_json(&ast, &checker::types(&ast).expect("the AST is checked"))),
+        Format::SExpression => print!("{}", serial::ast_to_sexpr(&ast, &checker::types(&ast).expect("the AST is checked"))),
+    }
+}
+
+/// Print an Mpl program, or an AST read from JSON, as source in the standard layout.
+pub fn fmt(source: String, options: &Options) {
+    match read_ast(&source, options) {
+        Ok(ast) => print!("{}", parser::printer::print(&ast)),
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Print the tokens of an Mpl program as S-expressions, one per line, or as JSON.
+pub fn tokens(source: String, options: &Options) {
+    let tokens = match scanner::scan(&source, options.bigint) {
         Ok(tokens) => tokens,
         Err(e) => { println!("Scanning failed: {}", e); return; },
     };
+    match options.format {
+        Format::Text | Format::SExpression => print!("{}", serial::tokens_to_sexpr(&tokens)),
+        Format::Json => println!("{}", serial::tokens_to_json(&tokens)),
+        Format::Dot => println!("The tokens cannot be shown in DOT"),
+    }
+}
 
-    let ast = match parser::parse(tokens) {
+/// Run an Mpl program under the command-line debugger.
+pub fn debug(source: String) {
+    with_stack(interpreter::DEFAULT_MAX_DEPTH, || debug_program(source));
+}
+
+fn debug_program(source: String) {
+    let ast = match front_end(&source, false) {
         Ok(ast) => ast,
-        Err(msg) => { println!("Parsing failed: {}", msg); return; },
+        Err(e) => { println!("{}", e); return; },
     };
 
-    let result = interpreter::evaluate(ast);
-    match result {
-        Ok(_) => (),
+    let mut interpreter = interpreter::Interpreter::new();
+    interpreter.add_monitor(Box::new(interpreter::Debugger::new(&source)));
+    match interpreter.run(ast) {
+        Ok(_) => println!("\nProgram finished"),
         Err(e) => println!("Runtime error: {}", e),
     }
 }
+
+/// Run the interpreter in a thread with enough stack for calls nested up to the depth.
+fn with_stack<F: FnOnce() + Send>(max_depth: usize, f: F) {
+    let stack_size = max_depth.saturating_add(1).saturating_mul(STACK_PER_CALL);
+    thread::scope(|scope| {
+        if let Err(e) = thread::Builder::new().stack_size(stack_size).spawn_scoped(scope, f) {
+            println!("Unable to start the interpreter: {}", e);
+        }
+    });
+}
+
+/// Set up recording or replaying of the input if the options ask for it.
+fn set_input(interpreter: &mut interpreter::Interpreter, options: &Options) -> Result<(), String> {
+    match (options.record.as_ref(), options.replay.as_ref()) {
+        (Some(_), Some(_)) => Err("Cannot both record and replay input".to_string()),
+        (Some(path), None) => {
+            interpreter.set_input(Box::new(interpreter::Recorder::create(path)?));
+            Ok(())
+        },
+        (None, Some(path)) => {
+            interpreter.set_input(Box::new(interpreter::Replayer::open(path)?));
+            Ok(())
+        },
+        (None, None) => Ok(()),
+    }
+}
+
+/// Scan, parse and type check the source into an AST, with the values of the constants
+/// folded into it. The error tells which phase failed.
+/// Integer literals may be larger than 32 bits if `bigint` is set.
+fn front_end(source: &str, bigint: bool) -> Result<Ast, String> {
+    let mut ast = checked_ast(source, bigint)?;
+    fold_constants(&mut ast, bigint)?;
+    Ok(ast)
+}
+
+/// Fold the values of the constants into a checked AST.
+fn fold_constants(ast: &mut Ast, bigint: bool) -> Result<(), String> {
+    constants::fold(ast, bigint).map_err(|msg| format!("Evaluating constants failed:\n{}", msg))
+}
+
+/// The checked AST of the program, read from the source or from JSON as the options say.
+fn read_ast(source: &str, options: &Options) -> Result<Ast, String> {
+    if !options.from_json {
+        return checked_ast(source, options.bigint);
+    }
+    let ast = serial::ast_from_json(source, options.bigint).map_err(|e| format!("Reading JSON failed: {}", e))?;
+    match checker::check(&ast) {
+        Ok(()) => Ok(ast),
+        Err(msg) => Err(format!("Type checking failed:\n{}", msg)),
+    }
+}
+
+/// Scan, parse and type check the source into an AST, as it is written.
+fn checked_ast(source: &str, bigint: bool) -> Result<Ast, String> {
+    let tokens = match scanner::scan(source, bigint) {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(format!("Scanning failed: {}", e)),
+    };
+
+    let ast = match parser::parse(tokens) {
+        Ok(ast) => ast,
+        Err(msg) => return Err(format!("Parsing failed: {}", msg)),
+    };
+
+    match checker::check(&ast) {
+        Ok(()) => Ok(ast),
+        Err(msg) => Err(format!("Type checking failed:\n{}", msg)),
+    }
+}
@@ -0,0 +1,245 @@
+//! Optimisation of a checked AST, after the constants have been folded. Sub-expressions of
+//! literals are computed once at compile time, boolean identities are simplified, empty
+//! statements are removed, and for loops over ranges known to be empty are eliminated.
+//! The optimised program behaves like the original one: an expression whose evaluation fails,
+//! such as a division by zero, is left to fail at runtime.
+
+use super::constants::literal;
+use super::interpreter::{Interpreter, MplValue};
+use super::parser::ast::*;
+
+/// Optimise a checked AST in place. Integers are unbounded if `bigint` is set.
+pub fn optimize(ast: &mut Ast, bigint: bool) {
+    let mut evaluator = Interpreter::new();
+    evaluator.set_bigint(bigint);
+    let mut optimizer = Optimizer { evaluator };
+    optimizer.visit_ast(ast);
+}
+
+/// The optimizer computes expressions with an interpreter that runs no program.
+struct Optimizer {
+    evaluator: Interpreter,
+}
+
+impl Optimizer {
+    /// Whether the for loop over the range is known to run no iterations. A step that is not
+    /// known to be positive keeps the loop, which fails at runtime.
+    fn is_empty_range(&mut self, begin: &Expression, end: &Expression, descending: bool, step: Option<&Expression>) -> bool {
+        let (begin, end) = match (begin, end) {
+            (Expression::Simple(begin), Expression::Simple(end)) if is_literal(begin) && is_literal(end)
+                => (begin.clone(), end.clone()),
+            _ => return false,
+        };
+        if let Some(step) = step {
+            let positive = match *step {
+                Expression::Simple(ref step) if is_literal(step) => Expression::Binary {
+                    left: step.clone(),
+                    operator: Operator::Greater,
+                    right: Operand::Int(0),
+                },
+                _ => return false,
+            };
+            if self.evaluator.evaluate_expression(&positive) != Ok(MplValue::Bool(true)) {
+                return false;
+            }
+        }
+        let operator = if descending { Operator::Less } else { Operator::Greater };
+        let empty = Expression::Binary { left: begin, operator, right: end };
+        self.evaluator.evaluate_expression(&empty) == Ok(MplValue::Bool(true))
+    }
+}
+
+impl VisitorMut for Optimizer {
+    fn visit_ast(&mut self, ast: &mut Ast) {
+        remove_empty(&mut ast.statements);
+        walk_ast_mut(self, ast);
+    }
+
+    fn visit_statement(&mut self, statement: &mut Located<Statement>) {
+        match statement.node {
+            // The diagnostics of a failed assertion show the operands as written.
+            Statement::Assert(_) | Statement::Constant { .. } => return,
+            Statement::For { ref mut statements, .. } | Statement::While { ref mut statements, .. }
+                => remove_empty(statements),
+            Statement::If { ref mut statements, ref mut else_statements, .. } => {
+                remove_empty(statements);
+                remove_empty(else_statements);
+            },
+            Statement::Function(ref mut function) => remove_empty(&mut function.statements),
+            _ => (),
+        }
+        walk_statement_mut(self, statement);
+        let replacement = match statement.node {
+            Statement::For { ref identifier, ref begin, ref end, descending, ref step, .. }
+                if self.is_empty_range(begin, end, descending, step.as_ref()) => {
+                // The loop only leaves the control variable at the beginning of the range.
+                Some(Statement::Assignment { identifier: identifier.clone(), index: None, expression: begin.clone() })
+            },
+            _ => None,
+        };
+        if let Some(replacement) = replacement {
+            statement.node = replacement;
+        }
+    }
+
+    /// Fold the expression after its operands.
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr);
+        let known = match *expr {
+            Expression::Simple(_) => false,
+            Expression::Unary { ref operand, .. } => is_literal(operand),
+            Expression::Binary { ref left, ref right, .. } => is_literal(left) && is_literal(right),
+        };
+        if known {
+            // An error is left to happen at runtime.
+            if let Ok(value) = self.evaluator.evaluate_expression(expr) {
+                *expr = Expression::Simple(literal(value));
+                return;
+            }
+        }
+        if let Some(simplified) = simplify(expr) {
+            *expr = simplified;
+        }
+    }
+
+    /// Drop the parentheses around an operand that was folded from an expression.
+    fn visit_operand(&mut self, operand: &mut Operand) {
+        walk_operand_mut(self, operand);
+        let simplified = match *operand {
+            Operand::Expr(ref expr) => match **expr {
+                Expression::Simple(ref inner) => Some(inner.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(simplified) = simplified {
+            *operand = simplified;
+        }
+    }
+}
+
+fn remove_empty(statements: &mut Vec<Located<Statement>>) {
+    statements.retain(|statement| statement.node != Statement::Empty);
+}
+
+/// Simplify the boolean identities of the expression. An operand that is dropped must not
+/// have an effect, except the right operand of a short-circuiting & or |, which is not
+/// evaluated anyway.
+fn simplify(expr: &Expression) -> Option<Expression> {
+    match *expr {
+        Expression::Unary { operator: Operator::Not, operand: Operand::Expr(ref inner) } => match **inner {
+            Expression::Unary { operator: Operator::Not, ref operand } => Some(Expression::Simple(operand.clone())),
+            _ => None,
+        },
+        Expression::Binary { ref left, operator, ref right } => match (operator, left, right) {
+            (Operator::And, &Operand::Bool(false), _) => Some(Expression::Simple(Operand::Bool(false))),
+            (Operator::Or, &Operand::Bool(true), _) => Some(Expression::Simple(Operand::Bool(true))),
+            (Operator::And, &Operand::Bool(true), other) | (Operator::And, other, &Operand::Bool(true))
+            | (Operator::Or, &Operand::Bool(false), other) | (Operator::Or, other, &Operand::Bool(false))
+                => Some(Expression::Simple(other.clone())),
+            (Operator::And, other, &Operand::Bool(false)) if is_pure(other) => Some(Expression::Simple(Operand::Bool(false))),
+            (Operator::Or, other, &Operand::Bool(true)) if is_pure(other) => Some(Expression::Simple(Operand::Bool(true))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_literal(operand: &Operand) -> bool {
+    matches!(*operand, Operand::Int(_) | Operand::BigInt(_) | Operand::Real(_) | Operand::String(_) | Operand::Bool(_))
+}
+
+/// Whether evaluating the operand can neither fail nor have an effect. Variables are always
+/// initialized when they are declared.
+fn is_pure(operand: &Operand) -> bool {
+    is_literal(operand) || matches!(*operand, Operand::Identifier(_) | Operand::Size(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{checker, constants, parser, scanner};
+
+    fn optimize_source(source: &str) -> Vec<Statement> {
+        let mut ast = parser::parse(scanner::scan(source, false).unwrap()).unwrap();
+        checker::check(&ast).unwrap();
+        constants::fold(&mut ast, false).unwrap();
+        optimize(&mut ast, false);
+        ast.statements.into_iter().map(|statement| statement.node).collect()
+    }
+
+    fn print(expr: Expression) -> Statement {
+        Statement::Print(expr)
+    }
+
+    fn identifier(identifier: &str) -> Operand {
+        Operand::Identifier(identifier.to_string())
+    }
+
+    #[test]
+    fn constant_expressions_are_folded() {
+        let source = "var x : int := 4 + (6 * 2);\nconst n : int := 3;\nprint x * (n - 1);\nprint \"a\" + \"b\";\n";
+        assert_eq!(optimize_source(source), vec![
+            Statement::Declaration { identifier: "x".to_string(), mpl_type: MplType::Int, value: Some(Expression::Simple(Operand::Int(16))) },
+            Statement::Constant { identifier: "n".to_string(), mpl_type: MplType::Int, value: Expression::Simple(Operand::Int(3)) },
+            print(Expression::Binary { left: identifier("x"), operator: Operator::Times, right: Operand::Int(2) }),
+            print(Expression::Simple(Operand::String("ab".to_string()))),
+        ]);
+    }
+
+    #[test]
+    fn errors_are_left_to_runtime() {
+        let source = "print 1 + (1 / 0);\nprint 65536 * 65536;\n";
+        assert_eq!(optimize_source(source), vec![
+            print(Expression::Binary {
+                left: Operand::Int(1),
+                operator: Operator::Plus,
+                right: Operand::Expr(Box::new(Expression::Binary {
+                    left: Operand::Int(1),
+                    operator: Operator::Divide,
+                    right: Operand::Int(0),
+                })),
+            }),
+            print(Expression::Binary { left: Operand::Int(65536), operator: Operator::Times, right: Operand::Int(65536) }),
+        ]);
+    }
+
+    #[test]
+    fn boolean_identities() {
+        let source = "var b : bool;\nfunction f() : bool do return true; end function;\n\
+                      print !(!b);\nprint b & true;\nprint false | f();\nprint false & f();\n\
+                      print b | true;\nprint f() | true;\n";
+        let statements = optimize_source(source);
+        let call = Operand::Call { identifier: "f".to_string(), arguments: vec![] };
+        assert_eq!(statements[2..], [
+            print(Expression::Simple(identifier("b"))),
+            print(Expression::Simple(identifier("b"))),
+            print(Expression::Simple(call.clone())),
+            print(Expression::Simple(Operand::Bool(false))),
+            print(Expression::Simple(Operand::Bool(true))),
+            // The call may have an effect, so it is kept.
+            print(Expression::Binary { left: call, operator: Operator::Or, right: Operand::Bool(true) }),
+        ]);
+    }
+
+    #[test]
+    fn empty_statements_and_loops_are_removed() {
+        let source = "var i : int;;\nfor i in 3..(1 + 1) do print i; end for;\nfor i in 1 downto 2 do ; end for;\n\
+                      for i in 3..1 step 0 do end for;\nfor i in 1..1 do ;; end for;\n";
+        let statements = optimize_source(source);
+        assert_eq!(statements.len(), 5);
+        let assign = |value| Statement::Assignment {
+            identifier: "i".to_string(),
+            index: None,
+            expression: Expression::Simple(Operand::Int(value)),
+        };
+        assert_eq!(statements[1], assign(3));
+        assert_eq!(statements[2], assign(1));
+        // A step that is not positive fails at runtime.
+        assert!(matches!(statements[3], Statement::For { .. }));
+        match statements[4] {
+            Statement::For { ref statements, .. } => assert!(statements.is_empty()),
+            ref statement => panic!("expected a for loop, got {:?}", statement),
+        }
+    }
+}
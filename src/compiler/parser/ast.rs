@@ -1,68 +1,491 @@
 use std::fmt;
 
+pub use super::super::scanner::token::{Operator, Position};
+pub use super::super::bigint::BigInt;
+
 /// Abstract Syntax Tree for Mini PL. There is no Node struct/enum, but the nodes are different
 /// enums depending on the type of node. For example the nodes can be Operand enums or Expression
 /// enums.
 pub struct Ast {
-    pub statements: Vec<Statement>,
+    pub statements: Vec<Located<Statement>>,
+}
+
+/// A node of the AST together with the positions of its first and last token in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Located<T> {
+    pub node: T,
+    pub position: Position,
+    pub end: Position,
 }
 
 /// The statement enum. One of the AST node types.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Statement {
     Declaration { identifier: String, mpl_type: MplType, value: Option<Expression> },
-    Assignment { identifier: String, expression: Expression },
-    For { identifier: String, begin: Expression, end: Expression, statements: Vec<Statement> },
-    Read(String), // Identifier
+    Constant { identifier: String, mpl_type: MplType, value: Expression }, // the value is known at compile time
+    Assignment { identifier: String, index: Option<Expression>, expression: Expression }, // index of an array element
+    For {
+        identifier: String,
+        begin: Expression,
+        end: Expression,
+        descending: bool, // counts down to the end with downto
+        step: Option<Expression>, // the step is 1 if not given
+        statements: Vec<Located<Statement>>,
+    },
+    If { condition: Expression, statements: Vec<Located<Statement>>, else_statements: Vec<Located<Statement>> },
+    While { condition: Expression, statements: Vec<Located<Statement>> },
+    Break, // leave the innermost loop
+    Continue, // begin the next iteration of the innermost loop
+    Function(Function), // declaration of a procedure or a function
+    Call { identifier: String, arguments: Vec<Expression> }, // call of a procedure or a function
+    Return(Option<Expression>), // the value is None in a procedure
+    Read { identifier: String, index: Option<Expression> }, // index of an array element
     Print(Expression),
     Assert(Expression),
     Empty, // TODO remove!
 }
 
+/// A procedure or function declaration. A procedure has no return type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub identifier: String,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<MplType>,
+    pub statements: Vec<Located<Statement>>,
+}
+
+/// A parameter of a procedure or function. Arguments are passed by value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub identifier: String,
+    pub mpl_type: MplType,
+}
+
 /// The statement enum. One of the AST node types.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
     Simple(Operand),
-    Binary { left: Operand, operator: char, right: Operand },
-    Unary { operator: char, operand: Operand },
+    Binary { left: Operand, operator: Operator, right: Operand },
+    Unary { operator: Operator, operand: Operand },
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expression::Simple(ref operand) => write!(f, "{}", operand),
+            Expression::Binary { ref left, operator, ref right } => write!(f, "{} {} {}", left, operator, right),
+            Expression::Unary { operator, ref operand } => write!(f, "{}{}", operator, operand),
+        }
+    }
 }
 
 /// An operand of an expression. One of the AST Node types.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Operand {
     Int(i32),
+    BigInt(BigInt), // an integer literal too large for Int, only with --bigint
+    Real(f64),
     String(String),
+    Bool(bool),
     Identifier(String),
+    Index { identifier: String, index: Box<Expression> }, // element of an array
+    Size(String), // size of an array
+    Call { identifier: String, arguments: Vec<Expression> },
     Expr(Box<Expression>),
 }
 
 impl fmt::Display for Operand {
+    /// The operand as source that parses back to it. Literals that cannot be written in the
+    /// source, such as negative numbers made by folding constants, are written as expressions
+    /// with their value.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &Operand::Int(ref i) => write!(f, "{}", i),
-            &Operand::String(ref s) => write!(f, "{}", s),
-            &Operand::Identifier(ref id) => write!(f, "{}", id),
-            &Operand::Expr(_) => write!(f, "(...)"),
+        match *self {
+            Operand::Int(i) if i < 0 => match i.checked_neg() {
+                Some(magnitude) => write!(f, "(0 - {})", magnitude),
+                None => write!(f, "((0 - {}) - 1)", i32::MAX),
+            },
+            Operand::Int(i) => write!(f, "{}", i),
+            Operand::BigInt(ref i) => {
+                let digits = i.to_string();
+                match digits.strip_prefix('-') {
+                    Some(magnitude) => write!(f, "(0 - {})", magnitude),
+                    None => write!(f, "{}", digits),
+                }
+            },
+            Operand::Real(r) if r.is_nan() => write!(f, "((1e308 * 10.0) - (1e308 * 10.0))"),
+            Operand::Real(r) if r == f64::INFINITY => write!(f, "(1e308 * 10.0)"),
+            Operand::Real(r) if r == f64::NEG_INFINITY => write!(f, "(0.0 - (1e308 * 10.0))"),
+            Operand::Real(r) if r < 0.0 => write!(f, "(0.0 - {})", format_real(-r)),
+            Operand::Real(r) => write!(f, "{}", format_real(r.abs())), // no sign, also for -0.0
+            Operand::String(ref s) => write!(f, "{}", quote(s)),
+            Operand::Bool(b) => write!(f, "{}", b),
+            Operand::Identifier(ref id) => write!(f, "{}", id),
+            Operand::Index { ref identifier, ref index } => write!(f, "{}[{}]", identifier, index),
+            Operand::Size(ref identifier) => write!(f, "{}.size", identifier),
+            Operand::Call { ref identifier, ref arguments } => {
+                let arguments: Vec<String> = arguments.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", identifier, arguments.join(", "))
+            },
+            Operand::Expr(ref expr) => write!(f, "({})", expr),
         }
     }
 }
 
+/// Quote a string as a string literal of the source. Quotes, backslashes and newlines are
+/// escaped, and the other characters are written as they are.
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A pass over the AST. Each method visits a node, and by default walks into its children with
+/// the walk function of the node, so a pass only overrides the methods of the nodes it looks
+/// at. An overriding method calls the walk function itself to go on into the children.
+pub trait Visitor {
+    fn visit_ast(&mut self, ast: &Ast) {
+        walk_ast(self, ast);
+    }
+
+    fn visit_statement(&mut self, statement: &Located<Statement>) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    /// Visit the statements of a body: of a loop, of a branch of an if statement, or of a
+    /// procedure or function. The statements of the program are not a block.
+    fn visit_block(&mut self, statements: &[Located<Statement>]) {
+        walk_block(self, statements);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_operand(&mut self, operand: &Operand) {
+        walk_operand(self, operand);
+    }
+}
+
+pub fn walk_ast<V: Visitor + ?Sized>(visitor: &mut V, ast: &Ast) {
+    for statement in &ast.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+/// Visit the expressions and the nested statements of the statement, in the order of the source.
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Located<Statement>) {
+    match statement.node {
+        Statement::Declaration { value: Some(ref value), .. } | Statement::Constant { ref value, .. }
+        | Statement::Return(Some(ref value)) | Statement::Print(ref value) | Statement::Assert(ref value)
+            => visitor.visit_expression(value),
+        Statement::Assignment { ref index, ref expression, .. } => {
+            if let Some(ref index) = *index {
+                visitor.visit_expression(index);
+            }
+            visitor.visit_expression(expression);
+        },
+        Statement::Read { index: Some(ref index), .. } => visitor.visit_expression(index),
+        Statement::For { ref begin, ref end, ref step, ref statements, .. } => {
+            visitor.visit_expression(begin);
+            visitor.visit_expression(end);
+            if let Some(ref step) = *step {
+                visitor.visit_expression(step);
+            }
+            visitor.visit_block(statements);
+        },
+        Statement::If { ref condition, ref statements, ref else_statements } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block(statements);
+            visitor.visit_block(else_statements);
+        },
+        Statement::While { ref condition, ref statements } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block(statements);
+        },
+        Statement::Function(ref function) => visitor.visit_function(function),
+        Statement::Call { ref arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        },
+        Statement::Declaration { value: None, .. } | Statement::Read { index: None, .. } | Statement::Return(None)
+        | Statement::Break | Statement::Continue | Statement::Empty => (),
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, statements: &[Located<Statement>]) {
+    for statement in statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &Function) {
+    visitor.visit_block(&function.statements);
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match *expression {
+        Expression::Simple(ref operand) | Expression::Unary { ref operand, .. } => visitor.visit_operand(operand),
+        Expression::Binary { ref left, ref right, .. } => {
+            visitor.visit_operand(left);
+            visitor.visit_operand(right);
+        },
+    }
+}
+
+/// Visit the expressions in the operand: the index of an element, the arguments of a call, or
+/// the expression in parentheses.
+pub fn walk_operand<V: Visitor + ?Sized>(visitor: &mut V, operand: &Operand) {
+    match *operand {
+        Operand::Index { ref index, .. } => visitor.visit_expression(index),
+        Operand::Call { ref arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        },
+        Operand::Expr(ref expr) => visitor.visit_expression(expr),
+        Operand::Int(_) | Operand::BigInt(_) | Operand::Real(_) | Operand::String(_) | Operand::Bool(_)
+        | Operand::Identifier(_) | Operand::Size(_) => (),
+    }
+}
+
+/// A pass that changes the AST in place. The methods and walk functions are those of Visitor,
+/// over mutable nodes.
+pub trait VisitorMut {
+    fn visit_ast(&mut self, ast: &mut Ast) {
+        walk_ast_mut(self, ast);
+    }
+
+    fn visit_statement(&mut self, statement: &mut Located<Statement>) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_function(&mut self, function: &mut Function) {
+        walk_function_mut(self, function);
+    }
+
+    fn visit_block(&mut self, statements: &mut [Located<Statement>]) {
+        walk_block_mut(self, statements);
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+
+    fn visit_operand(&mut self, operand: &mut Operand) {
+        walk_operand_mut(self, operand);
+    }
+}
+
+pub fn walk_ast_mut<V: VisitorMut + ?Sized>(visitor: &mut V, ast: &mut Ast) {
+    for statement in &mut ast.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Located<Statement>) {
+    match statement.node {
+        Statement::Declaration { value: Some(ref mut value), .. } | Statement::Constant { ref mut value, .. }
+        | Statement::Return(Some(ref mut value)) | Statement::Print(ref mut value) | Statement::Assert(ref mut value)
+            => visitor.visit_expression(value),
+        Statement::Assignment { ref mut index, ref mut expression, .. } => {
+            if let Some(ref mut index) = *index {
+                visitor.visit_expression(index);
+            }
+            visitor.visit_expression(expression);
+        },
+        Statement::Read { index: Some(ref mut index), .. } => visitor.visit_expression(index),
+        Statement::For { ref mut begin, ref mut end, ref mut step, ref mut statements, .. } => {
+            visitor.visit_expression(begin);
+            visitor.visit_expression(end);
+            if let Some(ref mut step) = *step {
+                visitor.visit_expression(step);
+            }
+            visitor.visit_block(statements);
+        },
+        Statement::If { ref mut condition, ref mut statements, ref mut else_statements } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block(statements);
+            visitor.visit_block(else_statements);
+        },
+        Statement::While { ref mut condition, ref mut statements } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block(statements);
+        },
+        Statement::Function(ref mut function) => visitor.visit_function(function),
+        Statement::Call { ref mut arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        },
+        Statement::Declaration { value: None, .. } | Statement::Read { index: None, .. } | Statement::Return(None)
+        | Statement::Break | Statement::Continue | Statement::Empty => (),
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statements: &mut [Located<Statement>]) {
+    for statement in statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, function: &mut Function) {
+    visitor.visit_block(&mut function.statements);
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match *expression {
+        Expression::Simple(ref mut operand) | Expression::Unary { ref mut operand, .. } => visitor.visit_operand(operand),
+        Expression::Binary { ref mut left, ref mut right, .. } => {
+            visitor.visit_operand(left);
+            visitor.visit_operand(right);
+        },
+    }
+}
+
+pub fn walk_operand_mut<V: VisitorMut + ?Sized>(visitor: &mut V, operand: &mut Operand) {
+    match *operand {
+        Operand::Index { ref mut index, .. } => visitor.visit_expression(index),
+        Operand::Call { ref mut arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        },
+        Operand::Expr(ref mut expr) => visitor.visit_expression(expr),
+        Operand::Int(_) | Operand::BigInt(_) | Operand::Real(_) | Operand::String(_) | Operand::Bool(_)
+        | Operand::Identifier(_) | Operand::Size(_) => (),
+    }
+}
+
 /// MplType represents a type in mpl. This enum is used both by the AST to mark types, and the
 /// interpreter to reason about types more cleanly.
 #[derive(Clone, Debug, PartialEq)]
 pub enum MplType {
     Int,
+    Real,
     String,
     Bool,
+    Array { element: Box<MplType>, size: usize }, // the element type is not an array
 }
 
 impl fmt::Display for MplType {
     // Implement the display trait for printing types nicely in errormessages
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &MplType::Int => write!(f, "Integer"),
-            &MplType::String => write!(f, "String"),
-            &MplType::Bool => write!(f, "Boolean"),
+        match *self {
+            MplType::Int => write!(f, "Integer"),
+            MplType::Real => write!(f, "Real"),
+            MplType::String => write!(f, "String"),
+            MplType::Bool => write!(f, "Boolean"),
+            MplType::Array { ref element, size } => write!(f, "Array [{}] of {}", size, element),
         }
     }
 }
+
+/// Format a real number as it is printed. The digits are the fewest that read back as the same
+/// number, with at least one digit after the decimal point, as in 2.0 and 0.1. Magnitudes below
+/// 1e-5 or from 1e16 up are written with an exponent instead, as in 1e16 and 2.5e-7. The
+/// special values are written NaN, inf and -inf.
+pub fn format_real(r: f64) -> String {
+    let magnitude = r.abs();
+    if !r.is_finite() {
+        r.to_string()
+    } else if magnitude != 0.0 && !(1e-5..1e16).contains(&magnitude) {
+        format!("{:e}", r)
+    } else if r.fract() == 0.0 {
+        format!("{:.1}", r)
+    } else {
+        r.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn real_format() {
+        assert_eq!(format_real(2.0), "2.0");
+        assert_eq!(format_real(-0.1), "-0.1");
+        assert_eq!(format_real(0.0), "0.0");
+        assert_eq!(format_real(1.0 / 3.0), "0.3333333333333333");
+        assert_eq!(format_real(123456789012345.0), "123456789012345.0");
+        assert_eq!(format_real(1e16), "1e16");
+        assert_eq!(format_real(-2.5e-7), "-2.5e-7");
+        assert_eq!(format_real(f64::INFINITY), "inf");
+        assert_eq!(format_real(f64::NAN), "NaN");
+    }
+
+    /// The identifiers read by the expressions, in order.
+    struct Reads(Vec<String>);
+
+    impl Visitor for Reads {
+        fn visit_operand(&mut self, operand: &Operand) {
+            match *operand {
+                Operand::Identifier(ref identifier) | Operand::Size(ref identifier)
+                | Operand::Index { ref identifier, .. } | Operand::Call { ref identifier, .. }
+                    => self.0.push(identifier.clone()),
+                _ => (),
+            }
+            walk_operand(self, operand);
+        }
+    }
+
+    /// The number of blocks, and of the statements in them.
+    struct Blocks(usize, usize);
+
+    impl Visitor for Blocks {
+        fn visit_block(&mut self, statements: &[Located<Statement>]) {
+            self.0 += 1;
+            self.1 += statements.len();
+            walk_block(self, statements);
+        }
+    }
+
+    /// Renames the variables read by the expressions.
+    struct Rename(&'static str, &'static str);
+
+    impl VisitorMut for Rename {
+        fn visit_operand(&mut self, operand: &mut Operand) {
+            if let Operand::Identifier(ref mut identifier) = *operand {
+                if identifier == self.0 {
+                    *identifier = self.1.to_string();
+                }
+            }
+            walk_operand_mut(self, operand);
+        }
+    }
+
+    fn parse_source(source: &str) -> Ast {
+        super::super::parse(super::super::super::scanner::scan(source, false).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn visitors() {
+        let source = "var n : int := a.size; for i in 1..n step s do if f(i, x[i]) then print (i * 2) + j; \
+                      end if; end for; function f(k : int, y : int) : bool do return (0 - k) < y; end function; \
+                      read x[n]; assert (!(n = m));";
+        let mut ast = parse_source(source);
+        let mut reads = Reads(Vec::new());
+        reads.visit_ast(&ast);
+        assert_eq!(reads.0, vec!["a", "n", "s", "f", "i", "x", "i", "i", "j", "k", "y", "n", "n", "m"]);
+        let mut blocks = Blocks(0, 0);
+        blocks.visit_ast(&ast);
+        assert_eq!((blocks.0, blocks.1), (4, 3)); // for, then, else and function
+
+        Rename("i", "index").visit_ast(&mut ast);
+        let printed = super::super::printer::print(&ast);
+        assert!(printed.contains("for i in 1..n step s do\n    if f(index, x[index]) then\n        print (index * 2) + j;\n"));
+    }
+}
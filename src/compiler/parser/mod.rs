@@ -1,8 +1,16 @@
 pub mod ast;
+pub mod printer;
+pub mod tree;
+#[allow(clippy::module_inception)]
 mod parser;
 use super::scanner::token::*;
 
 /// Parse a vector of tokens into an AST. Returns possible parsing errors.
-pub fn parse(input: Vec<Token>) -> Result<ast::Ast, String> {
-    parser::Parser::new(input).into_ast()
+pub fn parse(input: Vec<(Token, Position)>) -> Result<ast::Ast, String> {
+    parser::Parser::with_positions(input).into_ast()
+}
+
+/// Parse a vector of tokens into a single expression. Returns possible parsing errors.
+pub fn parse_expression(input: Vec<(Token, Position)>) -> Result<ast::Expression, String> {
+    parser::Parser::with_positions(input).into_expression()
 }
@@ -6,18 +6,26 @@ use super::super::scanner::token::*;
 use std::vec::IntoIter;
 use std::mem;
 
-pub struct Parser<I: Iterator<Item = Token>> {
-    buffer: Option<Token>,
+pub struct Parser<I: Iterator<Item = (Token, Position)>> {
+    buffer: Option<(Token, Position)>,
     iterator: I,
+    position: Position, // position of the token most recently returned by next()
 }
 
-impl Parser<IntoIter<Token>> {
-    pub fn new(tokens: Vec<Token>) -> Parser<IntoIter<Token>> {
-        Parser { buffer: None, iterator: tokens.into_iter() }
+impl Parser<IntoIter<(Token, Position)>> {
+    /// Create a parser for tokens without source positions.
+    #[cfg(test)]
+    pub fn new(tokens: Vec<Token>) -> Parser<IntoIter<(Token, Position)>> {
+        Parser::with_positions(tokens.into_iter().map(|token| (token, Position::default())).collect())
+    }
+
+    /// Create a parser for tokens paired with their positions, as returned by the scanner.
+    pub fn with_positions(tokens: Vec<(Token, Position)>) -> Parser<IntoIter<(Token, Position)>> {
+        Parser { buffer: None, iterator: tokens.into_iter(), position: Position::default() }
     }
 }
 
-impl<I: Iterator<Item = Token>> Parser<I> {
+impl<I: Iterator<Item = (Token, Position)>> Parser<I> {
 
     fn assume_next(&mut self, expected: Token) -> Result<(), String> {
         match self.next() {
@@ -35,13 +43,22 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     /// or the iterator, whichever is appropriate. Use this instead of self.expect_next() when
     /// not requiring another token.
     fn next(&mut self) -> Option<Token> {
-        if self.buffer == None {
+        let next = if self.buffer.is_none() {
             self.iterator.next()
         } else {
             let mut next = None;
             mem::swap(&mut self.buffer, &mut next);
             next
-        }
+        };
+        next.map(|(token, position)| {
+            self.position = position;
+            token
+        })
+    }
+
+    /// Put a token back, so that the next call to self.next() returns it again.
+    fn push_back(&mut self, token: Token) {
+        self.buffer = Some((token, self.position));
     }
 
     /// Returns next token, or an error if there is no next token. Use this instead of self.next()
@@ -56,13 +73,27 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     // DIFFERENT STATEMENTS
 
     fn parse_read(&mut self) -> Result<Statement, String> {
-        // "read" <identifier> read identifier and return
-        let stmt_res = match self.next() {
-            Some(Token::Identifier(name)) => Ok(Statement::Read(name)),
+        // "read" <identifier> [ "[" <expr> "]" ] read identifier and return
+        let identifier = match self.next() {
+            Some(Token::Identifier(name)) => Ok(name),
             Some(token) => Err(format!("Unexpected token {:?}", token)),
-            None => Err(format!("Reached end while parsing")),
-        };
-        stmt_res.and_then(|statement| self.assume_end().and(Ok(statement)))
+            None => Err("Reached end while parsing".to_string()),
+        }?;
+        let index = self.parse_index()?;
+        self.assume_end().and(Ok(Statement::Read { identifier, index }))
+    }
+
+    /// Parse the index of an array element, if the next token opens one.
+    fn parse_index(&mut self) -> Result<Option<Expression>, String> {
+        match self.next() {
+            Some(Token::OpenBracket) => self.parse_expression()
+                .and_then(|expr| self.assume_next(Token::CloseBracket).and(Ok(Some(expr)))),
+            Some(token) => {
+                self.push_back(token);
+                Ok(None)
+            },
+            None => Ok(None),
+        }
     }
 
     fn parse_assert(&mut self) -> Result<Statement, String> {
@@ -75,15 +106,16 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     }
 
     fn parse_assignment(&mut self, identifier: String) -> Result<Statement, String> {
+        let index = self.parse_index()?;
         self.assume_next(Token::Assignment)
             .and(self.parse_expression())
             .and_then(|expression| self.assume_end()
-                .and(Ok(Statement::Assignment { identifier, expression }))
+                .and(Ok(Statement::Assignment { identifier, index, expression }))
             )
     }
 
     fn parse_for(&mut self) -> Result<Statement, String> {
-        // for <iden> in <expr> .. <expr> do <stmts> end for
+        // for <iden> in <expr> (.. | downto) <expr> [step <expr>] do <stmts> end for
         let res_identifier = match self.next() {
             Some(Token::Identifier(value)) => Ok(value),
             Some(wrong) => Err(format!("Bad token {:?}", wrong)),
@@ -93,30 +125,151 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         );
 
         let identifier = res_identifier?;
-        let begin = self.parse_expression().and_then(
-            |expr| self.assume_next(Token::Range).and(Ok(expr))
+        let begin = self.parse_expression()?;
+        let descending = match self.expect_next()? {
+            Token::Range => false,
+            Token::Reserved(Keyword::Downto) => true,
+            wrong => return Err(format!("Syntax error: Expected Range or downto, got {:?}", wrong)),
+        };
+        let end = self.parse_expression()?;
+        let step = match self.expect_next()? {
+            Token::Reserved(Keyword::Step) => Some(self.parse_expression()?),
+            token => { self.push_back(token); None },
+        };
+        self.assume_next(Token::Reserved(Keyword::Do))?;
+
+        let (statements, _) = self.parse_block(&[Keyword::End])?;
+
+        self.assume_next(Token::Reserved(Keyword::For))
+            .and(self.assume_end())?;
+        Ok(Statement::For { identifier, begin, end, descending, step, statements })
+    }
+
+    fn parse_if(&mut self) -> Result<Statement, String> {
+        // if <expr> then <stmts> [else <stmts>] end if
+        let condition = self.parse_expression().and_then(
+            |expr| self.assume_next(Token::Reserved(Keyword::Then)).and(Ok(expr))
         )?;
-        let end = self.parse_expression().and_then(
+
+        let (statements, terminator) = self.parse_block(&[Keyword::Else, Keyword::End])?;
+        let else_statements = match terminator {
+            Keyword::Else => self.parse_block(&[Keyword::End])?.0,
+            _ => Vec::new(),
+        };
+
+        self.assume_next(Token::Reserved(Keyword::If))
+            .and(self.assume_end())?;
+        Ok(Statement::If { condition, statements, else_statements })
+    }
+
+    fn parse_while(&mut self) -> Result<Statement, String> {
+        // while <expr> do <stmts> end while
+        let condition = self.parse_expression().and_then(
             |expr| self.assume_next(Token::Reserved(Keyword::Do)).and(Ok(expr))
         )?;
 
+        let (statements, _) = self.parse_block(&[Keyword::End])?;
+
+        self.assume_next(Token::Reserved(Keyword::While))
+            .and(self.assume_end())?;
+        Ok(Statement::While { condition, statements })
+    }
+
+    fn parse_function(&mut self, keyword: Keyword) -> Result<Statement, String> {
+        // procedure <iden> ( <params> ) do <stmts> end procedure
+        // function <iden> ( <params> ) : <type> do <stmts> end function
+        let identifier = match self.expect_next()? {
+            Token::Identifier(value) => Ok(value),
+            token => Err(format!("Wrong token {:?}", token)),
+        }?;
+        self.assume_next(Token::OpenParen)?;
+        let parameters = self.parse_parameters()?;
+
+        let return_type = match keyword {
+            Keyword::Function => {
+                self.assume_next(Token::TypeDecl)?;
+                Some(self.parse_type()?)
+            },
+            _ => None,
+        };
+        self.assume_next(Token::Reserved(Keyword::Do))?;
+
+        let (statements, _) = self.parse_block(&[Keyword::End])?;
 
-        let mut stmt_results = Vec::new();
+        self.assume_next(Token::Reserved(keyword))
+            .and(self.assume_end())?;
+        Ok(Statement::Function(Function { identifier, parameters, return_type, statements }))
+    }
 
+    /// Parse the parameter list of a function, after the opening parenthesis.
+    /// # Grammar rule
+    /// params := <empty>
+    /// params := <iden> : <type> { , <iden> : <type> }
+    fn parse_parameters(&mut self) -> Result<Vec<Parameter>, String> {
+        let mut parameters = Vec::new();
+        let mut token = self.expect_next()?;
+        if token == Token::CloseParen {
+            return Ok(parameters);
+        }
         loop {
-            match self.next() {
-                Some(Token::Reserved(Keyword::End)) => break,
-                Some(token) => stmt_results.push(self.parse_statement(token)),
-                None => return Err("Reached end while parsing".to_string()),
+            let identifier = match token {
+                Token::Identifier(value) => Ok(value),
+                token => Err(format!("Wrong token {:?}", token)),
+            }?;
+            self.assume_next(Token::TypeDecl)?;
+            let mpl_type = self.parse_type()?;
+            parameters.push(Parameter { identifier, mpl_type });
+            match self.expect_next()? {
+                Token::Comma => token = self.expect_next()?,
+                Token::CloseParen => return Ok(parameters),
+                token => return Err(format!("Syntax error: Expected Comma or CloseParen, got {:?}", token)),
             }
         }
+    }
 
-        let res_stmts : Result<_, _> = stmt_results.iter().cloned().collect();
-        let statements = res_stmts?;
+    /// Parse the argument list of a call, after the opening parenthesis.
+    /// # Grammar rule
+    /// args := <empty>
+    /// args := <expr> { , <expr> }
+    fn parse_arguments(&mut self) -> Result<Vec<Expression>, String> {
+        let mut arguments = Vec::new();
+        match self.expect_next()? {
+            Token::CloseParen => return Ok(arguments),
+            token => self.push_back(token),
+        }
+        loop {
+            arguments.push(self.parse_expression()?);
+            match self.expect_next()? {
+                Token::Comma => (),
+                Token::CloseParen => return Ok(arguments),
+                token => return Err(format!("Syntax error: Expected Comma or CloseParen, got {:?}", token)),
+            }
+        }
+    }
 
-        self.assume_next(Token::Reserved(Keyword::For))
-            .and(self.assume_end())?;
-        Ok(Statement::For { identifier, begin, end, statements })
+    fn parse_return(&mut self) -> Result<Statement, String> {
+        // return [<expr>]
+        match self.expect_next()? {
+            Token::EndStatement => Ok(Statement::Return(None)),
+            token => {
+                self.push_back(token);
+                self.parse_expression()
+                    .and_then(|expr| self.assume_end().map(|_| Statement::Return(Some(expr))))
+            },
+        }
+    }
+
+    /// Parse statements until one of the terminating keywords. Returns the statements and the
+    /// keyword that ended them.
+    fn parse_block(&mut self, terminators: &[Keyword]) -> Result<(Vec<Located<Statement>>, Keyword), String> {
+        let mut statements = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::Reserved(ref word)) if terminators.contains(word) => return Ok((statements, word.clone())),
+                Some(token) => statements.push(self.parse_located(token)?),
+                None => return Err("Reached end while parsing".to_string()),
+            }
+        }
     }
 
     fn parse_declaration(&mut self) -> Result<Statement, String> {
@@ -126,33 +279,90 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         }?;
         self.assume_next(Token::TypeDecl)?;
 
-        let mpl_type = match self.next() {
+        let mpl_type = self.parse_type()?;
+
+        let value = match self.next() {
+            Some(Token::EndStatement) => Ok(None),
+            Some(Token::Assignment) => self.parse_expression()
+                .and_then(|expr| self.assume_end().and(Ok(Some(expr)))),
+            Some(token) => Err(format!("Bad token {:?}", token)),
+            None => Err("Reached end while parsing".to_string()),
+        }?;
+
+        Ok(Statement::Declaration { identifier, mpl_type, value })
+    }
+
+    fn parse_constant(&mut self) -> Result<Statement, String> {
+        // const <identifier> : <type> := <expr> ; is a declaration that must have a value
+        match self.parse_declaration()? {
+            Statement::Declaration { identifier, mpl_type, value: Some(value) }
+                => Ok(Statement::Constant { identifier, mpl_type, value }),
+            Statement::Declaration { identifier, .. } => Err(format!("Constant {} must have a value", identifier)),
+            _ => unreachable!("parse_declaration returns a declaration"),
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<MplType, String> {
+        match self.next() {
             Some(Token::Reserved(word)) => match word {
                 Keyword::Int => Ok(MplType::Int),
                 Keyword::String => Ok(MplType::String),
                 Keyword::Bool => Ok(MplType::Bool),
+                Keyword::Real => Ok(MplType::Real),
+                Keyword::Array => self.parse_array_type(),
                 _ => Err(format!("Not a type {:?}", word)),
             },
             Some(token) => Err(format!("bad token {:?}", token)),
             None => Err("Reached end while parsing".to_string()),
-        }?;
+        }
+    }
 
-        let value = match self.next() {
-            Some(Token::EndStatement) => Ok(None),
-            Some(Token::Assignment) => self.parse_expression()
-                .and_then(|expr| self.assume_end().and(Ok(Some(expr)))),
-            Some(token) => Err(format!("Bad token {:?}", token)),
-            None => Err(format!("Reached end while parsing")),
+    fn parse_array_type(&mut self) -> Result<MplType, String> {
+        // array "[" <int> "]" of <type>
+        self.assume_next(Token::OpenBracket)?;
+        let size = match self.expect_next()? {
+            Token::Int(size) if size > 0 => Ok(size as usize),
+            Token::Int(size) => Err(format!("Array size must be positive, got {}", size)),
+            token => Err(format!("Syntax error: Expected array size, got {:?}", token)),
         }?;
+        self.assume_next(Token::CloseBracket)
+            .and(self.assume_next(Token::Reserved(Keyword::Of)))?;
+        match self.parse_type()? {
+            MplType::Array { .. } => Err("The elements of an array cannot be arrays".to_string()),
+            element => Ok(MplType::Array { element: Box::new(element), size }),
+        }
+    }
 
-        Ok(Statement::Declaration { identifier, mpl_type, value })
+    /// Parse a statement starting with the given token, and attach the positions of the token
+    /// and of the last token of the statement to it.
+    fn parse_located(&mut self, token: Token) -> Result<Located<Statement>, String> {
+        let position = self.position;
+        let node = self.parse_statement(token)?;
+        Ok(Located { node, position, end: self.position })
     }
 
     fn parse_statement(&mut self, token: Token) -> Result<Statement, String> {
         match token {
             Token::Reserved(Keyword::Var) => self.parse_declaration(), // Declaration
-            Token::Identifier(ident) => self.parse_assignment(ident), // Assignment
+            Token::Reserved(Keyword::Const) => self.parse_constant(),
+            Token::Identifier(ident) => match self.expect_next()? {
+                Token::OpenParen => self.parse_arguments() // Call
+                    .and_then(|arguments| self.assume_end()
+                        .and(Ok(Statement::Call { identifier: ident, arguments }))
+                    ),
+                token => {
+                    self.push_back(token);
+                    self.parse_assignment(ident) // Assignment
+                },
+            },
             Token::Reserved(Keyword::For) => self.parse_for(),
+            Token::Reserved(Keyword::If) => self.parse_if(),
+            Token::Reserved(Keyword::While) => self.parse_while(),
+            Token::Reserved(Keyword::Break) => self.assume_end().map(|_| Statement::Break),
+            Token::Reserved(Keyword::Continue) => self.assume_end().map(|_| Statement::Continue),
+            Token::Reserved(Keyword::Procedure) => self.parse_function(Keyword::Procedure),
+            Token::Reserved(Keyword::Function) => self.parse_function(Keyword::Function),
+            Token::Reserved(Keyword::Return) => self.parse_return(),
             Token::Reserved(Keyword::Read) => self.parse_read(),
             Token::Reserved(Keyword::Print) => self.parse_expression()
                 .and_then(|expr| self.assume_end().map(|_| expr))
@@ -192,7 +402,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 .and_then(|token| self.parse_operand(token))
                 .map(|right| Expression::Binary { left, operator, right }),
             Some(token) => {
-                self.buffer = Some(token);
+                self.push_back(token);
                 Ok(Expression::Simple(left))
             },
             None => Ok(Expression::Simple(left)),
@@ -203,8 +413,27 @@ impl<I: Iterator<Item = Token>> Parser<I> {
         // TODO refactor!
         match token {
             Token::Int(i) => Ok(Operand::Int(i)),
+            Token::BigInt(i) => Ok(Operand::BigInt(i)),
+            Token::Real(r) => Ok(Operand::Real(r)),
             Token::String(s) => Ok(Operand::String(s)),
-            Token::Identifier(id) => Ok(Operand::Identifier(id)),
+            Token::Bool(b) => Ok(Operand::Bool(b)),
+            Token::Identifier(id) => match self.next() {
+                Some(Token::OpenParen) => self.parse_arguments()
+                    .map(|arguments| Operand::Call { identifier: id, arguments }),
+                Some(Token::OpenBracket) => self.parse_expression()
+                    .and_then(|index| self.assume_next(Token::CloseBracket)
+                        .and(Ok(Operand::Index { identifier: id, index: Box::new(index) }))
+                    ),
+                Some(Token::Dot) => match self.expect_next()? {
+                    Token::Identifier(ref attribute) if attribute == "size" => Ok(Operand::Size(id)),
+                    token => Err(format!("Syntax error: Expected size, got {:?}", token)),
+                },
+                Some(token) => {
+                    self.push_back(token);
+                    Ok(Operand::Identifier(id))
+                },
+                None => Ok(Operand::Identifier(id)),
+            },
             Token::OpenParen => self.parse_expression()
                 .and_then(|expr| self.assume_next(Token::CloseParen)
                     .and(Ok(Operand::Expr(Box::new(expr))))
@@ -217,18 +446,24 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     pub fn into_ast(mut self) -> Result<Ast, String> {
         let mut statements = Vec::new();
 
-        loop {
-            match self.next() {
-                Some(token) => match self.parse_statement(token) {
-                    Ok(stmt) => statements.push(stmt),
-                    Err(e) => return Err(e),
-                },
-                None => break,
-            };
-        };
+        while let Some(token) = self.next() {
+            match self.parse_located(token) {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => return Err(e),
+            }
+        }
 
         Ok(Ast { statements })
     }
+
+    /// Parse a single expression that must span all of the remaining tokens.
+    pub fn into_expression(mut self) -> Result<Expression, String> {
+        let expression = self.parse_expression()?;
+        match self.next() {
+            None => Ok(expression),
+            Some(token) => Err(format!("Unexpected token {:?} after expression", token)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -237,10 +472,10 @@ mod tests {
 
     #[test]
     fn success_expression() {
-        let mut parser = Parser::new(vec![Token::Int(1), Token::Operator('+'), Token::Int(2)]);
+        let mut parser = Parser::new(vec![Token::Int(1), Token::Operator(Operator::Plus), Token::Int(2)]);
         let result = parser.parse_expression();
         assert_eq!(result, Ok(Expression::Binary {
-            operator: '+',
+            operator: Operator::Plus,
             left: Operand::Int(1),
             right: Operand::Int(2)})
         );
@@ -248,7 +483,7 @@ mod tests {
 
     #[test]
     fn failure_expression() {
-        let mut parser = Parser::new(vec![Token::Int(1), Token::Operator('+'), Token::EndStatement]);
+        let mut parser = Parser::new(vec![Token::Int(1), Token::Operator(Operator::Plus), Token::EndStatement]);
         let result = parser.parse_expression();
         assert_eq!(result, Err("Bad token EndStatement".to_string()));
     }
@@ -271,6 +506,28 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn success_array_declaration() {
+        let mut parser = Parser::new(vec![
+            // Token::Reserved(Keyword::Var),
+            Token::Identifier("a".to_string()),
+            Token::TypeDecl,
+            Token::Reserved(Keyword::Array),
+            Token::OpenBracket,
+            Token::Int(3),
+            Token::CloseBracket,
+            Token::Reserved(Keyword::Of),
+            Token::Reserved(Keyword::Bool),
+            Token::EndStatement,
+        ]);
+        let result = parser.parse_declaration();
+        assert_eq!(result, Ok(Statement::Declaration {
+            identifier: "a".to_string(),
+            mpl_type: MplType::Array { element: Box::new(MplType::Bool), size: 3 },
+            value: None,
+        }));
+    }
+
     #[test]
     fn failure_declaration() {
         let mut parser = Parser::new(vec![
@@ -284,13 +541,115 @@ mod tests {
         assert_eq!(result, Err("Syntax error: Expected TypeDecl, got Assignment".to_string()))
     }
 
+    #[test]
+    fn success_for_downto_with_step() {
+        let mut parser = Parser::new(vec![
+            // Token::Reserved(Keyword::For),
+            Token::Identifier("i".to_string()),
+            Token::Reserved(Keyword::In),
+            Token::Int(10),
+            Token::Reserved(Keyword::Downto),
+            Token::Int(1),
+            Token::Reserved(Keyword::Step),
+            Token::Int(2),
+            Token::Reserved(Keyword::Do),
+            Token::Reserved(Keyword::End),
+            Token::Reserved(Keyword::For),
+            Token::EndStatement,
+        ]);
+        let result = parser.parse_for();
+        assert_eq!(result, Ok(Statement::For {
+            identifier: "i".to_string(),
+            begin: Expression::Simple(Operand::Int(10)),
+            end: Expression::Simple(Operand::Int(1)),
+            descending: true,
+            step: Some(Expression::Simple(Operand::Int(2))),
+            statements: vec![],
+        }));
+    }
+
+    #[test]
+    fn failure_array_of_arrays() {
+        let mut parser = Parser::new(vec![
+            // Token::Reserved(Keyword::Array),
+            Token::OpenBracket,
+            Token::Int(2),
+            Token::CloseBracket,
+            Token::Reserved(Keyword::Of),
+            Token::Reserved(Keyword::Array),
+            Token::OpenBracket,
+            Token::Int(3),
+            Token::CloseBracket,
+            Token::Reserved(Keyword::Of),
+            Token::Reserved(Keyword::Real),
+        ]);
+        assert_eq!(parser.parse_array_type(), Err("The elements of an array cannot be arrays".to_string()));
+    }
+
+    #[test]
+    fn failure_constant_without_value() {
+        let mut parser = Parser::new(vec![
+            // Token::Reserved(Keyword::Const),
+            Token::Identifier("c".to_string()),
+            Token::TypeDecl,
+            Token::Reserved(Keyword::Bool),
+            Token::EndStatement,
+        ]);
+        assert_eq!(parser.parse_constant(), Err("Constant c must have a value".to_string()));
+    }
+
+    #[test]
+    fn success_while() {
+        let mut parser = Parser::new(vec![
+            // Token::Reserved(Keyword::While),
+            Token::Identifier("b".to_string()),
+            Token::Reserved(Keyword::Do),
+            Token::Reserved(Keyword::Break),
+            Token::EndStatement,
+            Token::Reserved(Keyword::End),
+            Token::Reserved(Keyword::While),
+            Token::EndStatement,
+        ]);
+        let result = parser.parse_while();
+        assert_eq!(result, Ok(Statement::While {
+            condition: Expression::Simple(Operand::Identifier("b".to_string())),
+            statements: vec![Located { node: Statement::Break, position: Position::default(), end: Position::default() }],
+        }));
+    }
+
+    #[test]
+    fn success_call_operand() {
+        let mut parser = Parser::new(vec![
+            Token::Identifier("f".to_string()),
+            Token::OpenParen,
+            Token::Int(1),
+            Token::Comma,
+            Token::Identifier("x".to_string()),
+            Token::CloseParen,
+            Token::Operator(Operator::Plus),
+            Token::Int(2),
+        ]);
+        let result = parser.parse_expression();
+        assert_eq!(result, Ok(Expression::Binary {
+            left: Operand::Call {
+                identifier: "f".to_string(),
+                arguments: vec![
+                    Expression::Simple(Operand::Int(1)),
+                    Expression::Simple(Operand::Identifier("x".to_string())),
+                ],
+            },
+            operator: Operator::Plus,
+            right: Operand::Int(2),
+        }));
+    }
+
     #[test]
     fn success_for() {
-        let mut parser = Parser::new(vec![]);
+        let _parser = Parser::new(vec![]);
     }
 
     #[test]
     fn failure_for() {
-        let mut parser = Parser::new(vec![]);
+        let _parser = Parser::new(vec![]);
     }
 }
@@ -0,0 +1,190 @@
+//! Printing an AST as Mini-PL source. The printed source parses back to the same AST, apart
+//! from the positions, and is laid out with one statement per line and blocks indented by four
+//! spaces. Expressions are printed by their `Display` implementation, and comments are not kept
+//! in the AST, so they are lost.
+
+use super::ast::*;
+
+use std::fmt::Write;
+
+const INDENT: usize = 4;
+
+/// The source of the AST.
+pub fn print(ast: &Ast) -> String {
+    let mut source = String::new();
+    block(&mut source, &ast.statements, 0);
+    source
+}
+
+/// A type as it is written in the source.
+pub fn type_name(mpl_type: &MplType) -> String {
+    match *mpl_type {
+        MplType::Int => "int".to_string(),
+        MplType::Real => "real".to_string(),
+        MplType::String => "string".to_string(),
+        MplType::Bool => "bool".to_string(),
+        MplType::Array { ref element, size } => format!("array [{}] of {}", size, type_name(element)),
+    }
+}
+
+/// Write the statements, each on its own lines at the depth.
+fn block(source: &mut String, statements: &[Located<Statement>], depth: usize) {
+    for statement in statements {
+        self::statement(source, &statement.node, depth);
+    }
+}
+
+fn statement(source: &mut String, statement: &Statement, depth: usize) {
+    let indent = " ".repeat(INDENT * depth);
+    let target = |identifier: &str, index: &Option<Expression>| match *index {
+        Some(ref index) => format!("{}[{}]", identifier, index),
+        None => identifier.to_string(),
+    };
+    source.push_str(&indent);
+    match *statement {
+        Statement::Declaration { ref identifier, ref mpl_type, value: Some(ref value) }
+            => writeln!(source, "var {} : {} := {};", identifier, type_name(mpl_type), value),
+        Statement::Declaration { ref identifier, ref mpl_type, value: None }
+            => writeln!(source, "var {} : {};", identifier, type_name(mpl_type)),
+        Statement::Constant { ref identifier, ref mpl_type, ref value }
+            => writeln!(source, "const {} : {} := {};", identifier, type_name(mpl_type), value),
+        Statement::Assignment { ref identifier, ref index, ref expression }
+            => writeln!(source, "{} := {};", target(identifier, index), expression),
+        Statement::Read { ref identifier, ref index } => writeln!(source, "read {};", target(identifier, index)),
+        Statement::For { ref identifier, ref begin, ref end, descending, ref step, ref statements } => {
+            let range = if descending { " downto " } else { ".." };
+            write!(source, "for {} in {}{}{}", identifier, begin, range, end).unwrap();
+            if let Some(ref step) = *step {
+                write!(source, " step {}", step).unwrap();
+            }
+            source.push_str(" do\n");
+            block(source, statements, depth + 1);
+            writeln!(source, "{}end for;", indent)
+        },
+        Statement::If { ref condition, ref statements, ref else_statements } => {
+            writeln!(source, "if {} then", condition).unwrap();
+            block(source, statements, depth + 1);
+            if !else_statements.is_empty() {
+                writeln!(source, "{}else", indent).unwrap();
+                block(source, else_statements, depth + 1);
+            }
+            writeln!(source, "{}end if;", indent)
+        },
+        Statement::While { ref condition, ref statements } => {
+            writeln!(source, "while {} do", condition).unwrap();
+            block(source, statements, depth + 1);
+            writeln!(source, "{}end while;", indent)
+        },
+        Statement::Function(ref function) => {
+            let kind = if function.return_type.is_some() { "function" } else { "procedure" };
+            let parameters: Vec<String> = function.parameters.iter()
+                .map(|parameter| format!("{} : {}", parameter.identifier, type_name(&parameter.mpl_type)))
+                .collect();
+            write!(source, "{} {}({})", kind, function.identifier, parameters.join(", ")).unwrap();
+            if let Some(ref return_type) = function.return_type {
+                write!(source, " : {}", type_name(return_type)).unwrap();
+            }
+            source.push_str(" do\n");
+            block(source, &function.statements, depth + 1);
+            writeln!(source, "{}end {};", indent, kind)
+        },
+        Statement::Call { ref identifier, ref arguments } => {
+            let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+            writeln!(source, "{}({});", identifier, arguments.join(", "))
+        },
+        Statement::Return(Some(ref value)) => writeln!(source, "return {};", value),
+        Statement::Return(None) => writeln!(source, "return;"),
+        Statement::Print(ref value) => writeln!(source, "print {};", value),
+        Statement::Assert(ref value) => writeln!(source, "assert ({});", value),
+        Statement::Break => writeln!(source, "break;"),
+        Statement::Continue => writeln!(source, "continue;"),
+        Statement::Empty => writeln!(source, ";"),
+    }.unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::fuzz::generator::Generator;
+    use super::super::super::scanner;
+
+    fn parse_source(source: &str) -> Ast {
+        super::super::parse(scanner::scan(source, false).unwrap()).unwrap()
+    }
+
+    /// The statements with all positions set to the default, to compare trees parsed from
+    /// different sources.
+    fn without_positions(statements: &[Located<Statement>]) -> Vec<Located<Statement>> {
+        statements.iter().map(|statement| {
+            let mut node = statement.node.clone();
+            match node {
+                Statement::For { ref mut statements, .. } | Statement::While { ref mut statements, .. } => {
+                    *statements = without_positions(statements);
+                },
+                Statement::If { ref mut statements, ref mut else_statements, .. } => {
+                    *statements = without_positions(statements);
+                    *else_statements = without_positions(else_statements);
+                },
+                Statement::Function(ref mut function) => function.statements = without_positions(&function.statements),
+                _ => (),
+            }
+            Located { node, position: Position::default(), end: Position::default() }
+        }).collect()
+    }
+
+    #[test]
+    fn layout() {
+        let source = "var a : array [3] of real; for i in 3 downto 1 step 2 do if !b then a[i] := (1.5 * x) + 0.0; \
+                      else ; end if; end for; procedure p(s : string, n : int) do while true do break; end while; \
+                      return; end procedure; function f() : bool do return g(1, \"a\\\"\\\\\\n\") & (c.size = 2); \
+                      end function; assert (x <> y); print -y; read a[0]; p(\"\", 0);";
+        assert_eq!(print(&parse_source(source)), "\
+var a : array [3] of real;
+for i in 3 downto 1 step 2 do
+    if !b then
+        a[i] := (1.5 * x) + 0.0;
+    else
+        ;
+    end if;
+end for;
+procedure p(s : string, n : int) do
+    while true do
+        break;
+    end while;
+    return;
+end procedure;
+function f() : bool do
+    return g(1, \"a\\\"\\\\\\n\") & (c.size = 2);
+end function;
+assert (x <> y);
+print -y;
+read a[0];
+p(\"\", 0);
+");
+    }
+
+    #[test]
+    fn literals_made_by_folding() {
+        let print_operand = |operand: Operand| Expression::Simple(operand).to_string();
+        assert_eq!(print_operand(Operand::Int(-5)), "(0 - 5)");
+        assert_eq!(print_operand(Operand::Int(i32::MIN)), "((0 - 2147483647) - 1)");
+        assert_eq!(print_operand(Operand::Real(-0.25)), "(0.0 - 0.25)");
+        assert_eq!(print_operand(Operand::Real(-0.0)), "0.0");
+        assert_eq!(print_operand(Operand::Real(2.5e-7)), "2.5e-7");
+        assert_eq!(print_operand(Operand::Real(f64::INFINITY)), "(1e308 * 10.0)");
+        let bigint = |digits: &str| Operand::BigInt(BigInt::parse(digits).unwrap());
+        assert_eq!(print_operand(bigint("12345678901")), "12345678901");
+        assert_eq!(print_operand(Operand::BigInt(BigInt::from(-12345678901))), "(0 - 12345678901)");
+    }
+
+    #[test]
+    fn printed_source_parses_back_to_the_same_tree() {
+        let mut generator = Generator::new(0);
+        for _ in 0..500 {
+            let ast = generator.shape();
+            let source = print(&ast);
+            let parsed = parse_source(&source);
+            assert_eq!(without_positions(&parsed.statements), ast.statements, "source:\n{}", source);
+        }
+    }
+}
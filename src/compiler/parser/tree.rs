@@ -0,0 +1,203 @@
+//! The AST as a tree of labelled nodes, for showing it as indented text or drawing it with
+//! Graphviz.
+
+use super::ast::*;
+
+use std::fmt;
+use std::fmt::Write;
+
+/// A node with a label and its children.
+#[derive(Debug, PartialEq)]
+pub struct Tree {
+    pub label: String,
+    pub children: Vec<Tree>,
+}
+
+impl Tree {
+    fn new(label: String, children: Vec<Tree>) -> Tree {
+        Tree { label, children }
+    }
+
+    fn leaf(label: String) -> Tree {
+        Tree::new(label, Vec::new())
+    }
+
+    /// The tree in the DOT language of Graphviz. The nodes are numbered in preorder.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph ast {\n  node [shape=box, fontname=monospace];\n".to_string();
+        self.write_dot(&mut dot, &mut 0);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write the node and its descendants, and return the number of the node.
+    fn write_dot(&self, dot: &mut String, next: &mut usize) -> usize {
+        let id = *next;
+        *next += 1;
+        let label = self.label.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(dot, "  n{} [label=\"{}\"];", id, label).unwrap();
+        for child in &self.children {
+            let child = child.write_dot(dot, next);
+            writeln!(dot, "  n{} -> n{};", id, child).unwrap();
+        }
+        id
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(f, "{:width$}{}", "", self.label, width = 2 * depth)?;
+        for child in &self.children {
+            child.write_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tree {
+    /// The tree with each node on its own line, indented under its parent.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// The tree of an AST.
+pub fn tree(ast: &Ast) -> Tree {
+    Tree::new("Program".to_string(), statements(&ast.statements))
+}
+
+fn statements(statements: &[Located<Statement>]) -> Vec<Tree> {
+    statements.iter().map(statement).collect()
+}
+
+fn statement(statement: &Located<Statement>) -> Tree {
+    let position = statement.position;
+    let (label, children) = match statement.node {
+        Statement::Declaration { ref identifier, ref mpl_type, ref value } => {
+            (format!("Declaration {} : {}", identifier, mpl_type), value.iter().map(expression).collect())
+        },
+        Statement::Constant { ref identifier, ref mpl_type, ref value } => {
+            (format!("Constant {} : {}", identifier, mpl_type), vec![expression(value)])
+        },
+        Statement::Assignment { ref identifier, ref index, expression: ref value } => {
+            let mut children: Vec<Tree> = index.iter().map(|index| Tree::new("Index".to_string(), vec![expression(index)])).collect();
+            children.push(expression(value));
+            (format!("Assignment {}", identifier), children)
+        },
+        Statement::For { ref identifier, ref begin, ref end, descending, ref step, statements: ref body } => {
+            let mut children = vec![expression(begin), expression(end)];
+            children.extend(step.iter().map(|step| Tree::new("Step".to_string(), vec![expression(step)])));
+            children.push(Tree::new("Body".to_string(), statements(body)));
+            let label = if descending { format!("For {} downto", identifier) } else { format!("For {}", identifier) };
+            (label, children)
+        },
+        Statement::If { ref condition, statements: ref then_statements, ref else_statements } => {
+            let mut children = vec![expression(condition), Tree::new("Then".to_string(), statements(then_statements))];
+            if !else_statements.is_empty() {
+                children.push(Tree::new("Else".to_string(), statements(else_statements)));
+            }
+            ("If".to_string(), children)
+        },
+        Statement::While { ref condition, statements: ref body } => {
+            ("While".to_string(), vec![expression(condition), Tree::new("Body".to_string(), statements(body))])
+        },
+        Statement::Break => ("Break".to_string(), Vec::new()),
+        Statement::Continue => ("Continue".to_string(), Vec::new()),
+        Statement::Function(ref function) => {
+            let kind = if function.return_type.is_some() { "Function" } else { "Procedure" };
+            let mut children: Vec<Tree> = function.parameters.iter()
+                .map(|parameter| Tree::leaf(format!("Parameter {} : {}", parameter.identifier, parameter.mpl_type)))
+                .collect();
+            children.extend(function.return_type.iter().map(|mpl_type| Tree::leaf(format!("Returns {}", mpl_type))));
+            children.push(Tree::new("Body".to_string(), statements(&function.statements)));
+            (format!("{} {}", kind, function.identifier), children)
+        },
+        Statement::Call { ref identifier, ref arguments } => {
+            (format!("Call {}", identifier), arguments.iter().map(expression).collect())
+        },
+        Statement::Return(ref value) => ("Return".to_string(), value.iter().map(expression).collect()),
+        Statement::Read { ref identifier, ref index } => {
+            let children = index.iter().map(|index| Tree::new("Index".to_string(), vec![expression(index)])).collect();
+            (format!("Read {}", identifier), children)
+        },
+        Statement::Print(ref value) => ("Print".to_string(), vec![expression(value)]),
+        Statement::Assert(ref value) => ("Assert".to_string(), vec![expression(value)]),
+        Statement::Empty => ("Empty".to_string(), Vec::new()),
+    };
+    Tree::new(format!("{} at {}", label, position), children)
+}
+
+fn expression(expr: &Expression) -> Tree {
+    match *expr {
+        Expression::Simple(ref operand) => operand_tree(operand),
+        Expression::Binary { ref left, operator, ref right }
+            => Tree::new(format!("Binary {}", operator), vec![operand_tree(left), operand_tree(right)]),
+        Expression::Unary { operator, ref operand } => Tree::new(format!("Unary {}", operator), vec![operand_tree(operand)]),
+    }
+}
+
+fn operand_tree(operand: &Operand) -> Tree {
+    match *operand {
+        Operand::Int(i) => Tree::leaf(format!("Int {}", i)),
+        Operand::BigInt(ref i) => Tree::leaf(format!("Int {}", i)),
+        Operand::Real(r) => Tree::leaf(format!("Real {}", format_real(r))),
+        Operand::String(ref s) => Tree::leaf(format!("String {:?}", s)),
+        Operand::Bool(b) => Tree::leaf(format!("Bool {}", b)),
+        Operand::Identifier(ref identifier) => Tree::leaf(format!("Identifier {}", identifier)),
+        Operand::Index { ref identifier, ref index } => Tree::new(format!("Index {}", identifier), vec![expression(index)]),
+        Operand::Size(ref identifier) => Tree::leaf(format!("Size {}", identifier)),
+        Operand::Call { ref identifier, ref arguments } => {
+            Tree::new(format!("Call {}", identifier), arguments.iter().map(expression).collect())
+        },
+        Operand::Expr(ref expr) => expression(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::scanner;
+
+    fn tree_source(source: &str) -> Tree {
+        tree(&super::super::parse(scanner::scan(source, false).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn indented_tree() {
+        let source = "var a : array [2] of int;\nfor i in 0..1 do a[i] := (i * 2) + 1; end for;\nprint \"n=\" + a.size;\n";
+        assert_eq!(tree_source(source).to_string(), "\
+Program
+  Declaration a : Array [2] of Integer at 1:1
+  For i at 2:1
+    Int 0
+    Int 1
+    Body
+      Assignment a at 2:18
+        Index
+          Identifier i
+        Binary +
+          Binary *
+            Identifier i
+            Int 2
+          Int 1
+  Print at 3:1
+    Binary +
+      String \"n=\"
+      Size a
+");
+    }
+
+    #[test]
+    fn dot_tree() {
+        assert_eq!(tree_source("print !b;\n").to_dot(), "\
+digraph ast {
+  node [shape=box, fontname=monospace];
+  n0 [label=\"Program\"];
+  n1 [label=\"Print at 1:1\"];
+  n2 [label=\"Unary !\"];
+  n3 [label=\"Identifier b\"];
+  n2 -> n3;
+  n1 -> n2;
+  n0 -> n1;
+}
+");
+    }
+}
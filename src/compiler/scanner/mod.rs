@@ -2,17 +2,131 @@
 //! Use lexer::scan(sourceString) to scan source code to tokens.
 
 pub mod token;
+#[allow(clippy::module_inception)]
 mod scanner;
 
 use std::vec::Vec;
 
-/// Scan the input string, return a vector of tokens (lexer::token::Token) or an error.
-pub fn scan(input: &String) -> Result<Vec<token::Token>, String> {
-    let mut scanner = scanner::Scanner::new();
+/// Scan the input string, return a vector of tokens (lexer::token::Token) paired with their
+/// positions in the source, or an error. Integer literals larger than 32 bits are an error,
+/// unless `bigint` is set.
+pub fn scan(input: &str, bigint: bool) -> Result<Vec<(token::Token, token::Position)>, String> {
+    let mut scanner = scanner::Scanner::new(bigint);
 
-    for c in (*input).chars() {
+    for c in input.chars() {
         scanner.consume(c);
     };
 
     scanner.into_tokens()
 }
+
+/// Scan the input string like `scan`, and also return the comments in it.
+pub fn scan_with_comments(input: &str, bigint: bool) -> Result<ScannedSource, String> {
+    let mut scanner = scanner::Scanner::new(bigint);
+
+    for c in input.chars() {
+        scanner.consume(c);
+    };
+
+    scanner.into_tokens_and_comments()
+}
+
+/// The tokens of a source paired with their positions, and the comments of the source.
+pub type ScannedSource = (Vec<(token::Token, token::Position)>, Vec<token::Comment>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::token::{Comment, Operator, Position, Token};
+    use super::super::bigint::BigInt;
+
+    fn scan_tokens(input: &str) -> Vec<Token> {
+        scan(input, false).unwrap().into_iter().map(|(token, _)| token).collect()
+    }
+
+    #[test]
+    fn multicharacter_operators() {
+        assert_eq!(scan_tokens("a<=b<>c>=d<e>f;"), vec![
+            Token::Identifier("a".to_string()),
+            Token::Operator(Operator::LessEqual),
+            Token::Identifier("b".to_string()),
+            Token::Operator(Operator::NotEqual),
+            Token::Identifier("c".to_string()),
+            Token::Operator(Operator::GreaterEqual),
+            Token::Identifier("d".to_string()),
+            Token::Operator(Operator::Less),
+            Token::Identifier("e".to_string()),
+            Token::Operator(Operator::Greater),
+            Token::Identifier("f".to_string()),
+            Token::EndStatement,
+        ]);
+        assert_eq!(scan_tokens("1 < 2 % 3 | x;"), vec![
+            Token::Int(1),
+            Token::Operator(Operator::Less),
+            Token::Int(2),
+            Token::Operator(Operator::Modulo),
+            Token::Int(3),
+            Token::Operator(Operator::Or),
+            Token::Identifier("x".to_string()),
+            Token::EndStatement,
+        ]);
+    }
+
+    #[test]
+    fn reals_and_ranges() {
+        assert_eq!(scan_tokens("1..2 1.5..2.0e1 3e-2 4E+1 5.size;"), vec![
+            Token::Int(1),
+            Token::Range,
+            Token::Int(2),
+            Token::Real(1.5),
+            Token::Range,
+            Token::Real(20.0),
+            Token::Real(0.03),
+            Token::Real(40.0),
+            Token::Int(5),
+            Token::Dot,
+            Token::Identifier("size".to_string()),
+            Token::EndStatement,
+        ]);
+        let positions: Vec<Position> = scan("x 1..2;", false).unwrap().into_iter()
+            .map(|(_, position)| position)
+            .collect();
+        assert_eq!(positions, vec![
+            Position::new(1, 1),
+            Position::new(1, 3),
+            Position::new(1, 4),
+            Position::new(1, 6),
+            Position::new(1, 7),
+        ]);
+    }
+
+    #[test]
+    fn malformed_reals() {
+        assert_eq!(scan("var x : real := 1e;", false), Err("Malformed real 1e at 1:17".to_string()));
+        assert_eq!(scan("print 1e400;", false), Err("Real 1e400 at 1:7 is too large".to_string()));
+        assert_eq!(scan("print 1.;", false), Ok(vec![
+            (Token::Reserved(token::Keyword::Print), Position::new(1, 1)),
+            (Token::Int(1), Position::new(1, 7)),
+            (Token::Dot, Position::new(1, 8)),
+            (Token::EndStatement, Position::new(1, 9)),
+        ]));
+    }
+
+    #[test]
+    fn large_integers() {
+        assert_eq!(scan("print 2147483648;", false), Err("Integer 2147483648 at 1:7 is too large".to_string()));
+        assert_eq!(scan("print 2147483648;", true).unwrap()[1].0, Token::BigInt(BigInt::parse("2147483648").unwrap()));
+        assert_eq!(scan("print 2147483647;", true).unwrap()[1].0, Token::Int(i32::MAX));
+    }
+
+    #[test]
+    fn comments() {
+        let (tokens, comments) = scan_with_comments("x := 1; // one\n// plint-allow: dead-store\nx := 2;// last", false).unwrap();
+        assert_eq!(tokens.len(), 8);
+        assert_eq!(comments, vec![
+            Comment { text: " one".to_string(), position: Position::new(1, 9) },
+            Comment { text: " plint-allow: dead-store".to_string(), position: Position::new(2, 1) },
+            Comment { text: " last".to_string(), position: Position::new(3, 8) },
+        ]);
+    }
+}
@@ -1,7 +1,13 @@
 
 use super::token::Token;
 use super::token::Keyword;
-use std::mem::replace;
+use super::token::Position;
+use super::token::Operator;
+use super::token::Comment;
+use super::ScannedSource;
+use super::super::bigint::BigInt;
+
+use std::mem;
 
 /// The internal state of the scanner.
 enum State {
@@ -11,24 +17,54 @@ enum State {
     ReadingEscape,
     ReadingString,
     ReadingInt,
+    ReadingPoint, // an integer followed by a dot, which may start a real or a range
+    ReadingReal,
+    ReadingExponent, // a real after e, or after the sign of the exponent
     ReadingWord,
 }
 
 /// The scanner is a state machine, with a buffer for reading multicharacter tokens.
+/// Every token is stored with the position of its first character.
 pub struct Scanner {
-    tokens: Vec<Token>,
+    tokens: Vec<(Token, Position)>,
+    comments: Vec<Comment>,
     buffer: String,
     state: State,
+    position: Position, // position of the character being consumed
+    start: Position, // position of the first character of the current token
+    error: Option<String>, // the first malformed token
+    bigint: bool, // whether integer literals may be larger than 32 bits
 }
 
 impl Scanner {
-    /// Initialize a scanner.
-    pub fn new() -> Scanner {
-        Scanner { tokens: Vec::new(), buffer: String::new(), state: State::Empty }
+    /// Initialize a scanner. Integer literals must fit in 32 bits, unless `bigint` is set.
+    pub fn new(bigint: bool) -> Scanner {
+        Scanner {
+            tokens: Vec::new(),
+            comments: Vec::new(),
+            buffer: String::new(),
+            state: State::Empty,
+            position: Position::new(1, 1),
+            start: Position::new(1, 1),
+            error: None,
+            bigint,
+        }
     }
 
     /// Consume the next character from input. The Scanner expects characters to be fed externally.
     pub fn consume(&mut self, c: char) {
+        self.step(c);
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+    }
+
+    /// Advance the state machine by one character. Does not move the position, so that a
+    /// character can be re-consumed after a state change.
+    fn step(&mut self, c: char) {
         match self.state {
             State::Unclear => {
                 let stored_c = match self.buffer.chars().next() {
@@ -38,29 +74,36 @@ impl Scanner {
                 self.buffer = String::new();
                 self.state = State::Empty;
                 match (stored_c, c) {
-                    (':', '=') => self.tokens.push(Token::Assignment),
+                    (':', '=') => self.push(Token::Assignment),
                     ('/', '/') => self.state = State::Comment,
-                    ('.', '.') => self.tokens.push(Token::Range),
+                    ('.', '.') => self.push(Token::Range),
+                    ('<', '=') => self.push(Token::Operator(Operator::LessEqual)),
+                    ('<', '>') => self.push(Token::Operator(Operator::NotEqual)),
+                    ('>', '=') => self.push(Token::Operator(Operator::GreaterEqual)),
                     (':', _) => {
-                        self.tokens.push(Token::TypeDecl);
-                        self.consume(c) // re-consume with new state
+                        self.push(Token::TypeDecl);
+                        self.step(c) // re-consume with new state
                     },
                     ('.', _) => {
-                        self.tokens.push(Token::Dot);
-                        self.consume(c) // re-consume
+                        self.push(Token::Dot);
+                        self.step(c) // re-consume
                     },
                     _ => {
-                        self.tokens.push(Token::Operator(stored_c));
-                        self.consume(c) // re-consume with new state
+                        self.push(operator_token(stored_c));
+                        self.step(c) // re-consume with new state
                     }
                 }
             },
             State::Comment => {
                 if c == '\n' {
+                    self.push_comment();
                     self.state = State::Empty
+                } else {
+                    self.buffer.push(c);
                 }
             },
             State::Empty => {
+                self.start = self.position;
                 match c {
                     '"' => {
                         self.state = State::ReadingString;
@@ -77,10 +120,13 @@ impl Scanner {
                         self.buffer.push(c);
                         self.state = State::Unclear;
                     },
-                    _ if is_operator(c) => self.tokens.push(Token::Operator(c)),
-                    ')' => self.tokens.push(Token::CloseParen),
-                    '(' => self.tokens.push(Token::OpenParen),
-                    ';' => self.tokens.push(Token::EndStatement),
+                    _ if is_operator(c) => self.push(operator_token(c)),
+                    ')' => self.push(Token::CloseParen),
+                    '(' => self.push(Token::OpenParen),
+                    '[' => self.push(Token::OpenBracket),
+                    ']' => self.push(Token::CloseBracket),
+                    ';' => self.push(Token::EndStatement),
+                    ',' => self.push(Token::Comma),
                     _ => (),
                 }
             },
@@ -93,19 +139,63 @@ impl Scanner {
                     self.state = State::ReadingString;
             },
             State::ReadingString if c == '\\' => self.state = State::ReadingEscape,
+            State::ReadingInt if c == '.' => {
+                self.buffer.push(c);
+                self.state = State::ReadingPoint;
+            },
+            State::ReadingPoint => {
+                if is_integral(c) {
+                    self.buffer.push(c);
+                    self.state = State::ReadingReal;
+                    return;
+                }
+                // Not a real, but an integer followed by .. or by a dot
+                self.buffer.pop();
+                self.state = State::ReadingInt;
+                self.add_token();
+                self.state = State::Empty;
+                self.start = Position::new(self.position.line, self.position.column - 1);
+                if c == '.' {
+                    self.push(Token::Range);
+                } else {
+                    self.push(Token::Dot);
+                    self.step(c) // re-consume
+                }
+            },
+            State::ReadingInt | State::ReadingReal if (c == 'e' || c == 'E') && !self.buffer.contains('e') => {
+                self.buffer.push('e');
+                self.state = State::ReadingExponent;
+            },
+            State::ReadingExponent => {
+                if is_integral(c) {
+                    self.buffer.push(c);
+                    self.state = State::ReadingReal;
+                } else if (c == '+' || c == '-') && self.buffer.ends_with('e') {
+                    self.buffer.push(c);
+                } else {
+                    let literal = std::mem::take(&mut self.buffer);
+                    self.malformed(format!("Malformed real {} at {}", literal, self.start));
+                    self.state = State::Empty;
+                    self.step(c) // re-consume
+                }
+            },
             _ => {
                 if read_end(&self.state, c) {
                     self.add_token();
                     self.state = State::Empty;
+                    self.start = self.position;
                     match c {
                         _ if is_unclear(c) => {
                             self.buffer.push(c);
                             self.state = State::Unclear;
                         },
-                        _ if is_operator(c) => self.tokens.push(Token::Operator(c)),
-                        ';' => self.tokens.push(Token::EndStatement),
-                        ')' => self.tokens.push(Token::CloseParen),
-                        '(' => self.tokens.push(Token::OpenParen),
+                        _ if is_operator(c) => self.push(operator_token(c)),
+                        ';' => self.push(Token::EndStatement),
+                        ',' => self.push(Token::Comma),
+                        ')' => self.push(Token::CloseParen),
+                        '(' => self.push(Token::OpenParen),
+                        '[' => self.push(Token::OpenBracket),
+                        ']' => self.push(Token::CloseBracket),
                         _ => (),
                     }
                 } else {
@@ -116,12 +206,26 @@ impl Scanner {
     }
 
     /// Extract tokens from the scanner. If the scanner is still reading, return error.
-    pub fn into_tokens(self) -> Result<Vec<Token>, String> {
+    pub fn into_tokens(self) -> Result<Vec<(Token, Position)>, String> {
+        self.into_tokens_and_comments().map(|(tokens, _)| tokens)
+    }
+
+    /// Extract the tokens and the comments from the scanner. If the scanner is still reading,
+    /// return error.
+    pub fn into_tokens_and_comments(mut self) -> Result<ScannedSource, String> {
         let end = "Reached end while scanning";
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if let State::Comment = self.state {
+            self.push_comment();
+            self.state = State::Empty;
+        }
         match self.state {
-            State::Empty | State::Comment => Ok(self.tokens),
+            State::Empty | State::Comment => Ok((self.tokens, self.comments)),
             State::Unclear => Err(format!("{}. {} expected continuation", end, self.buffer)),
             State::ReadingInt => Err(format!("{} integer.", end)),
+            State::ReadingPoint | State::ReadingReal | State::ReadingExponent => Err(format!("{} real.", end)),
             State::ReadingString | State::ReadingEscape => Err(format!("{} string.", end)),
             State::ReadingWord =>  Err(format!("{} word.", end)),
         }
@@ -131,51 +235,80 @@ impl Scanner {
     fn add_token(&mut self) {
         let new_token = match self.state {
             State::ReadingInt => {
-                let literal = replace(&mut self.buffer, String::new());
-                Token::Int(literal.parse().unwrap()) // parse failure should be impossible, so unwrap
+                let literal = std::mem::take(&mut self.buffer);
+                match literal.parse() {
+                    Ok(i) => Token::Int(i),
+                    Err(_) if self.bigint => Token::BigInt(BigInt::parse(&literal).expect("only digits are scanned")),
+                    Err(_) => {
+                        self.malformed(format!("Integer {} at {} is too large", literal, self.start));
+                        Token::Int(0)
+                    },
+                }
+            },
+            State::ReadingReal => {
+                let literal = std::mem::take(&mut self.buffer);
+                let real: f64 = literal.parse().unwrap(); // the state machine only accepts valid literals
+                if real.is_infinite() {
+                    self.malformed(format!("Real {} at {} is too large", literal, self.start));
+                }
+                Token::Real(real)
             },
-            State::ReadingString => Token::String(replace(&mut self.buffer, String::new())),
-            State::ReadingWord => word_token(replace(&mut self.buffer, String::new())),
+            State::ReadingString => Token::String(std::mem::take(&mut self.buffer)),
+            State::ReadingWord => word_token(std::mem::take(&mut self.buffer)),
             _ => unreachable!("add_token called on non-reading state (scanner)"),
         };
-        self.tokens.push(new_token);
+        self.push(new_token);
+    }
+
+    /// Record an error of a malformed token. Scanning continues, but only the first error is
+    /// reported.
+    fn malformed(&mut self, error: String) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    /// Store a token at the start position of the current token.
+    fn push(&mut self, token: Token) {
+        self.tokens.push((token, self.start));
+    }
+
+    /// Store the comment read into the buffer. It starts where the first slash was.
+    fn push_comment(&mut self) {
+        let text = mem::take(&mut self.buffer);
+        self.comments.push(Comment { text, position: self.start });
     }
 }
 
 /// Helper. Accepts characters 0 ... 9 as integral.
 fn is_integral(c: char) -> bool {
-    match c {
-        '0' ... '9' => true,
-        _ => false,
-    }
+    c.is_ascii_digit()
 }
 
 /// Helper. Accepts characters 0 ... 9 as numeric, and a...z, A...Z as alphabetic.
 fn is_alphanumeric(c: char) -> bool {
-    match c {
-        _ if is_integral(c) => true,
-        'a'...'z' | 'A'...'Z' => true,
-        '_' => true,
-        _ => false,
-    }
+    is_integral(c) || matches!(c, 'a'..='z' | 'A'..='Z' | '_')
 }
 
 /// Helper. Characters that could be part of a single character token, or a different longer token
-/// are considered unclear. They are ":", "/", ".". For example : could be either a type-declaration
-/// token, or the beginning of an assignment token (:=).
+/// are considered unclear. They are ":", "/", ".", "<", ">". For example : could be either a
+/// type-declaration token, or the beginning of an assignment token (:=), and < could be the
+/// beginning of <= or <>.
 fn is_unclear(c: char) -> bool {
-    match c {
-        // * not included, it is a special case for comment
-        ':' | '/' | '.'  => true,
-        _ => false,
-    }
+    // * not included, it is a special case for comment
+    matches!(c, ':' | '/' | '.' | '<' | '>')
 }
 
 /// Helper. Return true if the character is an operator in MiniPl.
 fn is_operator(c: char) -> bool {
-    match c {
-        '+' | '-' | '*' | '/' | '&' | '!' | '=' | '<' => true,
-        _ => false,
+    Operator::from_char(c).is_some()
+}
+
+/// Helper. The token of a single character operator.
+fn operator_token(c: char) -> Token {
+    match Operator::from_char(c) {
+        Some(operator) => Token::Operator(operator),
+        None => unreachable!("{} is not an operator (scanner)", c),
     }
 }
 
@@ -183,7 +316,7 @@ fn is_operator(c: char) -> bool {
 /// and encounters a (unescaped) quote (") return true.
 fn read_end(state: &State, c: char) -> bool {
     match *state {
-        State::ReadingInt => !is_integral(c),
+        State::ReadingInt | State::ReadingReal => !is_integral(c),
         State::ReadingString => c == '"',
         State::ReadingWord => !is_alphanumeric(c),
         _ => false, // calling this is useless on other states
@@ -196,7 +329,10 @@ fn word_token(word: String) -> Token {
         "true" => Token::Bool(true),
         "false" => Token::Bool(false),
         "var" => Token::Reserved(Keyword::Var),
+        "const" => Token::Reserved(Keyword::Const),
         "for" => Token::Reserved(Keyword::For),
+        "downto" => Token::Reserved(Keyword::Downto),
+        "step" => Token::Reserved(Keyword::Step),
         "end" => Token::Reserved(Keyword::End),
         "in" => Token::Reserved(Keyword::In),
         "do" => Token::Reserved(Keyword::Do),
@@ -205,7 +341,19 @@ fn word_token(word: String) -> Token {
         "int" => Token::Reserved(Keyword::Int),
         "string" => Token::Reserved(Keyword::String),
         "bool" => Token::Reserved(Keyword::Bool),
+        "real" => Token::Reserved(Keyword::Real),
         "assert" => Token::Reserved(Keyword::Assert),
+        "if" => Token::Reserved(Keyword::If),
+        "then" => Token::Reserved(Keyword::Then),
+        "else" => Token::Reserved(Keyword::Else),
+        "while" => Token::Reserved(Keyword::While),
+        "break" => Token::Reserved(Keyword::Break),
+        "continue" => Token::Reserved(Keyword::Continue),
+        "procedure" => Token::Reserved(Keyword::Procedure),
+        "function" => Token::Reserved(Keyword::Function),
+        "return" => Token::Reserved(Keyword::Return),
+        "array" => Token::Reserved(Keyword::Array),
+        "of" => Token::Reserved(Keyword::Of),
         _ => Token::Identifier(word),
     }
 }
@@ -1,16 +1,25 @@
+use std::fmt;
+
+use super::super::bigint::BigInt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Reserved(Keyword),
     Identifier(String),
     Int(i32),
+    BigInt(BigInt), // an integer literal too large for Int, only scanned with --bigint
+    Real(f64),
     String(String),
     Bool(bool),
-    Operator(char),
+    Operator(Operator),
     OpenParen,
     CloseParen,
+    OpenBracket,
+    CloseBracket,
     Assignment,
     TypeDecl,
     Range,
+    Comma,
     Dot,
     EndStatement,
 }
@@ -18,14 +27,174 @@ pub enum Token {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Keyword {
     Var,
+    Const,
     For,
+    Downto,
+    Step,
     End,
     In,
     Do,
     Read,
     Print,
     Assert,
+    If,
+    Then,
+    Else,
+    While,
+    Break,
+    Continue,
+    Procedure,
+    Function,
+    Return,
     Int,
     String,
     Bool,
+    Real,
+    Array,
+    Of,
+}
+
+impl fmt::Display for Keyword {
+    /// The keyword as it is written in the source.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let word = match *self {
+            Keyword::Var => "var",
+            Keyword::Const => "const",
+            Keyword::For => "for",
+            Keyword::Downto => "downto",
+            Keyword::Step => "step",
+            Keyword::End => "end",
+            Keyword::In => "in",
+            Keyword::Do => "do",
+            Keyword::Read => "read",
+            Keyword::Print => "print",
+            Keyword::Assert => "assert",
+            Keyword::If => "if",
+            Keyword::Then => "then",
+            Keyword::Else => "else",
+            Keyword::While => "while",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+            Keyword::Procedure => "procedure",
+            Keyword::Function => "function",
+            Keyword::Return => "return",
+            Keyword::Int => "int",
+            Keyword::String => "string",
+            Keyword::Bool => "bool",
+            Keyword::Real => "real",
+            Keyword::Array => "array",
+            Keyword::Of => "of",
+        };
+        write!(f, "{}", word)
+    }
+}
+
+/// The operators of Mini PL. All of them are binary operators, except ! which is unary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Modulo,
+    And,
+    Or,
+    Not,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Operator {
+    /// The operator of a single character operator token.
+    pub fn from_char(c: char) -> Option<Operator> {
+        match c {
+            '+' => Some(Operator::Plus),
+            '-' => Some(Operator::Minus),
+            '*' => Some(Operator::Times),
+            '/' => Some(Operator::Divide),
+            '%' => Some(Operator::Modulo),
+            '&' => Some(Operator::And),
+            '|' => Some(Operator::Or),
+            '!' => Some(Operator::Not),
+            '=' => Some(Operator::Equal),
+            '<' => Some(Operator::Less),
+            '>' => Some(Operator::Greater),
+            _ => None,
+        }
+    }
+
+    /// The operator written as the text, as in the source.
+    pub fn from_text(text: &str) -> Option<Operator> {
+        match text {
+            "<>" => Some(Operator::NotEqual),
+            "<=" => Some(Operator::LessEqual),
+            ">=" => Some(Operator::GreaterEqual),
+            _ => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Operator::from_char(c),
+                    _ => None,
+                }
+            },
+        }
+    }
+
+    /// Whether the operator compares its operands, which must be of the same basic type.
+    pub fn is_comparison(self) -> bool {
+        matches!(self, Operator::Equal | Operator::NotEqual | Operator::Less
+            | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual)
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = match *self {
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Times => "*",
+            Operator::Divide => "/",
+            Operator::Modulo => "%",
+            Operator::And => "&",
+            Operator::Or => "|",
+            Operator::Not => "!",
+            Operator::Equal => "=",
+            Operator::NotEqual => "<>",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+        };
+        write!(f, "{}", sign)
+    }
+}
+
+/// A position in the source code. Lines and columns are counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A comment in the source, from // to the end of the line. The text excludes the slashes and
+/// the newline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub position: Position,
 }
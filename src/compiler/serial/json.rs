@@ -0,0 +1,346 @@
+//! A JSON value with a writer and a parser. Numbers are kept as their text, so integers of any
+//! size are read and written exactly.
+
+use std::fmt;
+
+/// A JSON value. The fields of an object keep their order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// An object of the fields, given in order.
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+    }
+
+    pub fn string(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+
+    pub fn number<N: ToString>(n: N) -> Json {
+        Json::Number(n.to_string())
+    }
+
+    /// The value of a field of an object, None if the value is not an object or has no such
+    /// field.
+    pub fn get(&self, name: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref fields) => fields.iter().find(|field| field.0 == name).map(|field| &field.1),
+            _ => None,
+        }
+    }
+
+    /// The name of the kind of the value, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            Json::Null => "null",
+            Json::Bool(_) => "a boolean",
+            Json::Number(_) => "a number",
+            Json::String(_) => "a string",
+            Json::Array(_) => "an array",
+            Json::Object(_) => "an object",
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    /// The value as compact JSON, without whitespace.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(ref n) => write!(f, "{}", n),
+            Json::String(ref s) => write!(f, "{}", quote(s)),
+            Json::Array(ref elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{}", quote(name), value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+/// Quote and escape a string as a JSON string literal.
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parse a JSON text. The error tells the line and column it was found at.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { chars: text.chars().collect(), index: 0 };
+    let result = parser.value().and_then(|value| {
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(value),
+            Some(c) => Err(format!("Unexpected {} after the value", c)),
+        }
+    });
+    result.map_err(|e| format!("{}: {}", parser.position(), e))
+}
+
+/// A recursive descent parser over the characters of the text.
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.index += 1;
+        c
+    }
+
+    /// The line and column of the current character.
+    fn position(&self) -> String {
+        let before = &self.chars[..self.index.min(self.chars.len())];
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        format!("{}:{}", line, column)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c == ' ' || c == '\t' || c == '\n' || c == '\r') {
+            self.index += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => { self.index += 1; Ok(()) },
+            Some(c) => Err(format!("Expected {}, got {}", expected, c)),
+            None => Err(format!("Expected {}, got the end", expected)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Json::String),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.index;
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.index += 1;
+                }
+                let word: String = self.chars[start..self.index].iter().collect();
+                match word.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => { self.index = start; Err(format!("Unexpected {}", word)) },
+                }
+            },
+            Some(c) => Err(format!("Unexpected {}", c)),
+            None => Err("Expected a value, got the end".to_string()),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.index += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err("Expected the name of a field".to_string());
+            }
+            let name = self.string()?;
+            self.expect(':')?;
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => (),
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => { self.index -= 1; return Err("Expected , or } after a field".to_string()); },
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.index += 1;
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => (),
+                Some(']') => return Ok(Json::Array(elements)),
+                _ => { self.index -= 1; return Err("Expected , or ] after an element".to_string()); },
+            }
+        }
+    }
+
+    /// A number, checked against the JSON grammar and kept as its text.
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.index;
+        let digits = |parser: &mut Parser| {
+            let start = parser.index;
+            while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+                parser.index += 1;
+            }
+            parser.index > start
+        };
+        if self.peek() == Some('-') {
+            self.index += 1;
+        }
+        let mut valid = digits(self);
+        if self.peek() == Some('.') {
+            self.index += 1;
+            valid &= digits(self);
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            self.index += 1;
+            if let Some('+') | Some('-') = self.peek() {
+                self.index += 1;
+            }
+            valid &= digits(self);
+        }
+        let text: String = self.chars[start..self.index].iter().collect();
+        if valid {
+            Ok(Json::Number(text))
+        } else {
+            self.index = start;
+            Err(format!("Malformed number {}", text))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => s.push(self.unicode_escape()?),
+                    _ => { self.index -= 1; return Err("Unknown escape in a string".to_string()); },
+                },
+                Some(c) if (c as u32) < 0x20 => {
+                    self.index -= 1;
+                    return Err("Control character in a string".to_string());
+                },
+                Some(c) => s.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    /// The character of a \u escape, which may be a surrogate pair.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let first = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if self.next() != Some('\\') || self.next() != Some('u') {
+                return Err("Expected the low surrogate of a pair".to_string());
+            }
+            let second = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err("Expected the low surrogate of a pair".to_string());
+            }
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+        std::char::from_u32(code).ok_or(format!("Invalid character \\u{:04x}", code))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.next().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err("Expected four hexadecimal digits".to_string()),
+            }
+        }
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_parse() {
+        let value = Json::object(vec![
+            ("name", Json::string("a \"b\"\n")),
+            ("values", Json::Array(vec![Json::number(-12), Json::Number("2.5e-7".to_string()), Json::Null, Json::Bool(true)])),
+            ("empty", Json::Object(Vec::new())),
+        ]);
+        let text = value.to_string();
+        assert_eq!(text, "{\"name\":\"a \\\"b\\\"\\n\",\"values\":[-12,2.5e-7,null,true],\"empty\":{}}");
+        assert_eq!(parse(&text), Ok(value));
+        assert_eq!(
+            parse(" [ 123456789012345678901 , \"\\u00e4\\ud83d\\ude00\" ]\n"),
+            Ok(Json::Array(vec![Json::Number("123456789012345678901".to_string()), Json::string("ä😀")])),
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("{\"a\": 1,}"), Err("1:9: Expected the name of a field".to_string()));
+        assert_eq!(parse("[1,\n 2 3]"), Err("2:4: Expected , or ] after an element".to_string()));
+        assert_eq!(parse("[01.]"), Err("1:2: Malformed number 01.".to_string()));
+        assert_eq!(parse("\"abc"), Err("1:5: Unterminated string".to_string()));
+        assert_eq!(parse("nul"), Err("1:1: Unexpected nul".to_string()));
+        assert_eq!(parse("1 2"), Err("1:3: Unexpected 2 after the value".to_string()));
+    }
+}
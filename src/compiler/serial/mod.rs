@@ -0,0 +1,652 @@
+//! Serialisation of the tokens and the AST of a program, for tools written in other languages.
+//! Both can be written as JSON and as S-expressions, and an AST can be read back from JSON, so
+//! a tool can generate a program and run it without writing Mini-PL source.
+//!
+//! # JSON
+//!
+//! An AST is an object `{"format": "plint-ast", "version": 1, "statements": [...]}`. The
+//! version changes only when the format changes incompatibly. Reading an AST, the format and
+//! the version may be left out, and so may the positions and the types, which are ignored.
+//!
+//! A statement is an object with a `kind`, the position of its first token as `line` and
+//! `column`, and the position of its last token as `end_line` and `end_column`. The other
+//! fields depend on the kind. Optional values are null when they are not given:
+//!
+//! | kind | fields |
+//! |------|--------|
+//! | `declaration` | `identifier`, `type`, `value` (optional expression) |
+//! | `constant` | `identifier`, `type`, `value` |
+//! | `assignment` | `identifier`, `index` (optional, for an array element), `value` |
+//! | `read` | `identifier`, `index` (optional) |
+//! | `for` | `identifier`, `begin`, `end`, `descending` (true with downto), `step` (optional), `body` |
+//! | `if` | `condition`, `then`, `else` (arrays of statements) |
+//! | `while` | `condition`, `body` |
+//! | `procedure` | `identifier`, `parameters`, `body` |
+//! | `function` | `identifier`, `parameters`, `return_type`, `body` |
+//! | `call` | `identifier`, `arguments` (array of expressions) |
+//! | `return` | `value` (optional) |
+//! | `print`, `assert` | `value` |
+//! | `break`, `continue`, `empty` | |
+//!
+//! Identifiers are written as in the source, so they cannot be reserved words such as `end`.
+//! A parameter is `{"identifier": "n", "type": "int"}`. A type is one of the strings `"int"`,
+//! `"real"`, `"string"` and `"bool"`, or an array `{"kind": "array", "size": 3, "element":
+//! "int"}`.
+//!
+//! An expression or operand is an object with a `kind` and its static `type`:
+//!
+//! | kind | fields |
+//! |------|--------|
+//! | `binary` | `operator`, `left`, `right` (operands) |
+//! | `unary` | `operator`, `operand` |
+//! | `int`, `real`, `string`, `bool` | `value` |
+//! | `identifier`, `size` | `identifier` |
+//! | `index` | `identifier`, `index` |
+//! | `call` | `identifier`, `arguments` |
+//! | `group` | `expression`, an expression in parentheses |
+//!
+//! An expression is a binary or unary expression, or a single operand. An operand is any kind
+//! but binary and unary, which must be put in a group. Operators are written as in the source,
+//! such as `"+"` and `"<>"`. Integers are numbers, and may be larger than 32 bits only with
+//! --bigint. Reals are numbers, or the strings `"inf"`, `"-inf"` and `"NaN"`.
+//!
+//! The tokens are an object `{"format": "plint-tokens", "version": 1, "tokens": [...]}`. A token
+//! has a `kind`, its `line` and `column`, and either the `text` of a `keyword`, `identifier`,
+//! `operator` or `symbol` such as `":="`, or the `value` of an `int`, `real`, `string` or `bool`.
+//!
+//! # S-expressions
+//!
+//! The S-expressions are a compact form of the same trees for reading. A statement is a list of
+//! its kind, its span such as `1:1-1:17`, and its fields in the order of the tables above, such
+//! as `(declaration 1:1-1:17 n int (the int 3))`. Each expression is written with its type as
+//! `(the int (+ n 1))`. Operands are atoms for literals and identifiers, and lists such as
+//! `(index a (the int i))`, `(size a)` and `(call f (the int n))`. Blocks are the lists
+//! `(do ...)`, `(then ...)` and `(else ...)`, and a for loop is written
+//! `(for SPAN i BEGIN to END (step STEP) (do ...))`, with `downto` for a descending loop and the
+//! step left out if it is not given. A token is a list of its kind, its position and its text
+//! or value, such as `(keyword 1:1 var)` and `(symbol 1:7 ":=")`.
+
+pub mod json;
+mod sexpr;
+
+pub use self::sexpr::{ast_to_sexpr, tokens_to_sexpr};
+
+use self::json::Json;
+use super::checker::Types;
+use super::parser::ast::*;
+use super::scanner;
+use super::scanner::token::Token;
+
+/// The version of the JSON format.
+const VERSION: usize = 1;
+
+/// The AST as JSON, with the types of its expressions.
+pub fn ast_to_json(ast: &Ast, types: &Types) -> Json {
+    Json::object(vec![
+        ("format", Json::string("plint-ast")),
+        ("version", Json::number(VERSION)),
+        ("statements", statements_json(&ast.statements, types)),
+    ])
+}
+
+/// The tokens as JSON.
+pub fn tokens_to_json(tokens: &[(Token, Position)]) -> Json {
+    let tokens = tokens.iter().map(|&(ref token, position)| {
+        let content = match *token {
+            Token::Reserved(ref keyword) => ("text", Json::string(&keyword.to_string())),
+            Token::Identifier(ref identifier) => ("text", Json::string(identifier)),
+            Token::Operator(operator) => ("text", Json::string(&operator.to_string())),
+            Token::Int(i) => ("value", Json::number(i)),
+            Token::BigInt(ref i) => ("value", Json::number(i)),
+            Token::Real(r) => ("value", real_json(r)),
+            Token::String(ref s) => ("value", Json::string(s)),
+            Token::Bool(b) => ("value", Json::Bool(b)),
+            ref token => ("text", Json::string(symbol_text(token))),
+        };
+        Json::object(vec![
+            ("kind", Json::string(token_kind(token))),
+            ("line", Json::number(position.line)),
+            ("column", Json::number(position.column)),
+            content,
+        ])
+    });
+    Json::object(vec![
+        ("format", Json::string("plint-tokens")),
+        ("version", Json::number(VERSION)),
+        ("tokens", Json::Array(tokens.collect())),
+    ])
+}
+
+/// The kind of a token in the serialised forms.
+fn token_kind(token: &Token) -> &'static str {
+    match *token {
+        Token::Reserved(_) => "keyword",
+        Token::Identifier(_) => "identifier",
+        Token::Int(_) | Token::BigInt(_) => "int",
+        Token::Real(_) => "real",
+        Token::String(_) => "string",
+        Token::Bool(_) => "bool",
+        Token::Operator(_) => "operator",
+        _ => "symbol",
+    }
+}
+
+/// The text of a token that is a symbol.
+fn symbol_text(token: &Token) -> &'static str {
+    match *token {
+        Token::OpenParen => "(",
+        Token::CloseParen => ")",
+        Token::OpenBracket => "[",
+        Token::CloseBracket => "]",
+        Token::Assignment => ":=",
+        Token::TypeDecl => ":",
+        Token::Range => "..",
+        Token::Comma => ",",
+        Token::Dot => ".",
+        Token::EndStatement => ";",
+        _ => unreachable!("only symbols have a symbol text"),
+    }
+}
+
+fn statements_json(statements: &[Located<Statement>], types: &Types) -> Json {
+    Json::Array(statements.iter().map(|statement| statement_json(statement, types)).collect())
+}
+
+fn statement_json(statement: &Located<Statement>, types: &Types) -> Json {
+    let expression = |expr: &Expression| expression_json(expr, types);
+    let optional = |expr: &Option<Expression>| expr.as_ref().map(expression).unwrap_or(Json::Null);
+    let (kind, fields) = match statement.node {
+        Statement::Declaration { ref identifier, ref mpl_type, ref value } => ("declaration", vec![
+            ("identifier", Json::string(identifier)),
+            ("type", type_json(mpl_type)),
+            ("value", optional(value)),
+        ]),
+        Statement::Constant { ref identifier, ref mpl_type, ref value } => ("constant", vec![
+            ("identifier", Json::string(identifier)),
+            ("type", type_json(mpl_type)),
+            ("value", expression(value)),
+        ]),
+        Statement::Assignment { ref identifier, ref index, expression: ref value } => ("assignment", vec![
+            ("identifier", Json::string(identifier)),
+            ("index", optional(index)),
+            ("value", expression(value)),
+        ]),
+        Statement::Read { ref identifier, ref index } => ("read", vec![
+            ("identifier", Json::string(identifier)),
+            ("index", optional(index)),
+        ]),
+        Statement::For { ref identifier, ref begin, ref end, descending, ref step, ref statements } => ("for", vec![
+            ("identifier", Json::string(identifier)),
+            ("begin", expression(begin)),
+            ("end", expression(end)),
+            ("descending", Json::Bool(descending)),
+            ("step", optional(step)),
+            ("body", statements_json(statements, types)),
+        ]),
+        Statement::If { ref condition, ref statements, ref else_statements } => ("if", vec![
+            ("condition", expression(condition)),
+            ("then", statements_json(statements, types)),
+            ("else", statements_json(else_statements, types)),
+        ]),
+        Statement::While { ref condition, ref statements } => ("while", vec![
+            ("condition", expression(condition)),
+            ("body", statements_json(statements, types)),
+        ]),
+        Statement::Function(ref function) => {
+            let parameters = function.parameters.iter()
+                .map(|parameter| Json::object(vec![
+                    ("identifier", Json::string(&parameter.identifier)),
+                    ("type", type_json(&parameter.mpl_type)),
+                ]))
+                .collect();
+            let mut fields = vec![
+                ("identifier", Json::string(&function.identifier)),
+                ("parameters", Json::Array(parameters)),
+            ];
+            if let Some(ref return_type) = function.return_type {
+                fields.push(("return_type", type_json(return_type)));
+            }
+            fields.push(("body", statements_json(&function.statements, types)));
+            (if function.return_type.is_some() { "function" } else { "procedure" }, fields)
+        },
+        Statement::Call { ref identifier, ref arguments } => ("call", vec![
+            ("identifier", Json::string(identifier)),
+            ("arguments", Json::Array(arguments.iter().map(expression).collect())),
+        ]),
+        Statement::Return(ref value) => ("return", vec![("value", optional(value))]),
+        Statement::Print(ref value) => ("print", vec![("value", expression(value))]),
+        Statement::Assert(ref value) => ("assert", vec![("value", expression(value))]),
+        Statement::Break => ("break", Vec::new()),
+        Statement::Continue => ("continue", Vec::new()),
+        Statement::Empty => ("empty", Vec::new()),
+    };
+    let mut object = vec![
+        ("kind", Json::string(kind)),
+        ("line", Json::number(statement.position.line)),
+        ("column", Json::number(statement.position.column)),
+        ("end_line", Json::number(statement.end.line)),
+        ("end_column", Json::number(statement.end.column)),
+    ];
+    object.extend(fields);
+    Json::object(object)
+}
+
+fn type_json(mpl_type: &MplType) -> Json {
+    match *mpl_type {
+        MplType::Int => Json::string("int"),
+        MplType::Real => Json::string("real"),
+        MplType::String => Json::string("string"),
+        MplType::Bool => Json::string("bool"),
+        MplType::Array { ref element, size } => Json::object(vec![
+            ("kind", Json::string("array")),
+            ("size", Json::number(size)),
+            ("element", type_json(element)),
+        ]),
+    }
+}
+
+fn expression_json(expr: &Expression, types: &Types) -> Json {
+    let fields = match *expr {
+        Expression::Simple(ref operand) => return operand_json(operand, types),
+        Expression::Binary { ref left, operator, ref right } => vec![
+            ("kind", Json::string("binary")),
+            ("operator", Json::string(&operator.to_string())),
+            ("left", operand_json(left, types)),
+            ("right", operand_json(right, types)),
+        ],
+        Expression::Unary { operator, ref operand } => vec![
+            ("kind", Json::string("unary")),
+            ("operator", Json::string(&operator.to_string())),
+            ("operand", operand_json(operand, types)),
+        ],
+    };
+    typed(fields, types.expression(expr))
+}
+
+fn operand_json(operand: &Operand, types: &Types) -> Json {
+    let fields = match *operand {
+        Operand::Int(i) => vec![("kind", Json::string("int")), ("value", Json::number(i))],
+        Operand::BigInt(ref i) => vec![("kind", Json::string("int")), ("value", Json::number(i))],
+        Operand::Real(r) => vec![("kind", Json::string("real")), ("value", real_json(r))],
+        Operand::String(ref s) => vec![("kind", Json::string("string")), ("value", Json::string(s))],
+        Operand::Bool(b) => vec![("kind", Json::string("bool")), ("value", Json::Bool(b))],
+        Operand::Identifier(ref identifier)
+            => vec![("kind", Json::string("identifier")), ("identifier", Json::string(identifier))],
+        Operand::Size(ref identifier)
+            => vec![("kind", Json::string("size")), ("identifier", Json::string(identifier))],
+        Operand::Index { ref identifier, ref index } => vec![
+            ("kind", Json::string("index")),
+            ("identifier", Json::string(identifier)),
+            ("index", expression_json(index, types)),
+        ],
+        Operand::Call { ref identifier, ref arguments } => vec![
+            ("kind", Json::string("call")),
+            ("identifier", Json::string(identifier)),
+            ("arguments", Json::Array(arguments.iter().map(|argument| expression_json(argument, types)).collect())),
+        ],
+        Operand::Expr(ref expr) => vec![("kind", Json::string("group")), ("expression", expression_json(expr, types))],
+    };
+    typed(fields, types.operand(operand))
+}
+
+/// An object of the fields, followed by the type if it is known.
+fn typed(mut fields: Vec<(&str, Json)>, mpl_type: Option<&MplType>) -> Json {
+    if let Some(mpl_type) = mpl_type {
+        fields.push(("type", type_json(mpl_type)));
+    }
+    Json::object(fields)
+}
+
+/// A real as a JSON number, or as a string if JSON has no number for it.
+fn real_json(r: f64) -> Json {
+    if r.is_finite() {
+        Json::Number(format_real(r))
+    } else {
+        Json::String(format_real(r))
+    }
+}
+
+/// Read an AST from JSON text. The error tells where in the text or in the tree it was found.
+/// Integers may be larger than 32 bits only if `bigint` is set.
+pub fn ast_from_json(text: &str, bigint: bool) -> Result<Ast, String> {
+    let json = json::parse(text)?;
+    let reader = Reader { bigint };
+    if let Some(format) = json.get("format") {
+        if *format != Json::string("plint-ast") {
+            return Err(format!("Expected the format plint-ast, got {}", format));
+        }
+    }
+    if let Some(version) = json.get("version") {
+        if *version != Json::number(VERSION) {
+            return Err(format!("Unsupported version {}, expected {}", version, VERSION));
+        }
+    }
+    let statements = reader.statements(field(&json, "statements", "")?, "statements")?;
+    Ok(Ast { statements })
+}
+
+/// Reads the nodes of an AST. Each method gets the path of the node in the tree, such as
+/// `statements[2].value`, to tell where an error was found.
+struct Reader {
+    bigint: bool,
+}
+
+impl Reader {
+    fn statements(&self, json: &Json, path: &str) -> Result<Vec<Located<Statement>>, String> {
+        array(json, path)?.iter().enumerate()
+            .map(|(i, statement)| self.statement(statement, &format!("{}[{}]", path, i)))
+            .collect()
+    }
+
+    fn statement(&self, json: &Json, path: &str) -> Result<Located<Statement>, String> {
+        let expression = |name: &str| self.expression(field(json, name, path)?, &join(path, name));
+        let optional = |name: &str| match json.get(name) {
+            None | Some(&Json::Null) => Ok(None),
+            Some(value) => self.expression(value, &join(path, name)).map(Some),
+        };
+        let identifier = || identifier_field(json, "identifier", path);
+        let block = |name: &str| self.statements(field(json, name, path)?, &join(path, name));
+        let node = match string_field(json, "kind", path)?.as_str() {
+            "declaration" => Statement::Declaration {
+                identifier: identifier()?,
+                mpl_type: mpl_type(field(json, "type", path)?, &join(path, "type"))?,
+                value: optional("value")?,
+            },
+            "constant" => Statement::Constant {
+                identifier: identifier()?,
+                mpl_type: mpl_type(field(json, "type", path)?, &join(path, "type"))?,
+                value: expression("value")?,
+            },
+            "assignment" => Statement::Assignment {
+                identifier: identifier()?,
+                index: optional("index")?,
+                expression: expression("value")?,
+            },
+            "read" => Statement::Read { identifier: identifier()?, index: optional("index")? },
+            "for" => Statement::For {
+                identifier: identifier()?,
+                begin: expression("begin")?,
+                end: expression("end")?,
+                descending: match json.get("descending") {
+                    None | Some(&Json::Null) => false,
+                    Some(&Json::Bool(descending)) => descending,
+                    Some(value) => return Err(expected(&join(path, "descending"), "a boolean", value)),
+                },
+                step: optional("step")?,
+                statements: block("body")?,
+            },
+            "if" => Statement::If {
+                condition: expression("condition")?,
+                statements: block("then")?,
+                else_statements: match json.get("else") {
+                    None | Some(&Json::Null) => Vec::new(),
+                    Some(_) => block("else")?,
+                },
+            },
+            "while" => Statement::While { condition: expression("condition")?, statements: block("body")? },
+            kind @ "procedure" | kind @ "function" => {
+                let parameters = array(field(json, "parameters", path)?, &join(path, "parameters"))?.iter().enumerate()
+                    .map(|(i, parameter)| {
+                        let path = format!("{}.parameters[{}]", path, i);
+                        Ok(Parameter {
+                            identifier: identifier_field(parameter, "identifier", &path)?,
+                            mpl_type: mpl_type(field(parameter, "type", &path)?, &join(&path, "type"))?,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?;
+                let return_type = match kind {
+                    "function" => Some(mpl_type(field(json, "return_type", path)?, &join(path, "return_type"))?),
+                    _ => None,
+                };
+                Statement::Function(Function { identifier: identifier()?, parameters, return_type, statements: block("body")? })
+            },
+            "call" => Statement::Call { identifier: identifier()?, arguments: self.expressions(json, "arguments", path)? },
+            "return" => Statement::Return(optional("value")?),
+            "print" => Statement::Print(expression("value")?),
+            "assert" => Statement::Assert(expression("value")?),
+            "break" => Statement::Break,
+            "continue" => Statement::Continue,
+            "empty" => Statement::Empty,
+            kind => return Err(format!("{}: Unknown statement kind {}", path, kind)),
+        };
+        Ok(Located {
+            node,
+            position: position(json, "line", "column", path)?,
+            end: position(json, "end_line", "end_column", path)?,
+        })
+    }
+
+    fn expressions(&self, json: &Json, name: &str, path: &str) -> Result<Vec<Expression>, String> {
+        let path = join(path, name);
+        array(field(json, name, &path)?, &path)?.iter().enumerate()
+            .map(|(i, argument)| self.expression(argument, &format!("{}[{}]", path, i)))
+            .collect()
+    }
+
+    fn expression(&self, json: &Json, path: &str) -> Result<Expression, String> {
+        let operand = |name: &str| self.operand(field(json, name, path)?, &join(path, name));
+        let operator = || {
+            let operator = string_field(json, "operator", path)?;
+            Operator::from_text(&operator).ok_or(format!("{}.operator: Unknown operator {}", path, operator))
+        };
+        match string_field(json, "kind", path)?.as_str() {
+            "binary" => Ok(Expression::Binary { left: operand("left")?, operator: operator()?, right: operand("right")? }),
+            "unary" => Ok(Expression::Unary { operator: operator()?, operand: operand("operand")? }),
+            _ => self.operand(json, path).map(Expression::Simple),
+        }
+    }
+
+    fn operand(&self, json: &Json, path: &str) -> Result<Operand, String> {
+        let identifier = || identifier_field(json, "identifier", path);
+        let value = || field(json, "value", path);
+        match string_field(json, "kind", path)?.as_str() {
+            "int" => match *value()? {
+                Json::Number(ref n) => match n.parse() {
+                    Ok(i) => Ok(Operand::Int(i)),
+                    Err(_) => match BigInt::parse(n) {
+                        Some(i) if self.bigint => Ok(Operand::BigInt(i)),
+                        Some(_) => Err(format!("{}.value: Integer {} does not fit in 32 bits without --bigint", path, n)),
+                        None => Err(format!("{}.value: Expected an integer, got {}", path, n)),
+                    },
+                },
+                ref value => Err(expected(&join(path, "value"), "an integer", value)),
+            },
+            "real" => match *value()? {
+                Json::Number(ref n) => n.parse().map(Operand::Real)
+                    .map_err(|_| format!("{}.value: Expected a real, got {}", path, n)),
+                Json::String(ref s) if s == "inf" => Ok(Operand::Real(f64::INFINITY)),
+                Json::String(ref s) if s == "-inf" => Ok(Operand::Real(f64::NEG_INFINITY)),
+                Json::String(ref s) if s == "NaN" => Ok(Operand::Real(f64::NAN)),
+                ref value => Err(expected(&join(path, "value"), "a real", value)),
+            },
+            "string" => string_field(json, "value", path).map(Operand::String),
+            "bool" => match *value()? {
+                Json::Bool(b) => Ok(Operand::Bool(b)),
+                ref value => Err(expected(&join(path, "value"), "a boolean", value)),
+            },
+            "identifier" => identifier().map(Operand::Identifier),
+            "size" => identifier().map(Operand::Size),
+            "index" => Ok(Operand::Index {
+                identifier: identifier()?,
+                index: Box::new(self.expression(field(json, "index", path)?, &join(path, "index"))?),
+            }),
+            "call" => Ok(Operand::Call { identifier: identifier()?, arguments: self.expressions(json, "arguments", path)? }),
+            "group" => {
+                let expr = self.expression(field(json, "expression", path)?, &join(path, "expression"))?;
+                Ok(Operand::Expr(Box::new(expr)))
+            },
+            kind @ "binary" | kind @ "unary" => Err(format!("{}: A {} expression must be in a group to be an operand", path, kind)),
+            kind => Err(format!("{}: Unknown expression kind {}", path, kind)),
+        }
+    }
+}
+
+fn mpl_type(json: &Json, path: &str) -> Result<MplType, String> {
+    match *json {
+        Json::String(ref name) => match name.as_str() {
+            "int" => Ok(MplType::Int),
+            "real" => Ok(MplType::Real),
+            "string" => Ok(MplType::String),
+            "bool" => Ok(MplType::Bool),
+            _ => Err(format!("{}: Unknown type {}", path, name)),
+        },
+        Json::Object(_) if json.get("kind") == Some(&Json::string("array")) => {
+            let size = match *field(json, "size", path)? {
+                Json::Number(ref n) => n.parse().map_err(|_| format!("{}.size: Expected a size, got {}", path, n))?,
+                ref value => return Err(expected(&join(path, "size"), "a size", value)),
+            };
+            let element = mpl_type(field(json, "element", path)?, &join(path, "element"))?;
+            if let MplType::Array { .. } = element {
+                return Err(format!("{}.element: The elements of an array cannot be arrays", path));
+            }
+            Ok(MplType::Array { element: Box::new(element), size })
+        },
+        ref value => Err(expected(path, "a type", value)),
+    }
+}
+
+/// The position in the fields for its line and column, or the default position if they are
+/// not given.
+fn position(json: &Json, line: &str, column: &str, path: &str) -> Result<Position, String> {
+    let number = |name: &str| match json.get(name) {
+        None | Some(&Json::Null) => Ok(0),
+        Some(Json::Number(n)) => n.parse().map_err(|_| format!("{}: Expected a line or column, got {}", join(path, name), n)),
+        Some(value) => Err(expected(&join(path, name), "a number", value)),
+    };
+    Ok(Position::new(number(line)?, number(column)?))
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn expected(path: &str, what: &str, value: &Json) -> String {
+    format!("{}: Expected {}, got {}", path, what, value.kind())
+}
+
+fn field<'a>(json: &'a Json, name: &str, path: &str) -> Result<&'a Json, String> {
+    match *json {
+        Json::Object(_) => json.get(name).ok_or(format!("{}: Missing field {}", if path.is_empty() { "program" } else { path }, name)),
+        ref value => Err(expected(if path.is_empty() { "program" } else { path }, "an object", value)),
+    }
+}
+
+fn string_field(json: &Json, name: &str, path: &str) -> Result<String, String> {
+    match *field(json, name, path)? {
+        Json::String(ref s) => Ok(s.clone()),
+        ref value => Err(expected(&join(path, name), "a string", value)),
+    }
+}
+
+/// A string field that the scanner would scan as a single identifier, so that it is not a
+/// reserved word and the AST can be printed as source.
+fn identifier_field(json: &Json, name: &str, path: &str) -> Result<String, String> {
+    let identifier = string_field(json, name, path)?;
+    // A source must not end in the middle of a word, so the identifier is followed by a space.
+    match scanner::scan(&format!("{} ", identifier), false) {
+        Ok(ref tokens) if tokens.len() == 1 && tokens[0].0 == Token::Identifier(identifier.clone()) => Ok(identifier),
+        _ => Err(format!("{}: {:?} is not an identifier", join(path, name), identifier)),
+    }
+}
+
+fn array<'a>(json: &'a Json, path: &str) -> Result<&'a [Json], String> {
+    match *json {
+        Json::Array(ref elements) => Ok(elements),
+        ref value => Err(expected(path, "an array", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{checker, parser, scanner};
+
+    fn parse_source(source: &str) -> Ast {
+        parser::parse(scanner::scan(source, false).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn ast_to_json_and_back() {
+        let source = "var i : int := 1;\nvar a : array [2] of real;\nfor i in 1 downto 0 step 1 do a[i] := 1.5 * (2.0 + 1.0); end for;\n\
+                      function f(n : int) : string do if !(n < 0) then return \"\\\"\"; end if; return \"b\"; end function;\n\
+                      print f(a.size) + \"\\n\";\nread i;\n";
+        let ast = parse_source(source);
+        let json = ast_to_json(&ast, &checker::types(&ast).unwrap()).to_string();
+        assert!(json.starts_with("{\"format\":\"plint-ast\",\"version\":1,\"statements\":[{\"kind\":\"declaration\",\
+                                  \"line\":1,\"column\":1,\"end_line\":1,\"end_column\":17,\"identifier\":\"i\",\"type\":\"int\",\
+                                  \"value\":{\"kind\":\"int\",\"value\":1,\"type\":\"int\"}},{\"kind\":\"declaration\",\
+                                  \"line\":2,\"column\":1,\"end_line\":2,\"end_column\":26,\"identifier\":\"a\",\
+                                  \"type\":{\"kind\":\"array\",\"size\":2,\"element\":\"real\"},\"value\":null}"));
+        assert!(json.contains("{\"kind\":\"binary\",\"operator\":\"*\",\"left\":{\"kind\":\"real\",\"value\":1.5,\"type\":\"real\"},\
+                               \"right\":{\"kind\":\"group\",\"expression\":{\"kind\":\"binary\",\"operator\":\"+\",\
+                               \"left\":{\"kind\":\"real\",\"value\":2.0,\"type\":\"real\"},\
+                               \"right\":{\"kind\":\"real\",\"value\":1.0,\"type\":\"real\"},\"type\":\"real\"},\"type\":\"real\"},\"type\":\"real\"}"));
+        assert!(json.contains("\"parameters\":[{\"identifier\":\"n\",\"type\":\"int\"}],\"return_type\":\"string\""));
+        let read = ast_from_json(&json, false).unwrap();
+        assert_eq!(read.statements, ast.statements);
+    }
+
+    #[test]
+    fn minimal_json() {
+        let json = "{\"statements\": [{\"kind\": \"print\", \"value\": {\"kind\": \"binary\", \"operator\": \"<>\",\
+                    \"left\": {\"kind\": \"int\", \"value\": 12345678901}, \"right\": {\"kind\": \"int\", \"value\": -1}}}]}";
+        let value = Expression::Binary {
+            left: Operand::BigInt(BigInt::parse("12345678901").unwrap()),
+            operator: Operator::NotEqual,
+            right: Operand::Int(-1),
+        };
+        let ast = ast_from_json(json, true).unwrap();
+        assert_eq!(ast.statements, vec![Located { node: Statement::Print(value), position: Position::default(), end: Position::default() }]);
+        assert_eq!(
+            ast_from_json(json, false).err().unwrap(),
+            "statements[0].value.left.value: Integer 12345678901 does not fit in 32 bits without --bigint",
+        );
+    }
+
+    #[test]
+    fn json_errors() {
+        let read = |json: &str| ast_from_json(json, false).err().unwrap();
+        assert_eq!(read("[]"), "program: Expected an object, got an array");
+        assert_eq!(read("{\"version\": 2, \"statements\": []}"), "Unsupported version 2, expected 1");
+        assert_eq!(read("{\"statements\": [{\"kind\": \"goto\"}]}"), "statements[0]: Unknown statement kind goto");
+        assert_eq!(read("{\"statements\": [{\"kind\": \"print\"}]}"), "statements[0]: Missing field value");
+        assert_eq!(
+            read("{\"statements\": [{\"kind\": \"while\", \"condition\": {\"kind\": \"bool\", \"value\": 1}, \"body\": []}]}"),
+            "statements[0].condition.value: Expected a boolean, got a number",
+        );
+        assert_eq!(
+            read("{\"statements\": [{\"kind\": \"declaration\", \"identifier\": \"a\", \"type\": {\"kind\": \"array\", \"size\": 2, \
+                  \"element\": \"char\"}}]}"),
+            "statements[0].type.element: Unknown type char",
+        );
+        let nested = "{\"statements\": [{\"kind\": \"print\", \"value\": {\"kind\": \"unary\", \"operator\": \"!\", \
+                      \"operand\": {\"kind\": \"unary\", \"operator\": \"!\", \"operand\": {\"kind\": \"bool\", \"value\": true}}}}]}";
+        assert_eq!(read(nested), "statements[0].value.operand: A unary expression must be in a group to be an operand");
+        let declaration = |identifier: &str| read(&format!(
+            "{{\"statements\": [{{\"kind\": \"declaration\", \"identifier\": {:?}, \"type\": \"int\"}}]}}",
+            identifier,
+        ));
+        assert_eq!(declaration("end"), "statements[0].identifier: \"end\" is not an identifier");
+        assert_eq!(declaration("true"), "statements[0].identifier: \"true\" is not an identifier");
+        assert_eq!(declaration("a b"), "statements[0].identifier: \"a b\" is not an identifier");
+        assert_eq!(declaration("1a"), "statements[0].identifier: \"1a\" is not an identifier");
+        assert_eq!(declaration(""), "statements[0].identifier: \"\" is not an identifier");
+        assert_eq!(read("{\"statements\": [}"), "1:17: Unexpected }");
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = scanner::scan("x := \"a\" + 1.0;", false).unwrap();
+        assert_eq!(tokens_to_json(&tokens).to_string(), "{\"format\":\"plint-tokens\",\"version\":1,\"tokens\":[\
+            {\"kind\":\"identifier\",\"line\":1,\"column\":1,\"text\":\"x\"},\
+            {\"kind\":\"symbol\",\"line\":1,\"column\":3,\"text\":\":=\"},\
+            {\"kind\":\"string\",\"line\":1,\"column\":6,\"value\":\"a\"},\
+            {\"kind\":\"operator\",\"line\":1,\"column\":10,\"text\":\"+\"},\
+            {\"kind\":\"real\",\"line\":1,\"column\":12,\"value\":1.0},\
+            {\"kind\":\"symbol\",\"line\":1,\"column\":15,\"text\":\";\"}]}");
+    }
+}
@@ -0,0 +1,257 @@
+//! The S-expression form of the tokens and the AST. The format is described in the parent
+//! module.
+
+use super::json::quote;
+use super::{symbol_text, token_kind};
+use super::super::checker::Types;
+use super::super::parser::ast::*;
+use super::super::scanner::token::Token;
+
+use std::fmt::Write;
+
+/// The AST as S-expressions, with each statement on its own line, indented by its depth.
+pub fn ast_to_sexpr(ast: &Ast, types: &Types) -> String {
+    let mut sexpr = "(program".to_string();
+    Writer { types }.block(&mut sexpr, &ast.statements, 1);
+    sexpr.push_str(")\n");
+    sexpr
+}
+
+/// The tokens as S-expressions, one per line.
+pub fn tokens_to_sexpr(tokens: &[(Token, Position)]) -> String {
+    let mut sexpr = "(tokens".to_string();
+    for &(ref token, position) in tokens {
+        let text = match *token {
+            Token::Reserved(ref keyword) => keyword.to_string(),
+            Token::Identifier(ref identifier) => identifier.clone(),
+            Token::Operator(operator) => operator.to_string(),
+            Token::Int(i) => i.to_string(),
+            Token::BigInt(ref i) => i.to_string(),
+            Token::Real(r) => format_real(r),
+            Token::String(ref s) => quote(s),
+            Token::Bool(b) => b.to_string(),
+            ref token => quote(symbol_text(token)),
+        };
+        write!(sexpr, "\n  ({} {} {})", token_kind(token), position, text).unwrap();
+    }
+    sexpr.push_str(")\n");
+    sexpr
+}
+
+struct Writer<'t, 'a: 't> {
+    types: &'t Types<'a>,
+}
+
+impl<'t, 'a> Writer<'t, 'a> {
+    /// Write the statements, each on a new line at the depth.
+    fn block(&self, sexpr: &mut String, statements: &[Located<Statement>], depth: usize) {
+        for statement in statements {
+            write!(sexpr, "\n{:width$}", "", width = 2 * depth).unwrap();
+            self.statement(sexpr, statement, depth);
+        }
+    }
+
+    /// Write a block as a list starting with the name, on a new line at the depth.
+    fn named_block(&self, sexpr: &mut String, name: &str, statements: &[Located<Statement>], depth: usize) {
+        write!(sexpr, "\n{:width$}({}", "", name, width = 2 * depth).unwrap();
+        self.block(sexpr, statements, depth + 1);
+        sexpr.push(')');
+    }
+
+    fn statement(&self, sexpr: &mut String, statement: &Located<Statement>, depth: usize) {
+        let kind = match statement.node {
+            Statement::Declaration { .. } => "declaration",
+            Statement::Constant { .. } => "constant",
+            Statement::Assignment { .. } => "assignment",
+            Statement::Read { .. } => "read",
+            Statement::For { .. } => "for",
+            Statement::If { .. } => "if",
+            Statement::While { .. } => "while",
+            Statement::Function(ref function) if function.return_type.is_some() => "function",
+            Statement::Function(_) => "procedure",
+            Statement::Call { .. } => "call",
+            Statement::Return(_) => "return",
+            Statement::Print(_) => "print",
+            Statement::Assert(_) => "assert",
+            Statement::Break => "break",
+            Statement::Continue => "continue",
+            Statement::Empty => "empty",
+        };
+        write!(sexpr, "({} {}-{}", kind, statement.position, statement.end).unwrap();
+        match statement.node {
+            Statement::Declaration { ref identifier, ref mpl_type, ref value } => {
+                write!(sexpr, " {} {}", identifier, type_sexpr(mpl_type)).unwrap();
+                if let Some(ref value) = *value {
+                    self.expression(sexpr, value);
+                }
+            },
+            Statement::Constant { ref identifier, ref mpl_type, ref value } => {
+                write!(sexpr, " {} {}", identifier, type_sexpr(mpl_type)).unwrap();
+                self.expression(sexpr, value);
+            },
+            Statement::Assignment { ref identifier, ref index, ref expression } => {
+                self.target(sexpr, identifier, index.as_ref());
+                self.expression(sexpr, expression);
+            },
+            Statement::Read { ref identifier, ref index } => self.target(sexpr, identifier, index.as_ref()),
+            Statement::For { ref identifier, ref begin, ref end, descending, ref step, ref statements } => {
+                write!(sexpr, " {}", identifier).unwrap();
+                self.expression(sexpr, begin);
+                sexpr.push_str(if descending { " downto" } else { " to" });
+                self.expression(sexpr, end);
+                if let Some(ref step) = *step {
+                    sexpr.push_str(" (step");
+                    self.expression(sexpr, step);
+                    sexpr.push(')');
+                }
+                self.named_block(sexpr, "do", statements, depth + 1);
+            },
+            Statement::If { ref condition, ref statements, ref else_statements } => {
+                self.expression(sexpr, condition);
+                self.named_block(sexpr, "then", statements, depth + 1);
+                if !else_statements.is_empty() {
+                    self.named_block(sexpr, "else", else_statements, depth + 1);
+                }
+            },
+            Statement::While { ref condition, ref statements } => {
+                self.expression(sexpr, condition);
+                self.named_block(sexpr, "do", statements, depth + 1);
+            },
+            Statement::Function(ref function) => {
+                let parameters: Vec<String> = function.parameters.iter()
+                    .map(|parameter| format!("({} {})", parameter.identifier, type_sexpr(&parameter.mpl_type)))
+                    .collect();
+                write!(sexpr, " {} ({})", function.identifier, parameters.join(" ")).unwrap();
+                if let Some(ref return_type) = function.return_type {
+                    write!(sexpr, " {}", type_sexpr(return_type)).unwrap();
+                }
+                self.named_block(sexpr, "do", &function.statements, depth + 1);
+            },
+            Statement::Call { ref identifier, ref arguments } => {
+                write!(sexpr, " {}", identifier).unwrap();
+                for argument in arguments {
+                    self.expression(sexpr, argument);
+                }
+            },
+            Statement::Return(Some(ref value)) | Statement::Print(ref value) | Statement::Assert(ref value)
+                => self.expression(sexpr, value),
+            Statement::Return(None) | Statement::Break | Statement::Continue | Statement::Empty => (),
+        }
+        sexpr.push(')');
+    }
+
+    /// Write the variable or array element assigned or read.
+    fn target(&self, sexpr: &mut String, identifier: &str, index: Option<&Expression>) {
+        match index {
+            Some(index) => {
+                write!(sexpr, " (index {}", identifier).unwrap();
+                self.expression(sexpr, index);
+                sexpr.push(')');
+            },
+            None => write!(sexpr, " {}", identifier).unwrap(),
+        }
+    }
+
+    /// Write a space and the expression.
+    fn expression(&self, sexpr: &mut String, expr: &Expression) {
+        sexpr.push(' ');
+        self.typed_expression(sexpr, expr);
+    }
+
+    /// Write the expression, with its type if it is known.
+    fn typed_expression(&self, sexpr: &mut String, expr: &Expression) {
+        let mpl_type = self.types.expression(expr);
+        if let Some(mpl_type) = mpl_type {
+            write!(sexpr, "(the {} ", type_sexpr(mpl_type)).unwrap();
+        }
+        match *expr {
+            Expression::Simple(ref operand) => self.operand(sexpr, operand),
+            Expression::Binary { ref left, operator, ref right } => {
+                write!(sexpr, "({} ", operator).unwrap();
+                self.operand(sexpr, left);
+                sexpr.push(' ');
+                self.operand(sexpr, right);
+                sexpr.push(')');
+            },
+            Expression::Unary { operator, ref operand } => {
+                write!(sexpr, "({} ", operator).unwrap();
+                self.operand(sexpr, operand);
+                sexpr.push(')');
+            },
+        }
+        if mpl_type.is_some() {
+            sexpr.push(')');
+        }
+    }
+
+    fn operand(&self, sexpr: &mut String, operand: &Operand) {
+        match *operand {
+            Operand::Int(i) => write!(sexpr, "{}", i).unwrap(),
+            Operand::BigInt(ref i) => write!(sexpr, "{}", i).unwrap(),
+            Operand::Real(r) => sexpr.push_str(&format_real(r)),
+            Operand::String(ref s) => sexpr.push_str(&quote(s)),
+            Operand::Bool(b) => write!(sexpr, "{}", b).unwrap(),
+            Operand::Identifier(ref identifier) => sexpr.push_str(identifier),
+            Operand::Index { ref identifier, ref index } => {
+                write!(sexpr, "(index {}", identifier).unwrap();
+                self.expression(sexpr, index);
+                sexpr.push(')');
+            },
+            Operand::Size(ref identifier) => write!(sexpr, "(size {})", identifier).unwrap(),
+            Operand::Call { ref identifier, ref arguments } => {
+                write!(sexpr, "(call {}", identifier).unwrap();
+                for argument in arguments {
+                    self.expression(sexpr, argument);
+                }
+                sexpr.push(')');
+            },
+            Operand::Expr(ref expr) => self.typed_expression(sexpr, expr),
+        }
+    }
+}
+
+fn type_sexpr(mpl_type: &MplType) -> String {
+    match *mpl_type {
+        MplType::Int => "int".to_string(),
+        MplType::Real => "real".to_string(),
+        MplType::String => "string".to_string(),
+        MplType::Bool => "bool".to_string(),
+        MplType::Array { ref element, size } => format!("(array {} {})", size, type_sexpr(element)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::{checker, parser, scanner};
+
+    #[test]
+    fn ast_and_tokens() {
+        let source = "var i : int;\nvar a : array [2] of int;\nfor i in 0..1 do\n  a[i] := (i * 2) + 1;\nend for;\n\
+                      if a.size > 1 then print \"n=\" + \"1\"; end if;\nprocedure p() do return; end procedure;\n";
+        let ast = parser::parse(scanner::scan(source, false).unwrap()).unwrap();
+        assert_eq!(ast_to_sexpr(&ast, &checker::types(&ast).unwrap()), "\
+(program
+  (declaration 1:1-1:12 i int)
+  (declaration 2:1-2:25 a (array 2 int))
+  (for 3:1-5:8 i (the int 0) to (the int 1)
+    (do
+      (assignment 4:3-4:22 (index a (the int i)) (the int (+ (the int (* i 2)) 1)))))
+  (if 6:1-6:44 (the bool (> (size a) 1))
+    (then
+      (print 6:20-6:36 (the string (+ \"n=\" \"1\")))))
+  (procedure 7:1-7:39 p ()
+    (do
+      (return 7:18-7:24))))
+");
+        let tokens = scanner::scan("x := !true;", false).unwrap();
+        assert_eq!(tokens_to_sexpr(&tokens), "\
+(tokens
+  (identifier 1:1 x)
+  (symbol 1:3 \":=\")
+  (operator 1:6 !)
+  (bool 1:7 true)
+  (symbol 1:11 \";\"))
+");
+    }
+}
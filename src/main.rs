@@ -4,22 +4,127 @@ use std::io::prelude::*;
 
 mod compiler;
 
-/// CLI of the interpreter. Usage: plint filename
-fn main() {
-    let args: Vec<String> = env::args().collect();
+const USAGE: &str = "\
+Usage: ./plint [run] [options] filename
+       ./plint debug filename
+       ./plint lint [options] filename
+       ./plint cfg [--dot] filename
+       ./plint ast [--dot | --json | --sexp] filename
+       ./plint tokens [--json | --sexp] filename
+       ./plint fmt [--from-json] filename
 
-    if args.len() < 2 {
-        println!("Usage: ./plint filename");
-        return;
-    }
+Options for run:
+  --trace          Write each executed statement and binding change to standard error
+  --trace=json     Write the trace as JSON lines
+  --coverage       Write the source annotated with execution counts to standard error
+  --lcov <path>    Write the coverage as an LCOV tracefile
+  --profile        Write the statements and expressions taking the most time to standard error
+  --profile-folded <path>
+                   Also write the profile as folded stacks for flamegraph tools
+  --record <path>  Record the input lines read by the program to a session log
+  --replay <path>  Feed the input lines recorded in a session log to the program
+  --max-depth <n>  Limit the depth of nested procedure and function calls (default 1000)
+  --bigint         Make integers unbounded, instead of 32 bits where an overflow is an error
+  --no-optimize    Run the program as written, without folding constant expressions
+  --strict-init    Refuse to run a program that may use a variable before it is assigned
+  --from-json      Read the program as an AST in JSON, as written by ast --json
+                   (also for lint, cfg, ast and fmt)
+
+Options for run and lint:
+  --allow <lint>   Do not report the lint
+  --warn <lint>    Report the lint as a warning
+  --deny <lint>    Report the lint as an error, and refuse to run a program with it
+                   The lints are unused-variable, dead-store, overwritten-read,
+                   self-assignment, constant-assert and uninitialized. The levels given
+                   in plint.toml files in the directories of the program are overridden
+                   by these options, and by // plint-allow: <lint> on the line before
+
+Options for cfg, ast and tokens:
+  --dot            Write the control-flow graphs or the syntax tree in the DOT language
+                   of Graphviz, instead of as text
+  --json           Write the syntax tree with its types, or the tokens, as JSON
+  --sexp           Write the syntax tree with its types, or the tokens, as S-expressions";
 
-    let filename = &args[1];
+/// A parsed command line.
+struct Command {
+    name: String,
+    options: compiler::Options,
+    filename: String,
+}
+
+/// CLI of the interpreter. Usage: plint [run | debug | lint | cfg | ast | tokens | fmt] [options] filename
+fn main() {
+    let command = match parse_args(env::args().skip(1).collect()) {
+        Ok(command) => command,
+        Err(e) => { println!("{}\n{}", e, USAGE); return; },
+    };
 
-    let mut f = File::open(filename).expect("file not found");
+    let mut f = File::open(&command.filename).expect("file not found");
 
     let mut source = String::new();
     f.read_to_string(&mut source)
         .expect("unable to read file");
 
-    compiler::run(source);
+    match command.name.as_str() {
+        "debug" => compiler::debug(source),
+        "lint" => compiler::lint(&command.filename, source, &command.options),
+        "cfg" => compiler::cfg(source, &command.options),
+        "ast" => compiler::ast(source, &command.options),
+        "tokens" => compiler::tokens(source, &command.options),
+        "fmt" => compiler::fmt(source, &command.options),
+        _ => compiler::run(&command.filename, source, &command.options),
+    }
+}
+
+/// Parse the command line arguments, excluding the program name.
+fn parse_args(mut args: Vec<String>) -> Result<Command, String> {
+    let name = match args.first().map(|arg| arg.as_str()) {
+        Some("run") | Some("debug") | Some("lint") | Some("cfg") | Some("ast") | Some("tokens")
+            | Some("fmt") => args.remove(0),
+        _ => "run".to_string(),
+    };
+
+    let mut options = compiler::Options::default();
+    let mut filename = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" => options.trace = Some(compiler::TraceFormat::Text),
+            "--trace=json" => options.trace = Some(compiler::TraceFormat::Json),
+            "--coverage" => options.coverage = true,
+            "--lcov" => options.lcov = Some(path_argument(&mut args, &arg)?),
+            "--profile" => options.profile = true,
+            "--profile-folded" => options.profile_folded = Some(path_argument(&mut args, &arg)?),
+            "--record" => options.record = Some(path_argument(&mut args, &arg)?),
+            "--replay" => options.replay = Some(path_argument(&mut args, &arg)?),
+            "--bigint" => options.bigint = true,
+            "--no-optimize" => options.no_optimize = true,
+            "--strict-init" => options.strict_init = true,
+            "--from-json" => options.from_json = true,
+            "--dot" => options.format = compiler::Format::Dot,
+            "--json" => options.format = compiler::Format::Json,
+            "--sexp" => options.format = compiler::Format::SExpression,
+            "--allow" | "--warn" | "--deny" => {
+                let lint = args.next().ok_or(format!("{} expects a lint", arg))?;
+                options.lint_levels.push((arg[2..].to_string(), lint));
+            },
+            "--max-depth" => match args.next().map(|n| n.parse()) {
+                Some(Ok(max_depth)) => options.max_depth = Some(max_depth),
+                _ => return Err(format!("{} expects a number", arg)),
+            },
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ if filename.is_none() => filename = Some(arg),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+
+    match filename {
+        Some(filename) => Ok(Command { name, options, filename }),
+        None => Err("No file given".to_string()),
+    }
+}
+
+/// Take the path following an option.
+fn path_argument<I: Iterator<Item = String>>(args: &mut I, option: &str) -> Result<String, String> {
+    args.next().ok_or(format!("{} expects a path", option))
 }
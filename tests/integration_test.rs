@@ -1,8 +1,392 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
 
+/// Run plint with the arguments, feeding the input to its standard input.
+fn plint_output(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_plint"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run plint");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Run plint with the arguments, feeding the input to its standard input. Returns the
+/// standard output.
+fn plint(args: &[&str], input: &str) -> String {
+    String::from_utf8_lossy(&plint_output(args, input).stdout).into_owned()
+}
 
 #[test]
 fn it_works() {
-    let source = include_str!("samples/simple.mpl");
+    assert_eq!(plint(&["tests/samples/simple.mpl"], ""), "16");
+}
+
+#[test]
+fn if_else_branches() {
+    assert_eq!(plint(&["tests/samples/branches.mpl"], ""), "10");
+}
+
+#[test]
+fn while_loop_with_break_and_continue() {
+    assert_eq!(plint(&["tests/samples/sum.mpl"], "5\n-2\n7\n0\n"), "312");
+}
+
+#[test]
+fn procedures_and_recursive_functions() {
+    assert_eq!(plint(&["tests/samples/functions.mpl"], ""), "Hello, World! Hello, World! 120");
+}
+
+#[test]
+fn call_depth_limit_with_stack_trace() {
+    assert_eq!(plint(&["--max-depth", "10", "tests/samples/countdown.mpl"], "9\n"), "0");
+    assert_eq!(plint(&["--max-depth", "10", "tests/samples/countdown.mpl"], "10\n"), "\
+        Runtime error: Call depth limit of 10 exceeded\n\
+        \x20 in down at 5:5\n\
+        \x20 [previous line repeated 9 more times]\n\
+        \x20 in the main program at 9:1\n");
+}
+
+#[test]
+fn declarations_are_local_to_their_block() {
+    assert_eq!(plint(&["tests/samples/scopes.mpl"], ""), "xx14");
+}
+
+#[test]
+fn arrays_with_bounds_checks() {
+    let output = plint(&["tests/samples/arrays.mpl"], "3\n14\n1\n5\n2\n");
+    assert_eq!(output, "[5, 1, 14, 3][true, false, true, true]1");
+
+    let output = plint(&["tests/samples/arrays.mpl"], "3\n14\n1\n5\n4\n");
+    assert!(output.ends_with("Runtime error: Index 4 is out of bounds for array numbers of size 4 at 16:1\n"));
+}
+
+#[test]
+fn arrays_too_large_for_memory_are_a_runtime_error() {
+    let output = plint(&["tests/samples/huge_array.mpl"], "");
+    assert_eq!(output, "start Runtime error: 2:1: Not enough memory for an array of 2000000000 elements\n");
+}
+
+#[test]
+fn comparison_and_logical_operators_short_circuit() {
+    let output = plint(&["tests/samples/operators.mpl"], "");
+    assert_eq!(output, "[1, 2, 3]0falsetruefalsetrueevaluated true");
+}
+
+#[test]
+fn failed_assertions_show_the_values_of_their_operands() {
+    assert_eq!(plint(&["tests/samples/assertions.mpl"], ""), "\
+        Assertion a[0] = 2 (1 = 2) was false\n\
+        Assertion ! (i > 2) (! true) was false\n\
+        Assertion (i < 2) & (a[i] = 0) (false & ?) was false\n\
+        side Assertion f() = 2 (1 = 2) was false\n\
+        done");
+}
+
+#[test]
+fn builtin_string_functions() {
+    let output = plint(&["tests/samples/strings.mpl"], "mARY-ann\n12\n");
+    assert_eq!(output, "Mary-ann 4 true");
+
+    let output = plint(&["tests/samples/strings.mpl"], "x\n1x\n");
+    assert_eq!(output, "X -1 Runtime error: string_to_int cannot convert \"1x\": not an integer\n");
+}
+
+#[test]
+fn real_arithmetic_and_conversions() {
+    let output = plint(&["tests/samples/reals.mpl"], "2\n2\n6e0\n");
+    assert_eq!(output, "4.0 2.000000000000002 2");
+
+    let output = plint(&["tests/samples/reals.mpl"], "3\n1.5\n4\n10.5\n");
+    assert_eq!(output, "5.333333333333333 2.309401076758503 2");
+}
+
+#[test]
+fn integer_overflow_is_an_error_without_bigint() {
+    let output = plint(&["tests/samples/number.mpl"], "12\n");
+    assert_eq!(output, "Give a numberThe result is: 479001600");
+
+    let output = plint(&["tests/samples/number.mpl"], "13\n");
+    assert_eq!(output, "Give a numberRuntime error: Integer overflow: 479001600 * 13 does not fit in 32 bits\n");
+
+    let output = plint(&["--bigint", "tests/samples/number.mpl"], "25\n");
+    assert_eq!(output, "Give a numberThe result is: 15511210043330985984000000");
+}
+
+#[test]
+fn conversions_to_integers_are_unbounded_with_bigint() {
+    let output = plint(&["--bigint", "tests/samples/big_conversions.mpl"], "99999999999\n");
+    assert_eq!(output, "100000000000 100000000000000000000");
+
+    let output = plint(&["tests/samples/big_conversions.mpl"], "99999999999\n");
+    assert_eq!(output, "Runtime error: Integer overflow: the result 99999999999 of string_to_int does not fit in 32 bits\n");
+}
+
+#[test]
+fn for_loops_with_step_and_downto() {
+    let output = plint(&["tests/samples/steps.mpl"], "3\n");
+    assert_eq!(output, "10 7 4 1 -2 0246810 12 3 14");
+
+    let output = plint(&["tests/samples/steps.mpl"], "0\n");
+    assert!(output.ends_with(" 3 Runtime error: 21:1: Step of for loop must be positive, got 0\n"));
+}
+
+#[test]
+fn control_variable_is_past_the_end_after_the_loop() {
+    let output = plint(&["tests/samples/ntimes.mpl"], "2\n");
+    assert_eq!(output, "How many times?0 : Hello, World!\n1 : Hello, World!\n");
+}
+
+#[test]
+fn for_loops_reach_the_largest_and_smallest_integers() {
+    let output = plint(&["--bigint", "tests/samples/extreme_ranges.mpl"], "");
+    assert_eq!(output, "2147483648 2147483650 -2147483649 -2147483663 24");
+    let output = plint(&["tests/samples/extreme_ranges.mpl"], "");
+    assert_eq!(output, "Runtime error: 6:1: Integer overflow: i is 2147483648 after the loop, which does not fit in 32 bits\n");
+}
+
+#[test]
+fn constants_are_evaluated_at_compile_time() {
+    assert_eq!(plint(&["tests/samples/constants.mpl"], ""), "HI![0, 1, 4]");
+}
+
+#[test]
+fn optimized_programs_behave_as_written() {
+    let expected = "48 5 true Runtime error: Division by zero\n";
+    assert_eq!(plint(&["tests/samples/optimized.mpl"], ""), expected);
+    assert_eq!(plint(&["--no-optimize", "tests/samples/optimized.mpl"], ""), expected);
+}
+
+#[test]
+fn lints_are_reported_unless_allowed() {
+    assert_eq!(plint(&["lint", "tests/samples/lints.mpl"], ""), "\
+        1:1-1:20: warning: Variable unused is never used [unused-variable]\n\
+        3:1-3:7: warning: Value read into n is overwritten before it is used [overwritten-read]\n\
+        5:1-5:21: warning: Initial value of total is never used [dead-store]\n\
+        7:1-7:15: warning: total is assigned to itself [self-assignment]\n\
+        8:1-8:15: warning: Assertion 1 < 2 does not depend on the variables of the program [constant-assert]\n");
+    let allowed = ["lint", "--allow", "unused-variable", "--allow", "constant-assert", "tests/samples/lints.mpl"];
+    assert_eq!(plint(&allowed, ""), "\
+        3:1-3:7: warning: Value read into n is overwritten before it is used [overwritten-read]\n\
+        5:1-5:21: warning: Initial value of total is never used [dead-store]\n\
+        7:1-7:15: warning: total is assigned to itself [self-assignment]\n");
+    assert_eq!(plint(&["lint", "--allow", "unused", "tests/samples/lints.mpl"], ""), "Unknown lint unused\n");
+}
+
+#[test]
+fn uninitialized_variables_warn_or_fail_with_strict_init() {
+    let sample = "tests/samples/uninitialized.mpl";
+    assert_eq!(plint(&[sample], "3\n"), "6");
+    assert_eq!(plint(&["lint", sample], ""), "\
+        6:5-6:23: warning: Variable total may be used before it is assigned [uninitialized]\n\
+        8:1-8:12: warning: Variable total may be used before it is assigned [uninitialized]\n");
+    assert_eq!(plint(&["--strict-init", sample], "3\n"), "Initialization check failed:\n\
+        6:5: Variable total may be used before it is assigned\n\
+        8:1: Variable total may be used before it is assigned\n");
+    assert_eq!(plint(&["--strict-init", "tests/samples/steps.mpl"], "3\n"), "10 7 4 1 -2 0246810 12 3 14");
+}
+
+#[test]
+fn lint_levels_from_config_files_options_and_comments() {
+    let strict = "tests/samples/config/stores.mpl";
+    let relaxed = "tests/samples/config/relaxed/stores.mpl";
+    assert_eq!(plint(&["lint", strict], ""), "2:1-2:17: error: Initial value of x is never used [dead-store]\n");
+    assert_eq!(plint(&[strict], ""), "Lint check failed:\n2:1: Initial value of x is never used [dead-store]\n");
+    assert_eq!(plint(&["--allow", "dead-store", strict], ""), "3");
+    assert_eq!(plint(&["lint", relaxed], ""), "2:1-2:17: warning: Initial value of x is never used [dead-store]\n");
+    assert_eq!(plint(&[relaxed], ""), "3");
+    assert_eq!(plint(&["lint", "--deny", "unused-variable", relaxed], ""), "\
+        1:1-1:17: error: Variable unused is never used [unused-variable]\n\
+        2:1-2:17: warning: Initial value of x is never used [dead-store]\n");
+}
+
+#[test]
+fn problems_in_the_lint_configuration_are_warnings() {
+    let sample = "tests/samples/config/shared/misspelled.mpl";
+    let output = plint_output(&[sample], "");
+    let warnings = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1");
+    assert!(warnings.starts_with("warning: "));
+    assert!(warnings.ends_with("shared/plint.toml:6: Unknown level forbid\nwarning: 2:1: Unknown lint dead-stores\n"));
+
+    let output = plint(&["lint", sample], "");
+    assert!(output.ends_with("shared/plint.toml:6: Unknown level forbid\nwarning: 2:1: Unknown lint dead-stores\n"));
+}
+
+#[test]
+fn type_errors_are_reported_before_running() {
+    assert_eq!(plint(&["tests/samples/ill_typed.mpl"], ""), "Type checking failed:\n\
+        1:1: Initial value must be Integer, not String\n\
+        2:1: Condition of if must be Boolean, not Integer\n\
+        3:5: Identifier y used before declaration\n");
+}
+
+#[test]
+fn debugger_breakpoint_in_loop() {
+    let commands = "break 7\ncontinue\n3\nprint v\nwhere\nset v := 10\ncontinue\ncontinue\n";
+    let output = plint(&["debug", "tests/samples/number.mpl"], commands);
+
+    assert!(output.contains("Stopped at line 7: v := v * i;\n  for i: iteration 1 (i = 1)"));
+    assert!(output.contains("v = 1\n"));
+    assert!(output.contains("for i: iteration 3 (i = 3)"));
+    assert!(output.contains("The result is: 60"));
+    assert!(output.ends_with("Program finished\n"));
+}
+
+#[test]
+fn debugger_next_steps_over_loop() {
+    let commands = "next\nnext\nnext\n2\nnext\nnext\nnext\nvars\nquit\n";
+    let output = plint(&["debug", "tests/samples/number.mpl"], commands);
+
+    assert!(!output.contains("Stopped at line 7"));
+    assert!(output.contains("Stopped at line 9: print \"The result is: \";"));
+    assert!(output.contains("  v : Integer = 2\n"));
+    assert!(output.contains("Program stopped by the debugger"));
+}
+
+#[test]
+fn trace_as_json_lines() {
+    let output = plint_output(&["run", "--trace=json", "tests/samples/number.mpl"], "3\n");
+    let trace = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = trace.lines().collect();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Give a numberThe result is: 6");
+    assert!(lines.contains(&r#"{"event":"binding","identifier":"n","old":0,"new":3}"#));
+    assert!(lines.contains(&r#"{"event":"iteration","identifier":"i","iteration":3}"#));
+    assert!(lines.contains(
+        &r#"{"event":"statement","line":7,"column":5,"source":"v := v * i;","operands":{"v":2,"i":3},"loops":[{"identifier":"i","iteration":3}]}"#
+    ));
+    assert!(lines.iter().all(|line| line.starts_with("{\"event\":")));
+}
+
+#[test]
+fn coverage_annotates_source() {
+    let output = plint_output(&["run", "--coverage", "tests/samples/ntimes.mpl"], "0\n");
+    let report = String::from_utf8_lossy(&output.stderr);
+
+    assert!(report.contains("        1:    6:for x in 0..nTimes-1 do    [for: 0 iterations 1x, 1+ iterations 0x]\n"));
+    assert!(report.contains("    #####:    7:    print x;\n"));
+    assert!(report.contains("        -:    9:end for;\n"));
+    assert!(report.contains("        1:   10:assert (x = nTimes);    [assert: true 1x, false 0x]\n"));
+}
+
+#[test]
+fn coverage_as_lcov() {
+    let path = std::env::temp_dir().join(format!("plint-{}.lcov", std::process::id()));
+    let path = path.to_str().unwrap();
+    plint(&["run", "--lcov", path, "tests/samples/ntimes.mpl"], "2\n");
+    let report = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(report.starts_with("TN:\nSF:tests/samples/ntimes.mpl\n"));
+    assert!(report.contains("BRDA:6,0,0,0\nBRDA:6,0,1,1\n"));
+    assert!(report.contains("DA:7,2\n"));
+    assert!(report.contains("LF:8\nLH:8\nend_of_record\n"));
+}
+
+#[test]
+fn profile_counts_and_folded_stacks() {
+    let path = std::env::temp_dir().join(format!("plint-{}.folded", std::process::id()));
+    let path = path.to_str().unwrap();
+    let output = plint_output(&["run", "--profile-folded", path, "tests/samples/nested.mpl"], "");
+    let folded = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let report = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "216225");
+    let row = report.lines().find(|line| line.ends_with("s := s + (i * j);")).unwrap();
+    assert_eq!(row.split_whitespace().take(2).collect::<Vec<_>>(), ["7:9", "900"]);
+    let stacks: Vec<&str> = folded.lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert!(stacks.contains(&"program;for i 5:1;for j 6:5;7:9 s := s + (i * j)"));
+    assert!(stacks.contains(&"program;for i 5:1;for j 6:5"));
+}
+
+#[test]
+fn record_and_replay_input() {
+    let path = std::env::temp_dir().join(format!("plint-{}.log", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let recorded = plint(&["--record", path, "tests/samples/number.mpl"], "5\n");
+    let log = std::fs::read_to_string(path).unwrap();
+    let replayed = plint(&["--replay", path, "tests/samples/number.mpl"], "");
+    let mismatch = plint(&["--replay", path, "tests/samples/ntimes.mpl"], "");
+    let unread = plint(&["--replay", path, "tests/samples/simple.mpl"], "");
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(log, "# plint session log\n3:1\tn\t5\n");
+    assert_eq!(recorded, "Give a numberThe result is: 120");
+    assert_eq!(replayed, recorded);
+    assert!(mismatch.ends_with(&format!("Replay failed: read nTimes at 3:1, but {} recorded read n at 3:1\n", path)));
+    assert!(unread.contains("recorded 1 more reads, the next one of n at 3:1"));
+}
+
+#[test]
+fn control_flow_graphs_and_syntax_trees() {
+    let cfg = plint(&["cfg", "tests/samples/branches.mpl"], "");
+    let dot = plint(&["cfg", "--dot", "tests/samples/branches.mpl"], "");
+    let ast = plint(&["ast", "tests/samples/branches.mpl"], "");
+    let ast_dot = plint(&["ast", "--dot", "tests/samples/branches.mpl"], "");
+
+    assert!(cfg.starts_with("program\n  B0 (entry)\n    var i : int\n"));
+    assert!(cfg.contains("  B2\n    i <= 10\n    -> B3 [true], B5 [false]\n"));
+    assert!(cfg.contains("  B4\n    i := i + 1\n    -> B2 [back]\n"));
+    assert!(dot.starts_with("digraph cfg {\n"));
+    assert!(dot.contains("    \"program.4\" -> \"program.2\" [label=\"back\", style=dashed];\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(ast.starts_with("Program\n  Declaration i : Integer at 1:1\n  Declaration evens : Integer at 2:1\n    Int 0\n"));
+    assert!(ast.contains("  Assert at 11:1\n    Binary =\n      Identifier evens\n      Int 5\n"));
+    assert!(ast_dot.starts_with("digraph ast {\n"));
+    assert!(ast_dot.contains("  n0 -> n1;\n"));
+}
+
+#[test]
+fn formatted_programs_run_as_written() {
+    let path = std::env::temp_dir().join(format!("plint-fmt-{}.mpl", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    assert_eq!(plint(&["fmt", "tests/samples/branches.mpl"], ""), std::fs::read_to_string("tests/samples/branches.mpl").unwrap());
+    let formatted = plint(&["fmt", "tests/samples/functions.mpl"], "");
+    assert!(formatted.contains("procedure greet(name : string, times : int) do\n    var i : int;\n    for i in 1..times do\n"));
+    std::fs::write(path, &formatted).unwrap();
+    let output = plint(&[path], "");
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(output, plint(&["tests/samples/functions.mpl"], ""));
+}
+
+#[test]
+fn programs_serialised_as_json_run_as_written() {
+    let path = std::env::temp_dir().join(format!("plint-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let json = plint(&["ast", "--json", "tests/samples/functions.mpl"], "");
+    std::fs::write(path, &json).unwrap();
+    let output = plint(&["--from-json", path], "");
+    let reserialised = plint(&["ast", "--json", "--from-json", path], "");
+    let traced = plint(&["--trace", "--from-json", path], "");
+    std::fs::write(path, "{\"statements\": [{\"kind\": \"print\", \"value\": {\"kind\": \"identifier\", \"identifier\": \"x\"}}]}").unwrap();
+    let undeclared = plint(&["--from-json", path], "");
+    std::fs::remove_file(path).unwrap();
+
+    assert!(json.starts_with("{\"format\":\"plint-ast\",\"version\":1,\"statements\":[{\"kind\":\"function\""));
+    assert_eq!(output, plint(&["tests/samples/functions.mpl"], ""));
+    assert_eq!(reserialised, json);
+    assert_eq!(traced, "Cannot trace, cover or profile a program read from JSON, which has no source\n");
+    assert_eq!(undeclared, "Type checking failed:\n0:0: Identifier x used before declaration\n");
+}
+
+#[test]
+fn syntax_trees_and_tokens_as_s_expressions() {
+    let ast = plint(&["ast", "--sexp", "tests/samples/functions.mpl"], "");
+    let tokens = plint(&["tokens", "--sexp", "tests/samples/functions.mpl"], "");
+    let json_tokens = plint(&["tokens", "--json", "tests/samples/functions.mpl"], "");
 
-    assert!(true);
+    assert!(ast.starts_with("(program\n  (function 1:1-6:13 factorial ((n int)) int\n    (do\n"));
+    assert!(ast.contains("\n      (return 5:5-5:32 (the int (* n (call factorial (the int (- n 1))))))))\n"));
+    assert!(tokens.starts_with("(tokens\n  (keyword 1:1 function)\n  (identifier 1:10 factorial)\n  (symbol 1:19 \"(\")\n"));
+    assert!(json_tokens.starts_with("{\"format\":\"plint-tokens\",\"version\":1,\"tokens\":[{\"kind\":\"keyword\",\"line\":1,\"column\":1,\"text\":\"function\"}"));
 }